@@ -50,6 +50,22 @@ def test_validator():
     validator.validate_number_range("age", "15", 18, 100)
     assert validator.has_errors()
 
+def test_validator_keeps_every_error_of_a_field():
+    """Test that errors of one field are accumulated, not overwritten."""
+    validator = Validator()
+    validator.check(False, "password", "Too short")
+    validator.check(False, "password", "No digits")
+    validator.check(True, "username", "Too short")
+    assert not validator.is_valid()
+    assert validator.all_errors() == [
+        ValidationError("password", "Too short"),
+        ValidationError("password", "No digits"),
+    ]
+    assert validator.get_errors() == {"password": "Too short; No digits"}
+    with pytest.raises(ValueError) as exc_info:
+        validator.into_result()
+    assert exc_info.value.errors == validator.all_errors()
+
 def test_validate_email():
     """Test email validation."""
     assert validate_email("test@example.com")
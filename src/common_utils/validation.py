@@ -1,30 +1,56 @@
 import re
-from typing import Dict, Optional, List
+from typing import Dict, Optional, List, Type
 from dataclasses import dataclass
 
 @dataclass
 class ValidationError:
-    """Ошибка валидации"""
+    """Ошибка валидации одного поля"""
     field: str
     message: str
 
 class Validator:
-    """Валидатор полей"""
+    """Валидатор полей; у одного поля может накопиться несколько ошибок"""
     
     def __init__(self):
-        self.errors: Dict[str, str] = {}
+        self.errors: Dict[str, List[ValidationError]] = {}
         
     def add_error(self, field: str, message: str) -> None:
         """Добавить ошибку"""
-        self.errors[field] = message
+        self.errors.setdefault(field, []).append(ValidationError(field, message))
+        
+    def check(self, condition: bool, field: str, message: str) -> bool:
+        """Добавить ошибку, если условие не выполнено"""
+        if not condition:
+            self.add_error(field, message)
+        return condition
         
     def has_errors(self) -> bool:
         """Проверить наличие ошибок"""
         return bool(self.errors)
         
+    def is_valid(self) -> bool:
+        """Проверить отсутствие ошибок"""
+        return not self.errors
+        
+    def all_errors(self) -> List[ValidationError]:
+        """Получить все ошибки в порядке добавления по полям"""
+        return [error for errors in self.errors.values() for error in errors]
+        
     def get_errors(self) -> Dict[str, str]:
-        """Получить все ошибки"""
-        return self.errors
+        """Получить сообщения об ошибках по полям; несколько ошибок поля объединяются через '; '"""
+        return {
+            field: "; ".join(error.message for error in errors)
+            for field, errors in self.errors.items()
+        }
+        
+    def into_result(self, error_type: Type[Exception] = ValueError) -> None:
+        """Выбросить error_type со всеми накопленными ошибками в поле errors, если они есть"""
+        if self.is_valid():
+            return
+        errors = self.all_errors()
+        error = error_type("; ".join(f"{e.field}: {e.message}" for e in errors))
+        error.errors = errors
+        raise error
         
     def validate_min_length(self, field: str, value: str, min_length: int, message: Optional[str] = None) -> 'Validator':
         """Проверить минимальную длину"""
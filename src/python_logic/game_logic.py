@@ -14,7 +14,7 @@ to the specialized C++ component.
 """
 
 import numpy as np
-from typing import Dict, List, Tuple, Optional, Any, Union, Callable
+from typing import Dict, List, Tuple, Optional, Any, Union, Callable, Sequence, TypeVar
 import json
 import uuid
 import time
@@ -26,13 +26,33 @@ import ctypes
 import os
 import sys
 from enum import Enum, auto
-from dataclasses import dataclass, field
+from dataclasses import dataclass, field, replace
 from abc import ABC, abstractmethod
 from pydantic import BaseModel, Field
 
 # Configure logging
 logger.add("tetris_towers_logic.log", rotation="1 day", retention="7 days")
 
+T = TypeVar("T")
+
+
+def weighted_choice(items: Sequence[Tuple[T, float]], rng: Optional[random.Random] = None) -> Optional[T]:
+    """Pick an item with probability proportional to its weight.
+    
+    Items with zero or negative weight are never picked. Returns None if nothing
+    can be picked. Pass a seeded ``random.Random`` for reproducible draws.
+    """
+    candidates = [(item, weight) for item, weight in items if weight > 0]
+    if not candidates:
+        return None
+    roll = (rng or random).uniform(0.0, sum(weight for _, weight in candidates))
+    for item, weight in candidates:
+        roll -= weight
+        if roll < 0:
+            return item
+    # Rounding can leave roll exactly at the upper bound
+    return candidates[-1][0]
+
 # Game constants
 class GameConstants:
     """Constants used throughout the game logic."""
@@ -478,6 +498,17 @@ class Spell:
     mana_cost: int
     description: str
     icon_path: str
+    stackable: bool = True  # whether a second cast may overlap an active one
+    
+    @property
+    def requires_target(self) -> bool:
+        """Whether the spell must be aimed at another player."""
+        return self.target_type == "opponent"
+    
+    @property
+    def is_self_only(self) -> bool:
+        """Whether the spell can only affect the caster."""
+        return self.target_type == "self"
     
     def to_dict(self) -> Dict[str, Any]:
         """Convert the spell to a dictionary for serialization."""
@@ -492,7 +523,8 @@ class Spell:
             "cooldown": self.cooldown,
             "mana_cost": self.mana_cost,
             "description": self.description,
-            "icon_path": self.icon_path
+            "icon_path": self.icon_path,
+            "stackable": self.stackable
         }
     
     @classmethod
@@ -509,7 +541,8 @@ class Spell:
             cooldown=data["cooldown"],
             mana_cost=data["mana_cost"],
             description=data["description"],
-            icon_path=data["icon_path"]
+            icon_path=data["icon_path"],
+            stackable=data.get("stackable", True)
         )
 
 
@@ -571,6 +604,7 @@ class Player:
     max_mana: int = 100
     spells: List[Spell] = field(default_factory=list)
     active_spells: List[ActiveSpell] = field(default_factory=list)
+    spell_cooldowns: Dict[str, float] = field(default_factory=dict)  # spell id -> time its cooldown ends
     current_block: Optional[Block] = None
     next_blocks: List[Block] = field(default_factory=list)
     blocks_placed: int = 0
@@ -606,12 +640,49 @@ class Player:
         """Add a spell to the player's collection."""
         self.spells.append(spell)
     
+    def has_active_spell(self, effect: str, current_time: float) -> bool:
+        """Whether a spell with the given effect is still active for this player."""
+        self.update_active_spells(current_time)
+        return any(active.spell.effect == effect for active in self.active_spells)
+    
+    def active_spell_remaining(self, spell_id: str, current_time: float) -> Optional[float]:
+        """Seconds left on the active spell with the given id, or None if it is not active."""
+        self.update_active_spells(current_time)
+        active = next((a for a in self.active_spells if a.spell.id == spell_id), None)
+        return active.remaining_time(current_time) if active else None
+    
+    def is_on_cooldown(self, spell_id: str, current_time: float) -> bool:
+        """Whether the spell was cast too recently to be cast again."""
+        return self.spell_cooldowns.get(spell_id, 0.0) > current_time
+    
+    def affordable_spells(self, current_time: float) -> List[str]:
+        """Ids of the spells cast_spell would accept right now.
+        
+        A spell needs enough mana and must be off cooldown; a non-stackable one also
+        needs its effect to have worn off.
+        """
+        return [
+            spell.id for spell in self.spells
+            if spell.mana_cost <= self.mana
+            and not self.is_on_cooldown(spell.id, current_time)
+            and (spell.stackable or not self.has_active_spell(spell.effect, current_time))
+        ]
+    
     def cast_spell(self, spell_id: str, target_id: str, current_time: float) -> Optional[ActiveSpell]:
-        """Cast a spell if the player has it and enough mana."""
+        """Cast a spell if the player has it, enough mana and it is off cooldown.
+        
+        Non-stackable spells cannot be cast again while the same effect is active.
+        """
         spell = next((s for s in self.spells if s.id == spell_id), None)
         if not spell:
             return None
         
+        if self.is_on_cooldown(spell_id, current_time):
+            return None
+        
+        if not spell.stackable and self.has_active_spell(spell.effect, current_time):
+            return None
+        
         if not self.use_mana(spell.mana_cost):
             return None
         
@@ -624,6 +695,7 @@ class Player:
         )
         
         self.active_spells.append(active_spell)
+        self.spell_cooldowns[spell.id] = current_time + spell.cooldown
         return active_spell
     
     def update_active_spells(self, current_time: float) -> None:
@@ -647,6 +719,7 @@ class Player:
             "max_mana": self.max_mana,
             "spells": [spell.to_dict() for spell in self.spells],
             "active_spells": [spell.to_dict() for spell in self.active_spells],
+            "spell_cooldowns": self.spell_cooldowns,
             "current_block": self.current_block.to_dict() if self.current_block else None,
             "next_blocks": [block.to_dict() for block in self.next_blocks],
             "blocks_placed": self.blocks_placed,
@@ -670,6 +743,7 @@ class Player:
             max_mana=data["max_mana"],
             spells=[Spell.from_dict(spell_data) for spell_data in data["spells"]],
             active_spells=[ActiveSpell.from_dict(spell_data) for spell_data in data["active_spells"]],
+            spell_cooldowns=data.get("spell_cooldowns", {}),
             blocks_placed=data["blocks_placed"],
             is_ai=data["is_ai"],
             ai_difficulty=data["ai_difficulty"],
@@ -689,7 +763,7 @@ class GameBoard:
     """Represents the game board where blocks are placed."""
     width: int
     height: int
-    cells: List[List[Optional[int]]]  # Grid of block IDs (None for empty)
+    cells: List[List[Optional[int]]] = field(init=False)  # Grid of block IDs (None for empty)
     blocks: Dict[int, Block] = field(default_factory=dict)
     
     def __post_init__(self):
@@ -832,7 +906,8 @@ class SpellFactory:
                 cooldown=30.0,
                 mana_cost=30,
                 description="Strengthens your blocks, making them more stable.",
-                icon_path="assets/spells/strengthen.png"
+                icon_path="assets/spells/strengthen.png",
+                stackable=False
             ),
             Spell(
                 id=str(uuid.uuid4()),
@@ -845,7 +920,8 @@ class SpellFactory:
                 cooldown=25.0,
                 mana_cost=25,
                 description="Makes your blocks lighter, reducing their impact when falling.",
-                icon_path="assets/spells/lighten.png"
+                icon_path="assets/spells/lighten.png",
+                stackable=False
             ),
             Spell(
                 id=str(uuid.uuid4()),
@@ -858,7 +934,8 @@ class SpellFactory:
                 cooldown=60.0,
                 mana_cost=50,
                 description="Doubles the points you earn for a short time.",
-                icon_path="assets/spells/multiply.png"
+                icon_path="assets/spells/multiply.png",
+                stackable=False
             ),
             Spell(
                 id=str(uuid.uuid4()),
@@ -937,6 +1014,15 @@ class SpellFactory:
     def create_all_spells() -> List[Spell]:
         """Create all available spells."""
         return SpellFactory.create_light_spells() + SpellFactory.create_dark_spells()
+    
+    @staticmethod
+    def create_spells(effects: List[str]) -> List[Spell]:
+        """Create fresh spells for the given effects, in order; duplicates are allowed."""
+        registry = {spell.effect: spell for spell in SpellFactory.create_all_spells()}
+        unknown = [effect for effect in effects if effect not in registry]
+        if unknown:
+            raise ValueError(f"Unknown spells: {', '.join(unknown)}")
+        return [replace(registry[effect], id=str(uuid.uuid4())) for effect in effects]
 
 
 class BlockFactory:
@@ -949,9 +1035,10 @@ class BlockFactory:
         """Create a new block of the specified type."""
         if block_type is None:
             # Randomly select a block type, with SPECIAL being less common
-            weights = [1, 1, 1, 1, 1, 1, 1, 0.3]  # Lower weight for SPECIAL
-            block_types = list(BlockType)
-            block_type = random.choices(block_types, weights=weights, k=1)[0]
+            block_type = weighted_choice([
+                (candidate, 0.3 if candidate == BlockType.SPECIAL else 1.0)
+                for candidate in BlockType
+            ])
         
         shape = BlockShape.create(block_type)
         
@@ -980,6 +1067,37 @@ class BlockFactory:
 class PhysicsEngine:
     """Интерфейс для работы с C++ физическим движком."""
     
+    # Переменная окружения с явным путём к библиотеке; отменяет поиск по умолчанию
+    LIBRARY_PATH_ENV = "PHYSICS_LIBRARY_PATH"
+    LIBRARY_DIRS = (".", "./build")
+    
+    @staticmethod
+    def default_library_name() -> str:
+        """Имя библиотеки физики с расширением, принятым на текущей платформе."""
+        if sys.platform == "win32":
+            return "physics.dll"
+        if sys.platform == "darwin":
+            return "libphysics.dylib"
+        return "libphysics.so"
+    
+    @classmethod
+    def library_candidates(cls) -> List[str]:
+        """Пути, по которым ищется библиотека, в порядке приоритета."""
+        override = os.getenv(cls.LIBRARY_PATH_ENV)
+        if override:
+            return [override]
+        name = cls.default_library_name()
+        return [os.path.join(directory, name) for directory in cls.LIBRARY_DIRS]
+    
+    @classmethod
+    def resolve_library_path(cls) -> str:
+        """Первый существующий путь к библиотеке физики."""
+        candidates = cls.library_candidates()
+        for path in candidates:
+            if os.path.isfile(path):
+                return path
+        raise RuntimeError(f"Physics library not found, tried: {', '.join(candidates)}")
+    
     def __init__(self):
         self._lib = None
         self._initialized = False
@@ -1021,15 +1139,12 @@ class PhysicsEngine:
                 ]
             
             # Загрузка библиотеки
+            library_path = self.resolve_library_path()
             try:
-                self._lib = ctypes.CDLL("./libphysics.so")
-            except OSError:
-                # Попытка загрузить библиотеку из альтернативного пути
-                try:
-                    self._lib = ctypes.CDLL("./build/libphysics.so")
-                except OSError as e:
-                    logger.error(f"Failed to load physics library: {e}")
-                    raise RuntimeError("Physics library not found")
+                self._lib = ctypes.CDLL(library_path)
+            except OSError as e:
+                logger.error(f"Failed to load physics library {library_path}: {e}")
+                raise RuntimeError(f"Physics library {library_path} could not be loaded")
             
             # Настройка типов возвращаемых значений
             self._lib.init_physics.restype = ctypes.c_bool
@@ -1283,10 +1398,28 @@ class PhysicsEngine:
 class GameManager:
     """Manages the game state and logic."""
     
-    def __init__(self, game_mode: GameMode = GameMode.SURVIVAL):
-        """Initialize the game manager."""
+    def __init__(
+        self,
+        game_mode: GameMode = GameMode.SURVIVAL,
+        starting_score: int = 0,
+        starting_spells: Optional[List[str]] = None
+    ):
+        """Initialize the game manager.
+        
+        starting_spells lists spell effects every new player receives; when it is None,
+        players are granted a random light or dark set instead.
+        """
+        if starting_score < 0:
+            raise ValueError("Starting score cannot be negative")
+        if starting_spells is not None:
+            if len(starting_spells) > GameConstants.MAX_SPELLS:
+                raise ValueError(f"Starting loadout cannot have more than {GameConstants.MAX_SPELLS} spells")
+            # Fail on unknown effects now rather than when the first player joins
+            SpellFactory.create_spells(starting_spells)
         self.game_id = str(uuid.uuid4())
         self.game_mode = game_mode
+        self.starting_score = starting_score
+        self.starting_spells = starting_spells
         self.game_state = GameState.INITIALIZING
         self.players: Dict[str, Player] = {}
         self.boards: Dict[str, GameBoard] = {}
@@ -1341,17 +1474,20 @@ class GameManager:
             player = Player(
                 id=player_id,
                 name=name,
+                score=self.starting_score,
                 is_ai=is_ai,
                 ai_difficulty=ai_difficulty
             )
             
-            # Add some initial spells
-            if random.random() < 0.5:
-                # Give light spells
-                player.spells = SpellFactory.create_light_spells()
+            if self.starting_spells is not None:
+                player.spells = SpellFactory.create_spells(self.starting_spells)
             else:
-                # Give dark spells
-                player.spells = SpellFactory.create_dark_spells()
+                # Add some initial spells: either the light or the dark set, equally likely
+                grant = weighted_choice([
+                    (SpellFactory.create_light_spells, 1.0),
+                    (SpellFactory.create_dark_spells, 1.0)
+                ])
+                player.spells = grant()
             
             # Create a game board for this player
             board = GameBoard(
@@ -1764,20 +1900,34 @@ class GameManager:
         
         return base_score + combo_bonus
     
+    def resolve_race_winner(self) -> Optional[str]:
+        """Pick the race winner among all players who have reached the top.
+        
+        Simultaneous finishers are ranked by higher score, then fewer blocks placed,
+        then earliest join, so the result never depends on board iteration order.
+        """
+        join_order = {player_id: index for index, player_id in enumerate(self.players)}
+        finishers = []
+        for player_id, board in self.boards.items():
+            player = self.players.get(player_id)
+            # Arbitrary threshold for "reaching the top"
+            if player and player.state == PlayerState.PLAYING and board.get_highest_block_position() <= 5:
+                finishers.append(player)
+        if not finishers:
+            return None
+        winner = min(finishers, key=lambda p: (-p.score, p.blocks_placed, join_order[p.id]))
+        return winner.id
+    
     def _check_victory_conditions(self) -> None:
         """Check for victory conditions based on the game mode."""
         if self.game_state != GameState.RUNNING:
             return
         
         if self.game_mode == GameMode.RACE:
-            # Check if any player has reached the top
-            for player_id, board in self.boards.items():
-                if board.get_highest_block_position() <= 5:  # Arbitrary threshold for "reaching the top"
-                    player = self.players.get(player_id)
-                    if player and player.state == PlayerState.PLAYING:
-                        player.state = PlayerState.VICTORIOUS
-                        self.game_state = GameState.VICTORY
-                        return
+            winner_id = self.resolve_race_winner()
+            if winner_id:
+                self.players[winner_id].state = PlayerState.VICTORIOUS
+                self.game_state = GameState.VICTORY
         
         elif self.game_mode == GameMode.SURVIVAL:
             # Check if only one player is left
@@ -1826,10 +1976,9 @@ class GameManager:
                     if p_id != player_id and self.players[p_id].state == PlayerState.PLAYING
                 ]
                 
-                if opponents:
-                    target_id = random.choice(opponents)
-                else:
-                    target_id = player_id
+                if not opponents:
+                    return
+                target_id = random.choice(opponents)
             
             self.cast_spell(player_id, spell.id, target_id)
     
@@ -2089,22 +2238,43 @@ class GameManager:
             
             return False
     
-    def cast_spell(self, caster_id: str, spell_id: str, target_id: str) -> bool:
-        """Cast a spell."""
+    def cast_spell(self, caster_id: str, spell_id: str, target_id: Optional[str] = None) -> bool:
+        """Cast a spell; returns False if it cannot be cast.
+        
+        The target must match the spell: targeted spells need another player as a
+        target, self-only spells cannot target anyone else. Mana is only spent once
+        the target has been validated.
+        """
         with self.lock:
             caster = self.players.get(caster_id)
             if not caster or caster.state != PlayerState.PLAYING:
                 return False
             
-            target = self.players.get(target_id)
-            if not target or target.state != PlayerState.PLAYING:
-                return False
-            
             # Find the spell
             spell = next((s for s in caster.spells if s.id == spell_id), None)
             if not spell:
                 return False
             
+            # Validate the target against the spell's declared target type
+            if spell.requires_target:
+                if not target_id:
+                    logger.warning(f"Spell '{spell.name}' requires a target")
+                    return False
+                if target_id == caster_id:
+                    logger.warning(f"Spell '{spell.name}' cannot target the caster")
+                    return False
+            elif spell.is_self_only:
+                if target_id is not None and target_id != caster_id:
+                    logger.warning(f"Spell '{spell.name}' can only target the caster")
+                    return False
+                target_id = caster_id
+            elif target_id is None:
+                target_id = caster_id
+            
+            target = self.players.get(target_id)
+            if not target or target.state != PlayerState.PLAYING:
+                return False
+            
             # Check if the caster has enough mana
             if caster.mana < spell.mana_cost:
                 return False
@@ -2160,8 +2330,20 @@ class GameManager:
             
             return {
                 "player": player.to_dict(),
-                "board": board.to_dict()
+                "board": board.to_dict(),
+                "affordable_spells": player.affordable_spells(self.current_time)
             }
+    
+    def affordable_spells(self, player_id: str) -> Optional[List[str]]:
+        """Ids of the spells a player can cast right now, so clients can gray out the rest.
+        
+        Returns None if the player is not in the game.
+        """
+        with self.lock:
+            player = self.players.get(player_id)
+            if not player:
+                return None
+            return player.affordable_spells(self.current_time)
 
 
 class GameServer:
@@ -2172,10 +2354,15 @@ class GameServer:
         self.games: Dict[str, GameManager] = {}
         self.lock = threading.RLock()
     
-    def create_game(self, game_mode: GameMode = GameMode.SURVIVAL) -> str:
+    def create_game(
+        self,
+        game_mode: GameMode = GameMode.SURVIVAL,
+        starting_score: int = 0,
+        starting_spells: Optional[List[str]] = None
+    ) -> str:
         """Create a new game and return its ID."""
         with self.lock:
-            game = GameManager(game_mode)
+            game = GameManager(game_mode, starting_score, starting_spells)
             game.initialize_game()
             self.games[game.game_id] = game
             return game.game_id
@@ -0,0 +1,25 @@
+"""
+Common fixtures for game logic tests.
+"""
+
+import pytest
+from .. import game_logic
+from ..game_logic import GameManager, GameServer
+
+class FakePhysicsEngine:
+    """Stands in for the C++ physics engine, which tests don't load."""
+    
+    def __getattr__(self, name):
+        return lambda *args, **kwargs: None
+
+@pytest.fixture
+def game_manager(monkeypatch):
+    """Create a game manager that doesn't need the physics library."""
+    monkeypatch.setattr(game_logic, "PhysicsEngine", FakePhysicsEngine)
+    return GameManager()
+
+@pytest.fixture
+def game_server(monkeypatch):
+    """Create a game server whose games don't need the physics library."""
+    monkeypatch.setattr(game_logic, "PhysicsEngine", FakePhysicsEngine)
+    return GameServer()
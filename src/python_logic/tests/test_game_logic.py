@@ -0,0 +1,227 @@
+"""
+Tests for game logic.
+"""
+
+import os
+import random
+import pytest
+from .. import game_logic
+from ..game_logic import (
+    GameConstants, GameMode, GameState, PhysicsEngine, Player, PlayerState, SpellFactory, weighted_choice
+)
+
+def _race_with_finishers(game_manager, count):
+    """Start a race where every player has a block in the top rows."""
+    game_manager.game_mode = GameMode.RACE
+    game_manager.game_state = GameState.RUNNING
+    player_ids = [game_manager.add_player(f"player{i}") for i in range(count)]
+    for player_id in player_ids:
+        game_manager.players[player_id].state = PlayerState.PLAYING
+        game_manager.boards[player_id].cells[2][0] = 1
+    return [game_manager.players[player_id] for player_id in player_ids]
+
+def test_race_winner_prefers_higher_score(game_manager):
+    first, second = _race_with_finishers(game_manager, 2)
+    second.score = 100
+    game_manager._check_victory_conditions()
+    assert game_manager.game_state == GameState.VICTORY
+    assert second.state == PlayerState.VICTORIOUS
+    assert first.state == PlayerState.PLAYING
+
+def test_race_winner_tiebreaks(game_manager):
+    first, second, third = _race_with_finishers(game_manager, 3)
+    for player in (first, second, third):
+        player.score = 50
+    first.blocks_placed = 12
+    second.blocks_placed = 10
+    third.blocks_placed = 10
+    # Equal score and blocks: the earlier join wins
+    assert game_manager.resolve_race_winner() == second.id
+
+def test_race_winner_requires_reaching_the_top(game_manager):
+    first, second = _race_with_finishers(game_manager, 2)
+    game_manager.boards[second.id].cells[2][0] = None
+    second.score = 1000
+    assert game_manager.resolve_race_winner() == first.id
+    game_manager.boards[first.id].cells[2][0] = None
+    assert game_manager.resolve_race_winner() is None
+
+def test_affordable_spells_respect_mana_and_cooldown(game_manager):
+    player_id = game_manager.add_player("caster")
+    player = game_manager.players[player_id]
+    spells = {spell.effect: spell for spell in SpellFactory.create_all_spells()}
+    lighten = spells[GameConstants.SPELL_EFFECT_LIGHTEN]
+    bridge = spells[GameConstants.SPELL_EFFECT_BRIDGE]
+    grow = spells[GameConstants.SPELL_EFFECT_GROW]
+    player.spells = [lighten, bridge, grow]
+    player.mana = 40
+    game_manager.current_time = 100.0
+
+    # grow costs 45 mana, more than the player has
+    assert game_manager.affordable_spells(player_id) == [lighten.id, bridge.id]
+    assert game_manager.get_player_state(player_id)["affordable_spells"] == [lighten.id, bridge.id]
+
+    assert player.cast_spell(lighten.id, player_id, game_manager.current_time)
+    # lighten is on cooldown and bridge now costs more than the remaining 15 mana
+    assert game_manager.affordable_spells(player_id) == []
+    player.mana = 100
+    assert game_manager.affordable_spells(player_id) == [bridge.id, grow.id]
+    game_manager.current_time += lighten.cooldown
+    assert game_manager.affordable_spells(player_id) == [lighten.id, bridge.id, grow.id]
+    assert game_manager.affordable_spells("unknown") is None
+
+def test_affordable_spells_skip_non_stackable_spell_while_its_effect_is_active(game_manager):
+    game_manager.starting_spells = ["lighten"]
+    player_id = game_manager.add_player("caster")
+    player = game_manager.players[player_id]
+    lighten = player.spells[0]
+    player.mana = 100
+    game_manager.current_time = 100.0
+
+    assert player.cast_spell(lighten.id, player_id, game_manager.current_time)
+    # Off cooldown, but the first cast is still active
+    player.spell_cooldowns.clear()
+    assert game_manager.affordable_spells(player_id) == []
+    assert player.cast_spell(lighten.id, player_id, game_manager.current_time) is None
+    game_manager.current_time += lighten.duration
+    assert game_manager.affordable_spells(player_id) == [lighten.id]
+
+def _spell_duel(game_manager, spells):
+    """Two playing players; the first one holds the given spells and plenty of mana."""
+    game_manager.starting_spells = spells
+    caster_id = game_manager.add_player("caster")
+    opponent_id = game_manager.add_player("opponent")
+    for player_id in (caster_id, opponent_id):
+        game_manager.players[player_id].state = PlayerState.PLAYING
+    caster = game_manager.players[caster_id]
+    caster.mana = 100
+    return caster, opponent_id
+
+def test_cast_spell_rejects_missing_target(game_manager):
+    caster, _ = _spell_duel(game_manager, ["wind"])
+    assert not game_manager.cast_spell(caster.id, caster.spells[0].id)
+    assert caster.mana == 100
+    assert game_manager.active_spells == []
+
+def test_cast_spell_rejects_targeting_the_caster(game_manager):
+    caster, _ = _spell_duel(game_manager, ["wind"])
+    assert not game_manager.cast_spell(caster.id, caster.spells[0].id, caster.id)
+    assert caster.mana == 100
+
+def test_cast_spell_rejects_self_only_spell_on_other_player(game_manager):
+    caster, opponent_id = _spell_duel(game_manager, ["strengthen", "wind"])
+    strengthen, wind = caster.spells
+    assert not game_manager.cast_spell(caster.id, strengthen.id, opponent_id)
+    assert caster.mana == 100
+
+    assert game_manager.cast_spell(caster.id, strengthen.id)
+    assert game_manager.active_spells[-1].target_id == caster.id
+    assert game_manager.cast_spell(caster.id, wind.id, opponent_id)
+    assert caster.mana == 100 - strengthen.mana_cost - wind.mana_cost
+
+def _player_with_spells(effects):
+    player = Player(id="player", name="player", mana=100)
+    player.spells = SpellFactory.create_spells(effects)
+    return player
+
+def test_active_spell_expires_and_reports_remaining_time():
+    player = _player_with_spells(["strengthen"])
+    strengthen = player.spells[0]
+    assert player.cast_spell(strengthen.id, player.id, 10.0)
+
+    assert player.has_active_spell("strengthen", 10.0)
+    assert player.active_spell_remaining(strengthen.id, 14.0) == pytest.approx(strengthen.duration - 4.0)
+    assert player.has_active_spell("strengthen", 10.0 + strengthen.duration - 0.1)
+    assert not player.has_active_spell("strengthen", 10.0 + strengthen.duration)
+    assert player.active_spell_remaining(strengthen.id, 10.0 + strengthen.duration) is None
+    assert player.active_spells == []
+
+def test_non_stackable_spell_is_not_refreshed_while_active():
+    player = _player_with_spells(["strengthen", "strengthen"])
+    first, second = player.spells
+    assert not first.stackable
+    assert player.cast_spell(first.id, player.id, 0.0)
+    # A second copy of the same effect is rejected and costs nothing
+    assert player.cast_spell(second.id, player.id, 5.0) is None
+    assert player.mana == 100 - first.mana_cost
+    assert player.active_spell_remaining(first.id, 5.0) == pytest.approx(first.duration - 5.0)
+
+    # Once the effect has worn off it can be cast again
+    assert player.cast_spell(second.id, player.id, first.duration)
+    assert player.active_spell_remaining(second.id, first.duration) == pytest.approx(second.duration)
+
+def test_stackable_spells_overlap():
+    player = _player_with_spells(["wind", "wind"])
+    first, second = player.spells
+    assert first.stackable
+    assert player.cast_spell(first.id, "opponent", 0.0)
+    assert player.cast_spell(second.id, "opponent", 1.0)
+    assert [active.spell.id for active in player.active_spells] == [first.id, second.id]
+
+def test_weighted_choice_edge_cases():
+    assert weighted_choice([]) is None
+    assert weighted_choice([("a", 0.0), ("b", -1.0)]) is None
+    rng = random.Random(1)
+    # Items without a positive weight are never picked
+    assert {weighted_choice([("a", 0.0), ("b", 1.0), ("c", -5.0)], rng) for _ in range(100)} == {"b"}
+
+def test_weighted_choice_distribution():
+    rng = random.Random(42)
+    draws = 10000
+    counts = {"a": 0, "b": 0, "c": 0}
+    for _ in range(draws):
+        counts[weighted_choice([("a", 1.0), ("b", 3.0), ("c", 6.0)], rng)] += 1
+    assert counts["a"] / draws == pytest.approx(0.1, abs=0.02)
+    assert counts["b"] / draws == pytest.approx(0.3, abs=0.02)
+    assert counts["c"] / draws == pytest.approx(0.6, abs=0.02)
+
+def test_weighted_choice_is_reproducible_with_seed():
+    items = [(name, 1.0) for name in "abcdefgh"]
+    draws = []
+    for _ in range(2):
+        rng = random.Random(7)
+        draws.append([weighted_choice(items, rng) for _ in range(20)])
+    assert draws[0] == draws[1]
+
+def test_new_player_gets_starting_score_and_loadout(game_server):
+    game_id = game_server.create_game(starting_score=500, starting_spells=["bridge", "wind", "wind"])
+    game = game_server.get_game(game_id)
+    player = game.players[game.add_player("newcomer")]
+    assert player.score == 500
+    assert [spell.effect for spell in player.spells] == ["bridge", "wind", "wind"]
+    # Every copy is a separate spell with its own id
+    assert len({spell.id for spell in player.spells}) == 3
+
+def test_starting_loadout_is_validated(game_server):
+    with pytest.raises(ValueError):
+        game_server.create_game(starting_score=-1)
+    with pytest.raises(ValueError):
+        game_server.create_game(starting_spells=["unknown"])
+    with pytest.raises(ValueError):
+        game_server.create_game(starting_spells=["wind"] * (GameConstants.MAX_SPELLS + 1))
+    assert game_server.games == {}
+
+def test_physics_library_name_matches_platform(monkeypatch):
+    expected = {"win32": "physics.dll", "darwin": "libphysics.dylib", "linux": "libphysics.so"}
+    for platform, name in expected.items():
+        monkeypatch.setattr(game_logic.sys, "platform", platform)
+        assert PhysicsEngine.default_library_name() == name
+
+def test_physics_library_path_env_override_wins(monkeypatch, tmp_path):
+    monkeypatch.delenv(PhysicsEngine.LIBRARY_PATH_ENV, raising=False)
+    monkeypatch.setattr(game_logic.sys, "platform", "linux")
+    assert PhysicsEngine.library_candidates() == [
+        os.path.join(directory, "libphysics.so") for directory in PhysicsEngine.LIBRARY_DIRS
+    ]
+
+    library = tmp_path / "custom_physics.so"
+    library.write_bytes(b"")
+    monkeypatch.setenv(PhysicsEngine.LIBRARY_PATH_ENV, str(library))
+    assert PhysicsEngine.library_candidates() == [str(library)]
+    assert PhysicsEngine.resolve_library_path() == str(library)
+
+def test_physics_library_missing_override_is_reported(monkeypatch, tmp_path):
+    missing = str(tmp_path / "missing.so")
+    monkeypatch.setenv(PhysicsEngine.LIBRARY_PATH_ENV, missing)
+    with pytest.raises(RuntimeError, match="missing.so"):
+        PhysicsEngine.resolve_library_path()
@@ -9,3 +9,8 @@ _CURRENT_DIR = os.path.dirname(__file__)
 _SRC_PATH = os.path.join(_CURRENT_DIR, "src")
 if _SRC_PATH not in sys.path:
     sys.path.insert(0, _SRC_PATH)
+
+# Общие утилиты (``common_utils``) лежат рядом с пакетом сервера, в каталоге ``src``
+_SHARED_PATH = os.path.dirname(os.path.abspath(_CURRENT_DIR))
+if _SHARED_PATH not in sys.path:
+    sys.path.append(_SHARED_PATH)
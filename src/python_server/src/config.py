@@ -1,28 +1,103 @@
 from pydantic_settings import BaseSettings
-from pydantic import ConfigDict
-from typing import Optional
+from pydantic import ConfigDict, field_validator
+from typing import Any, Callable, Dict, List, Optional, Tuple
+import asyncio
+import json
 import os
+import tempfile
+import tomllib
+import yaml
 from dotenv import load_dotenv
+from .utils import parse_interval
+from .exceptions import ConfigError
 
 load_dotenv()
 
+# Допустимый шаг физики: мельче — лишняя нагрузка, крупнее — блоки проскакивают друг сквозь друга
+MIN_PHYSICS_TIME_STEP = 1 / 240
+MAX_PHYSICS_TIME_STEP = 1 / 20
+
 class Settings(BaseSettings):
     """Configuration for the game server."""
 
     # Основные настройки сервера
     server_host: str = os.getenv("SERVER_HOST", "0.0.0.0")
     server_port: int = int(os.getenv("SERVER_PORT", "8080"))
+    startup_timeout: float = parse_interval(os.getenv("SERVER_STARTUP_TIMEOUT", "5"))
+    # Сколько ждать завершения начатых обработчиков и рассылок при остановке
+    shutdown_timeout: float = parse_interval(os.getenv("SERVER_SHUTDOWN_TIMEOUT", "5"))
+    # Обработчики, шаги физики и рассылки дольше этого порога попадают в лог как медленные
+    slow_operation_threshold: float = parse_interval(os.getenv("SERVER_SLOW_OPERATION_THRESHOLD", "0.1"))
+    # Токен для маршрутов /admin; пустой — маршруты отключены
+    admin_token: str = os.getenv("SERVER_ADMIN_TOKEN", "")
+    max_announcement_length: int = int(os.getenv("SERVER_MAX_ANNOUNCEMENT_LENGTH", "500"))
+
+    # Включение отдельных возможностей сервера
+    enable_spectators: bool = os.getenv("FEATURE_SPECTATORS", "true").lower() == "true"
+    enable_practice: bool = os.getenv("FEATURE_PRACTICE", "true").lower() == "true"
+    enable_replay: bool = os.getenv("FEATURE_REPLAY", "true").lower() == "true"
+    enable_metrics: bool = os.getenv("FEATURE_METRICS", "true").lower() == "true"
+
+    # Настройки CORS (списки через запятую)
+    cors_allowed_origins: str = os.getenv("CORS_ALLOWED_ORIGINS", "*")
+    cors_allowed_methods: str = os.getenv("CORS_ALLOWED_METHODS", "GET,POST,PUT,DELETE,OPTIONS")
+    cors_allowed_headers: str = os.getenv("CORS_ALLOWED_HEADERS", "Authorization,Content-Type")
 
     # Настройки игры
-    game_update_interval: float = float(os.getenv("GAME_UPDATE_INTERVAL", "0.016"))
+    game_update_interval: float = parse_interval(os.getenv("GAME_UPDATE_INTERVAL", "0.016"))
+    field_width: int = int(os.getenv("GAME_FIELD_WIDTH", "10"))
+    field_height: int = int(os.getenv("GAME_FIELD_HEIGHT", "20"))
+    snap_on_lock: bool = os.getenv("GAME_SNAP_ON_LOCK", "true").lower() == "true"
+    auto_spawn: bool = os.getenv("GAME_AUTO_SPAWN", "true").lower() == "true"
+    # Обратный отсчёт перед стартом игры, в секундах; 0 — старт сразу
+    start_countdown: int = int(os.getenv("GAME_START_COUNTDOWN", "3"))
+    placement_log: bool = os.getenv("GAME_PLACEMENT_LOG", "false").lower() == "true"
+    max_games_per_user: int = int(os.getenv("GAME_MAX_GAMES_PER_USER", "3"))
+    # Все игры делят один физический движок, поэтому их число ограничено
+    max_games: int = int(os.getenv("GAME_MAX_GAMES", "100"))
+    max_blocks_per_player: int = int(os.getenv("GAME_MAX_BLOCKS_PER_PLAYER", "200"))
+    # Список игр для лобби запрашивается часто, поэтому кэшируется и обрезается
+    max_games_listed: int = int(os.getenv("GAME_MAX_GAMES_LISTED", "50"))
+    # Таблица рекордов; без файла она не переживает перезапуск
+    leaderboard_file: str = os.getenv("GAME_LEADERBOARD_FILE", "")
+    leaderboard_size: int = int(os.getenv("GAME_LEADERBOARD_SIZE", "100"))
+    leaderboard_cache_ttl: float = parse_interval(os.getenv("GAME_LEADERBOARD_CACHE_TTL", "5"))
+    lobby_cache_ttl: float = parse_interval(os.getenv("GAME_LOBBY_CACHE_TTL", "1"))
+    idle_timeout: float = parse_interval(os.getenv("GAME_IDLE_TIMEOUT", "60"))
+    idle_warning: float = parse_interval(os.getenv("GAME_IDLE_WARNING", "10"))
+    idle_check_interval: float = parse_interval(os.getenv("GAME_IDLE_CHECK_INTERVAL", "1"))
+    game_cleanup_interval: float = parse_interval(os.getenv("GAME_CLEANUP_INTERVAL", "30"))
+    empty_game_timeout: float = parse_interval(os.getenv("EMPTY_GAME_TIMEOUT", "60"))
+    stale_game_timeout: float = parse_interval(os.getenv("STALE_GAME_TIMEOUT", "600"))
+
+    # Настройки сети
+    message_dedup_window: float = parse_interval(os.getenv("MESSAGE_DEDUP_WINDOW", "5.0"))
+    full_sync_interval: float = parse_interval(os.getenv("NETWORK_FULL_SYNC_INTERVAL", "5"))
+    max_connections: int = int(os.getenv("NETWORK_MAX_CONNECTIONS", "1000"))
+    max_connections_per_session: int = int(os.getenv("NETWORK_MAX_CONNECTIONS_PER_SESSION", "3"))
+    event_history_size: int = int(os.getenv("NETWORK_EVENT_HISTORY_SIZE", "100"))
+    max_parse_failures: int = int(os.getenv("NETWORK_MAX_PARSE_FAILURES", "5"))
+    resync_interval: float = parse_interval(os.getenv("NETWORK_RESYNC_INTERVAL", "1"))
+    max_batch_size: int = int(os.getenv("NETWORK_MAX_BATCH_SIZE", "10"))
+    # Соединение с большим числом незавершённых отправок считается медленным клиентом
+    slow_client_threshold: int = int(os.getenv("NETWORK_SLOW_CLIENT_THRESHOLD", "8"))
 
     # Настройки сессии
-    session_cleanup_interval: int = int(os.getenv("SESSION_CLEANUP_INTERVAL", "300"))
-    session_heartbeat_interval: int = int(os.getenv("SESSION_HEARTBEAT_INTERVAL", "30"))
+    session_cleanup_interval: float = parse_interval(os.getenv("SESSION_CLEANUP_INTERVAL", "300"))
+    session_heartbeat_interval: float = parse_interval(os.getenv("SESSION_HEARTBEAT_INTERVAL", "30"))
+    # Ключ подписи токенов переподключения; если не задан, генерируется при запуске
+    reconnect_secret: str = os.getenv("SESSION_RECONNECT_SECRET", "")
+    rotate_reconnect_tokens: bool = os.getenv("SESSION_ROTATE_RECONNECT_TOKENS", "true").lower() == "true"
 
     # Настройки физики
     physics_gravity: float = float(os.getenv("PHYSICS_GRAVITY", "9.8"))
     physics_friction: float = float(os.getenv("PHYSICS_FRICTION", "0.1"))
+    # Число подшагов интегрирования за один шаг обновления
+    physics_iterations: int = int(os.getenv("PHYSICS_ITERATIONS", "1"))
+    # Фиксированный шаг симуляции, в секундах
+    physics_time_step: float = parse_interval(os.getenv("PHYSICS_TIME_STEP", str(1 / 60)))
+    # Не считать физику, пока все блоки неподвижны
+    physics_pause_when_idle: bool = os.getenv("PHYSICS_PAUSE_WHEN_IDLE", "true").lower() == "true"
 
     # Настройки логирования
     log_level: str = os.getenv("LOG_LEVEL", "INFO")
@@ -30,3 +105,120 @@ class Settings(BaseSettings):
 
     model_config = ConfigDict(env_file=".env")
 
+    @field_validator(
+        "startup_timeout",
+        "shutdown_timeout",
+        "slow_operation_threshold",
+        "game_update_interval",
+        "lobby_cache_ttl",
+        "leaderboard_cache_ttl",
+        "game_cleanup_interval",
+        "empty_game_timeout",
+        "stale_game_timeout",
+        "idle_timeout",
+        "idle_warning",
+        "idle_check_interval",
+        "message_dedup_window",
+        "full_sync_interval",
+        "resync_interval",
+        "session_cleanup_interval",
+        "session_heartbeat_interval",
+        "physics_time_step",
+        mode="before"
+    )
+    @classmethod
+    def _parse_intervals(cls, value):
+        # Интервалы можно задавать строками вида "30s", "5m" или "500ms"
+        return parse_interval(value)
+
+    @field_validator("physics_time_step")
+    @classmethod
+    def _validate_physics_time_step(cls, value: float) -> float:
+        # Нулевой шаг превратил бы цикл физики в активное ожидание
+        if not MIN_PHYSICS_TIME_STEP <= value <= MAX_PHYSICS_TIME_STEP:
+            raise ValueError(
+                f"physics_time_step must be between {MIN_PHYSICS_TIME_STEP:.5f} "
+                f"and {MAX_PHYSICS_TIME_STEP:.5f} seconds, got {value}"
+            )
+        return value
+
+    @classmethod
+    def from_file(cls, path: str) -> "Settings":
+        """Загружает настройки из файла TOML, JSON или YAML (формат определяется по расширению)"""
+        with open(path, "rb") as f:
+            raw = f.read()
+
+        extension = os.path.splitext(path)[1].lower()
+        if extension in CONFIG_FORMATS:
+            candidates = [extension]
+        else:
+            # Неизвестное расширение: пробуем TOML, затем JSON
+            candidates = [".toml", ".json"]
+
+        errors: List[str] = []
+        for candidate in candidates:
+            name, parse = CONFIG_FORMATS[candidate]
+            try:
+                data = parse(raw)
+            except Exception as e:
+                errors.append(f"{name}: {e}")
+                continue
+            if not isinstance(data, dict):
+                errors.append(f"{name}: top level must be a mapping")
+                continue
+            return cls(**data)
+
+        raise ConfigError(f"Could not parse config file {path} ({'; '.join(errors)})")
+
+    def save_to_file(self, path: str) -> None:
+        """Сохраняет настройки в файл; формат определяется по расширению (по умолчанию JSON)"""
+        extension = os.path.splitext(path)[1].lower()
+        write = CONFIG_WRITERS.get(extension, CONFIG_WRITERS[".json"])
+        # None в TOML не представим, поэтому незаданные значения не сохраняются
+        data = {key: value for key, value in self.model_dump().items() if value is not None}
+        atomic_write(path, write(data))
+
+    async def save_to_file_async(self, path: str) -> None:
+        """То же, что save_to_file, но запись выполняется вне цикла событий"""
+        await asyncio.to_thread(self.save_to_file, path)
+
+def atomic_write(path: str, content: str) -> None:
+    """Записывает файл через временный файл, чтобы при сбое не оставить его наполовину записанным"""
+    directory = os.path.dirname(os.path.abspath(path))
+    fd, tmp_path = tempfile.mkstemp(dir=directory, prefix=".config-", suffix=".tmp")
+    try:
+        with os.fdopen(fd, "w", encoding="utf-8") as f:
+            f.write(content)
+            f.flush()
+            os.fsync(f.fileno())
+        os.replace(tmp_path, path)
+    except BaseException:
+        os.unlink(tmp_path)
+        raise
+
+def dump_toml(data: Dict[str, Any]) -> str:
+    # Настройки плоские, поэтому достаточно записать пары ключ = значение
+    lines = []
+    for key, value in data.items():
+        if isinstance(value, bool):
+            rendered = "true" if value else "false"
+        elif isinstance(value, (int, float)):
+            rendered = repr(value)
+        else:
+            rendered = json.dumps(str(value), ensure_ascii=False)
+        lines.append(f"{key} = {rendered}")
+    return "\n".join(lines) + "\n"
+
+CONFIG_FORMATS: Dict[str, Tuple[str, Callable[[bytes], Any]]] = {
+    ".toml": ("TOML", lambda raw: tomllib.loads(raw.decode("utf-8"))),
+    ".json": ("JSON", lambda raw: json.loads(raw)),
+    ".yaml": ("YAML", lambda raw: yaml.safe_load(raw)),
+    ".yml": ("YAML", lambda raw: yaml.safe_load(raw)),
+}
+
+CONFIG_WRITERS: Dict[str, Callable[[Dict[str, Any]], str]] = {
+    ".toml": dump_toml,
+    ".json": lambda data: json.dumps(data, indent=4, ensure_ascii=False) + "\n",
+    ".yaml": lambda data: yaml.safe_dump(data, allow_unicode=True, sort_keys=False),
+    ".yml": lambda data: yaml.safe_dump(data, allow_unicode=True, sort_keys=False),
+}
@@ -0,0 +1,19 @@
+from typing import List
+from fastapi import FastAPI
+from fastapi.middleware.cors import CORSMiddleware
+from .config import Settings
+
+def split_list(value: str) -> List[str]:
+    return [item.strip() for item in value.split(",") if item.strip()]
+
+def add_cors(app: FastAPI, settings: Settings) -> None:
+    """Подключает CORS ко всем HTTP-маршрутам, включая ответы на preflight-запросы OPTIONS"""
+    origins = split_list(settings.cors_allowed_origins)
+    app.add_middleware(
+        CORSMiddleware,
+        allow_origins=origins,
+        # Авторизация идёт через заголовок Authorization, cookie не используются
+        allow_credentials=False,
+        allow_methods=split_list(settings.cors_allowed_methods),
+        allow_headers=split_list(settings.cors_allowed_headers),
+    )
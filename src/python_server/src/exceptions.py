@@ -22,6 +22,26 @@ class InvalidGameSettingsError(GameError):
     """Некорректные настройки игры"""
     pass
 
+class InvalidValueError(GameError, ValueError):
+    """Неизвестное значение перечисления"""
+    pass
+
+class GameLimitExceededError(GameError):
+    """Превышен лимит игр на пользователя"""
+    pass
+
+class ServerAtCapacityError(GameError):
+    """Достигнут лимит одновременных игр на сервере"""
+    pass
+
+class FeatureDisabledError(GameError):
+    """Возможность отключена в настройках сервера"""
+    pass
+
+class InvalidTokenError(GameError):
+    """Токен переподключения подделан, отозван или относится к удалённой сессии"""
+    pass
+
 class GameFullError(GameError):
     """Игра заполнена"""
     pass
@@ -34,10 +54,42 @@ class InvalidActionError(GameError):
     """Некорректное действие"""
     pass
 
+class InconsistentStateError(GameError):
+    """Изменение привело состояние игры к нарушению инвариантов"""
+    pass
+
+class NotInGameError(InvalidActionError):
+    """Действие требует, чтобы сессия находилась в игре"""
+    pass
+
 class NetworkError(GameError):
     """Ошибка сети"""
     pass
 
+class ProtocolError(NetworkError):
+    """Сообщение клиента не удалось разобрать"""
+    pass
+
+class NotAuthenticatedError(NetworkError):
+    """Сообщение требует сессии, но session_id не передан или некорректен"""
+    pass
+
+class RateLimitedError(NetworkError):
+    """Запрос повторяется чаще, чем разрешено"""
+    pass
+
 class PhysicsError(GameError):
     """Ошибка физики"""
+    pass
+
+class BlockNotFoundError(PhysicsError):
+    """Блока нет в физическом движке (например, удалён при очистке линии)"""
+    pass
+
+class ConfigError(GameError):
+    """Не удалось загрузить конфигурацию"""
+    pass
+
+class StartupError(GameError):
+    """Подсистема сервера не запустилась"""
     pass 
\ No newline at end of file
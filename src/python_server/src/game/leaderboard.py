@@ -0,0 +1,56 @@
+import json
+import os
+from typing import List
+from ..logger import logger
+from ..config import atomic_write
+from .types import LeaderboardEntry
+
+class Leaderboard:
+    """Лучшие результаты завершённых игр.
+
+    Таблица живёт в памяти; сохранение в JSON-файл включается, только если задан путь.
+    """
+
+    def __init__(self, path: str = "", size: int = 100):
+        self.path = path
+        self.size = size
+        self.entries: List[LeaderboardEntry] = []
+
+    def record(self, entry: LeaderboardEntry) -> None:
+        self.entries.append(entry)
+        self._sort()
+
+    def top(self, limit: int) -> List[LeaderboardEntry]:
+        return self.entries[:max(0, limit)]
+
+    def _sort(self) -> None:
+        # При равном счёте выше тот, кто завершил игру раньше
+        self.entries.sort(key=lambda entry: (-entry["score"], entry["finished_at"]))
+        del self.entries[self.size:]
+
+    def load(self) -> None:
+        """Загружает таблицу из файла; повреждённый файл не мешает запуску сервера"""
+        if not self.path or not os.path.exists(self.path):
+            return
+        try:
+            with open(self.path, encoding="utf-8") as f:
+                entries = json.load(f)
+            if not isinstance(entries, list):
+                raise ValueError("top level must be a list")
+            self.entries = entries
+            self._sort()
+        except (OSError, ValueError, KeyError, TypeError) as e:
+            logger.error(f"Could not load leaderboard from {self.path}: {e}")
+            self.entries = []
+
+    def dump(self) -> str:
+        """Снимок таблицы в JSON; дальнейшие изменения таблицы на него не влияют"""
+        return json.dumps(self.entries, indent=4, ensure_ascii=False) + "\n"
+
+    def write(self, text: str) -> None:
+        """Записывает снимок из dump в файл; безопасно вызывать из другого потока"""
+        if self.path:
+            atomic_write(self.path, text)
+
+    def save(self) -> None:
+        self.write(self.dump())
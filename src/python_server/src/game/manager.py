@@ -1,88 +1,1605 @@
 import asyncio
+import json
+import math
+import random
+import time
 import uuid
-from typing import Dict, Optional
+from collections import Counter, deque
+from typing import Any, Awaitable, Callable, Deque, Dict, List, Optional, Set, Tuple
 from ..logger import logger
 from ..config import Settings
+from ..metrics import Metrics
+from ..exceptions import (
+    BlockNotFoundError,
+    GameFullError,
+    GameLimitExceededError,
+    GameNotFoundError,
+    InconsistentStateError,
+    InvalidActionError,
+    PlayerNotFoundError,
+    InvalidGameSettingsError,
+    InvalidValueError,
+    ServerAtCapacityError
+)
+from ..physics.manager import PhysicsManager, PhysicsMaterial
+from ..utils import TtlCache, require_feature, validate_game_settings
+from .leaderboard import Leaderboard
+from .tetromino import SPAWN_CELLS, Grid, Tetromino, TetrominoType
+from .types import (
+    DifficultyLevel,
+    DifficultyParameters,
+    GameResults,
+    GameSettings,
+    GameState,
+    GameStatus,
+    GameSummary,
+    GameType,
+    LeaderboardEntry,
+    PlayerResult,
+    PlayerState,
+    PlayerStats
+)
+
+# Палитра цветов игроков, назначаемых по кругу при входе в игру
+PLAYER_COLORS = ["#E74C3C", "#3498DB", "#2ECC71", "#F1C40F", "#9B59B6", "#E67E22"]
+TEAM_COUNT = 2
+# Сколько следующих фигур игрок видит в очереди
+NEXT_QUEUE_SIZE = 3
+MAX_PLAYER_NAME_LENGTH = 32
+# Типы игр, в которых игроки по умолчанию видят у соперников только счёт и высоту башни
+FOG_OF_WAR_GAME_TYPES = {GameType.BATTLE}
+# События о фигурах игрока: поля с id игроков и поля, скрываемые в тумане войны от тех, кто их не видит
+HIDDEN_EVENT_DETAILS: Dict[str, Tuple[Tuple[str, ...], Tuple[str, ...]]] = {
+    "block_placed": (("player_id",), ("block_id", "tetromino_type", "x", "y", "rotation")),
+    "block_transferred": (("from_player_id", "to_player_id"), ("block_id",)),
+}
+
+# Параметры стандартных уровней сложности
+DIFFICULTY_PRESETS: Dict[DifficultyLevel, DifficultyParameters] = {
+    DifficultyLevel.EASY: {
+        "fall_interval_ms": 1000, "friction": 0.7, "restitution": 0.05, "spell_frequency": 0.2
+    },
+    DifficultyLevel.MEDIUM: {
+        "fall_interval_ms": 700, "friction": 0.5, "restitution": 0.1, "spell_frequency": 0.1
+    },
+    DifficultyLevel.HARD: {
+        "fall_interval_ms": 400, "friction": 0.3, "restitution": 0.2, "spell_frequency": 0.05
+    },
+}
+
+# Очки за одновременно очищенные линии (умножаются на уровень игрока)
+LINE_CLEAR_SCORES = {1: 100, 2: 300, 3: 500, 4: 800}
+LINES_PER_LEVEL = 10
+# В бесконечной игре интервал падения сокращается с каждым уровнем, но не ниже минимума
+ENDLESS_SPEEDUP = 0.85
+MIN_FALL_INTERVAL_MS = 50
+# Изменение высоты башни (в клетках), о котором стоит сообщать участникам
+TOWER_HEIGHT_REPORT_STEP = 1.0
+
+# Состояния игры для метрик
+GAME_STATES = tuple(status.value for status in GameStatus)
+# Допустимые переходы между состояниями игры
+GAME_TRANSITIONS: Dict[GameStatus, Set[GameStatus]] = {
+    GameStatus.WAITING: {GameStatus.STARTING, GameStatus.RUNNING, GameStatus.FINISHED, GameStatus.CANCELLED},
+    GameStatus.STARTING: {GameStatus.WAITING, GameStatus.RUNNING, GameStatus.FINISHED, GameStatus.CANCELLED},
+    GameStatus.RUNNING: {GameStatus.PAUSED, GameStatus.FINISHED},
+    GameStatus.PAUSED: {GameStatus.RUNNING, GameStatus.FINISHED},
+    GameStatus.FINISHED: set(),
+    GameStatus.CANCELLED: set(),
+}
+# Длительность одного шага обратного отсчёта перед стартом, в секундах
+COUNTDOWN_TICK = 1.0
+
+# Обработчик игровых событий: получает id игры и событие
+GameEventListener = Callable[[uuid.UUID, Dict[str, Any]], Awaitable[None]]
+
+def piece_position(block_id: uuid.UUID, piece: Tetromino) -> Dict[str, Any]:
+    """Положение фигуры в ответе на move и rotate"""
+    return {"block_id": str(block_id), "x": piece.x, "y": piece.y, "rotation": piece.rotation}
+
+class Player:
+    def __init__(
+        self,
+        player_id: uuid.UUID,
+        color: str,
+        team: Optional[int] = None,
+        join_order: int = 0,
+        name: str = ""
+    ):
+        self.id = player_id
+        self.color = color
+        self.team = team
+        self.join_order = join_order
+        # Имя для таблицы рекордов и соперников; без имени — по порядку входа
+        self.name = name or f"Player {join_order}"
+        self.score = 0
+        self.tower_height = 0.0
+        self.blocks_placed = 0
+        self.blocks_destroyed = 0
+        self.spells_cast = 0
+        # Заклинания, выданные за очищенные линии, и линии, засчитанные для их выдачи
+        self.spells_granted = 0
+        self.spell_lines = 0
+        # Время последнего игрового ввода (в отличие от активности сессии, пинги его не обновляют)
+        self.last_input_at = time.monotonic()
+        self.idle_warned = False
+        # Время первой выданной фигуры: с него игрок начинает действовать
+        self.started_at: Optional[float] = None
+        # Когда текущая фигура в последний раз опускалась сама (или появилась)
+        self.last_fall_at: Optional[float] = None
+        self.block_ids: List[uuid.UUID] = []
+        # Очки за линии, очищенные каждым из блоков игрока; при передаче блока они уходят вместе с ним
+        self.block_scores: Dict[uuid.UUID, int] = {}
+        self.current_block_id: Optional[uuid.UUID] = None
+        self.next_queue: Deque[TetrominoType] = deque()
+        self.refill_queue()
+        self.level = 1
+        self.lines_cleared = 0
+        self.is_active = True
+        # Счёт и высота, о которых участники уже знают
+        self.reported_score = 0
+        self.reported_tower_height = 0.0
+
+    def refill_queue(self) -> None:
+        # Фигуры выдаются «мешками» по семь, как в современных версиях Tetris
+        while len(self.next_queue) < NEXT_QUEUE_SIZE:
+            bag = list(TetrominoType)
+            random.shuffle(bag)
+            self.next_queue.extend(bag)
+
+    def add_cleared_lines(self, lines: int) -> int:
+        """Начисляет очки за очищенные линии и возвращает их"""
+        if lines <= 0:
+            return 0
+        points = LINE_CLEAR_SCORES.get(lines, LINE_CLEAR_SCORES[4]) * self.level
+        self.score += points
+        self.lines_cleared += lines
+        self.level = 1 + self.lines_cleared // LINES_PER_LEVEL
+        return points
+
+    def take_report(self) -> bool:
+        """Отмечает текущие счёт и высоту как известные; True, если они заметно изменились"""
+        if (
+            self.score == self.reported_score
+            and abs(self.tower_height - self.reported_tower_height) < TOWER_HEIGHT_REPORT_STEP
+        ):
+            return False
+        self.reported_score = self.score
+        self.reported_tower_height = self.tower_height
+        return True
+
+    def next_piece(self) -> TetrominoType:
+        piece = self.next_queue.popleft()
+        self.refill_queue()
+        return piece
+
+    def elapsed(self) -> Optional[float]:
+        """Секунды с первой фигуры игрока; None, если он ещё не начал"""
+        if self.started_at is None:
+            return None
+        return time.monotonic() - self.started_at
+
+    def stats(self) -> PlayerStats:
+        """Производные показатели, вычисляемые из счётчиков игрока"""
+        total = self.blocks_placed + self.blocks_destroyed
+        elapsed = self.elapsed()
+        return {
+            "blocks_placed": self.blocks_placed,
+            "blocks_destroyed": self.blocks_destroyed,
+            "efficiency": self.blocks_placed / total if total else 0.0,
+            "tower_height": self.tower_height,
+            "score": self.score,
+            "spells_cast": self.spells_cast,
+            "spells_granted": self.spells_granted,
+            "score_rate": self.score / elapsed * 60 if elapsed else 0.0,
+        }
+
+    def get_state(self, redacted: bool = False) -> PlayerState:
+        """Состояние игрока; в скрытом виде — без текущей фигуры и очереди"""
+        return {
+            "player_id": str(self.id),
+            "name": self.name,
+            "score": self.score,
+            "level": self.level,
+            "lines_cleared": self.lines_cleared,
+            "is_active": self.is_active,
+            "tower_height": self.tower_height,
+            "current_block_id": str(self.current_block_id) if self.current_block_id and not redacted else None,
+            "next_pieces": [] if redacted else [piece.value for piece in list(self.next_queue)[:NEXT_QUEUE_SIZE]],
+            "color": self.color,
+            "team": self.team,
+            "stats": self.stats(),
+        }
 
 class Game:
     def __init__(self, game_id: uuid.UUID, settings: Settings):
         self.id = game_id
-        self.players: set[uuid.UUID] = set()
+        self.players: Dict[uuid.UUID, Player] = {}
+        self.spectators: set[uuid.UUID] = set()
         self.running = False
+        self.finished = False
+        self.cancelled = False
+        self.winner_id: Optional[uuid.UUID] = None
+        self.created_at = time.monotonic()
+        # Время последнего входа игрока, ввода или изменения настроек
+        self.last_activity_at = self.created_at
+        self.started_at: Optional[float] = None
+        self.finished_at: Optional[float] = None
+        # Начало паузы; None, пока игра не приостановлена
+        self.paused_at: Optional[float] = None
         self.settings = settings
-        self.update_task: Optional[asyncio.Task] = None
+        # Секунд до старта, пока идёт обратный отсчёт; None вне отсчёта
+        self.countdown: Optional[int] = None
+        self.countdown_task: Optional[asyncio.Task] = None
+        # Логическая модель поля, которая ведётся параллельно с физикой
+        self.grid = Grid(settings.field_width, settings.field_height)
+        self.pieces: Dict[uuid.UUID, Tetromino] = {}
+        # Последний применённый input_seq по фигурам: повтор ввода не сдвигает фигуру второй раз
+        self.piece_input_seqs: Dict[uuid.UUID, int] = {}
+        # События block_placed для аналитики (ведётся при включённом placement_log)
+        self.placement_log: List[Dict[str, Any]] = []
+        self.host_id: Optional[uuid.UUID] = None
+        # Пользователь, создавший игру (для ограничения числа игр на пользователя)
+        self.creator_id: Optional[uuid.UUID] = None
+        # Наибольший уровень среди игроков; в бесконечной игре определяет скорость падения
+        self.level = 1
+        # Номер изменения состояния; по нему проверяется актуальность кэша state_json
+        self.revision = 0
+        self._state_json: Optional[Tuple[Tuple[int, Optional[int]], str]] = None
+        # Тренировочная игра одного игрока: запускается без лобби, фигуры выдаются автоматически
+        self.practice = False
+        self._join_counter = 0
+        self.game_settings: GameSettings = {
+            "game_type": GameType.CLASSIC,
+            "difficulty": DifficultyLevel.MEDIUM,
+            "max_players": 4,
+            "time_limit": None,
+            "score_limit": None,
+            "target_height": settings.field_height,
+            "garbage_enabled": False,
+            "special_block_probability": 0.1,
+            "spells_enabled": True,
+            "field_width": settings.field_width,
+            "field_height": settings.field_height,
+        }
+
+    async def add_player(
+        self,
+        player_id: uuid.UUID,
+        color: Optional[str] = None,
+        team: Optional[int] = None,
+        name: Optional[str] = None
+    ) -> None:
+        if player_id in self.players:
+            return
+        # Проверка can_join и добавление идут без await между ними, поэтому параллельные join
+        # не могут превысить max_players или попасть в игру, которая тем временем стартовала
+        if not self.can_join():
+            if self.state() != GameStatus.WAITING:
+                raise InvalidActionError(f"Game {self.id} is {self.state()} and cannot be joined")
+            raise GameFullError(f"Game {self.id} is full")
+        # Первый присоединившийся игрок становится хостом
+        if self.host_id is None:
+            self.host_id = player_id
+        self._join_counter += 1
+        self.players[player_id] = Player(
+            player_id, self._pick_color(color), self._pick_team(team), self._join_counter,
+            self._pick_name(name)
+        )
+        self.touch()
+        self.mark_changed()
 
-    async def add_player(self, player_id: uuid.UUID) -> None:
-        self.players.add(player_id)
+    def touch(self) -> None:
+        self.last_activity_at = time.monotonic()
+
+    def set_game_settings(self, game_settings: GameSettings) -> None:
+        """Применяет проверенные настройки; поле пересоздаётся, если изменились его размеры"""
+        self.game_settings = game_settings
+        width = game_settings.get("field_width", self.settings.field_width)
+        height = game_settings.get("field_height", self.settings.field_height)
+        if (width, height) != (self.grid.width, self.grid.height):
+            self.grid = Grid(width, height)
+
+    def mark_changed(self) -> None:
+        """Отмечает изменение состояния, которое видят клиенты"""
+        self.revision += 1
 
     async def remove_player(self, player_id: uuid.UUID) -> None:
-        self.players.discard(player_id)
+        self.players.pop(player_id, None)
+        # Роль хоста переходит к игроку, присоединившемуся раньше остальных
+        if self.host_id == player_id:
+            remaining = self.players_in_join_order()
+            self.host_id = remaining[0].id if remaining else None
+        self.mark_changed()
+
+    def elapsed_for_player(self, player_id: uuid.UUID) -> Optional[float]:
+        """Время игры конкретного игрока, независимо от момента старта самой игры"""
+        player = self.players.get(player_id)
+        if not player:
+            raise PlayerNotFoundError(f"Player {player_id} is not in game {self.id}")
+        return player.elapsed()
+
+    def is_full(self) -> bool:
+        return len(self.players) >= self.game_settings["max_players"]
+
+    def is_team_game(self) -> bool:
+        return self.game_settings["game_type"] == GameType.TEAM
+
+    def _pick_color(self, requested: Optional[str]) -> str:
+        used = {player.color for player in self.players.values()}
+        if requested and requested not in used:
+            return requested
+        for color in PLAYER_COLORS:
+            if color not in used:
+                return color
+        return PLAYER_COLORS[len(self.players) % len(PLAYER_COLORS)]
+
+    @staticmethod
+    def _pick_name(requested: Optional[str]) -> str:
+        if not isinstance(requested, str):
+            return ""
+        return requested.strip()[:MAX_PLAYER_NAME_LENGTH]
+
+    def _pick_team(self, requested: Optional[int]) -> Optional[int]:
+        if not self.is_team_game():
+            return None
+        if requested is not None and 0 <= requested < TEAM_COUNT:
+            return requested
+        # Новый игрок попадает в наименее заполненную команду
+        sizes = [0] * TEAM_COUNT
+        for player in self.players.values():
+            if player.team is not None:
+                sizes[player.team] += 1
+        return sizes.index(min(sizes))
+
+    def team_scores(self) -> Dict[int, int]:
+        scores: Dict[int, int] = {}
+        for player in self.players.values():
+            if player.team is not None:
+                scores[player.team] = scores.get(player.team, 0) + player.score
+        return scores
+
+    def winning_team(self) -> Optional[int]:
+        """Команда, первой набравшая score_limit суммарно; None, если такой нет"""
+        score_limit = self.game_settings["score_limit"]
+        if not self.is_team_game() or score_limit is None:
+            return None
+        reached = [(score, team) for team, score in self.team_scores().items() if score >= score_limit]
+        if not reached:
+            return None
+        return max(reached)[1]
+
+    def determine_winner(self) -> Optional[uuid.UUID]:
+        """Определяет победителя по правилам режима.
+
+        Побеждает игрок с наибольшим счётом, затем с самой высокой башней,
+        затем присоединившийся раньше. В командной игре кандидаты ограничены
+        лучшей командой, в кооперативной личного победителя нет.
+        """
+        game_type = self.game_settings["game_type"]
+        if game_type == GameType.COOPERATIVE:
+            return None
+        candidates = list(self.players.values())
+        if game_type == GameType.TEAM:
+            team_scores = self.team_scores()
+            if team_scores:
+                # При равенстве очков выигрывает команда с меньшим номером
+                best_team = min(team_scores, key=lambda team: (-team_scores[team], team))
+                candidates = [player for player in candidates if player.team == best_team]
+        if not candidates:
+            return None
+        winner = min(
+            candidates,
+            key=lambda player: (-player.score, -player.tower_height, player.join_order)
+        )
+        return winner.id
+
+    def standings(self) -> List[Player]:
+        """Игроки в порядке итоговых мест: победитель первым, остальные по правилам determine_winner"""
+        return sorted(
+            self.players.values(),
+            key=lambda player: (
+                player.id != self.winner_id, -player.score, -player.tower_height, player.join_order
+            )
+        )
+
+    def results(self) -> GameResults:
+        """Итоги завершённой игры для сообщения game_over"""
+        standings: List[PlayerResult] = [
+            {
+                "rank": rank,
+                "player_id": str(player.id),
+                "team": player.team,
+                "score": player.score,
+                "tower_height": player.tower_height,
+                "blocks_placed": player.blocks_placed,
+                "lines_cleared": player.lines_cleared,
+                "spells_cast": player.spells_cast,
+            }
+            for rank, player in enumerate(self.standings(), start=1)
+        ]
+        return {
+            "game_id": str(self.id),
+            "winner_id": str(self.winner_id) if self.winner_id else None,
+            "duration": self.play_time(),
+            "standings": standings,
+        }
+
+    async def finish(self, winner_id: Optional[uuid.UUID] = None) -> Optional[uuid.UUID]:
+        """Завершает игру; если победитель не указан, он определяется автоматически"""
+        self._require_transition(GameStatus.FINISHED)
+        if winner_id is None:
+            winner_id = self.determine_winner()
+        self.winner_id = winner_id
+        self.finished = True
+        self.finished_at = time.monotonic()
+        self.mark_changed()
+        await self.stop()
+        return winner_id
+
+    async def add_spectator(self, user_id: uuid.UUID) -> None:
+        self.spectators.add(user_id)
+        self.mark_changed()
+
+    async def remove_spectator(self, user_id: uuid.UUID) -> None:
+        self.spectators.discard(user_id)
+        self.mark_changed()
 
     async def is_empty(self) -> bool:
         return len(self.players) == 0
 
+    def block_owner(self, block_id: uuid.UUID) -> Optional[Player]:
+        for player in self.players.values():
+            if block_id in player.block_ids:
+                return player
+        return None
+
+    def active_player_count(self) -> int:
+        return sum(1 for player in self.players.values() if player.is_active)
+
     async def is_running(self) -> bool:
         return self.running
 
+    def state(self) -> GameStatus:
+        if self.finished:
+            return GameStatus.FINISHED
+        if self.cancelled:
+            return GameStatus.CANCELLED
+        if self.running:
+            return GameStatus.PAUSED if self.paused_at is not None else GameStatus.RUNNING
+        return GameStatus.STARTING if self.countdown is not None else GameStatus.WAITING
+
+    def _require_transition(self, target: GameStatus) -> None:
+        current = self.state()
+        if target not in GAME_TRANSITIONS[current]:
+            raise InvalidActionError(f"Game {self.id} cannot go from {current} to {target}")
+
+    def is_active(self) -> bool:
+        """Идёт ли отсчёт или сама игра (в том числе приостановленная)"""
+        return self.state() in (GameStatus.STARTING, GameStatus.RUNNING, GameStatus.PAUSED)
+
+    def is_paused(self) -> bool:
+        return self.state() == GameStatus.PAUSED
+
+    def can_join(self) -> bool:
+        return self.state() == GameStatus.WAITING and not self.is_full()
+
+    def is_ready_to_start(self) -> bool:
+        return self.state() == GameStatus.WAITING and bool(self.players)
+
+    def time_remaining(self) -> Optional[int]:
+        time_limit = self.game_settings["time_limit"]
+        if time_limit is None or self.started_at is None:
+            return time_limit
+        return max(0, int(time_limit - self.play_time()))
+
+    def play_time(self) -> float:
+        """Секунды игры с момента старта; пауза не учитывается"""
+        if self.started_at is None:
+            return 0.0
+        return (self.paused_at or self.finished_at or time.monotonic()) - self.started_at
+
+    def difficulty_parameters(self) -> DifficultyParameters:
+        difficulty = self.game_settings["difficulty"]
+        if difficulty == DifficultyLevel.CUSTOM:
+            return self.game_settings["custom_difficulty"]
+        return DIFFICULTY_PRESETS[difficulty]
+
+    def fall_interval_ms(self) -> int:
+        interval = self.difficulty_parameters()["fall_interval_ms"]
+        if self.game_settings["game_type"] == GameType.ENDLESS:
+            interval = max(MIN_FALL_INTERVAL_MS, round(interval * ENDLESS_SPEEDUP ** (self.level - 1)))
+        return interval
+
+    def fall_interval(self) -> float:
+        """Интервал падения фигуры на одну клетку, в секундах"""
+        return self.fall_interval_ms() / 1000
+
+    def grant_spells(self, player: Player, lines: int) -> int:
+        """Выдаёт игроку заклинания за очищенные линии: spell_frequency заклинания на линию"""
+        if lines <= 0 or not self.game_settings.get("spells_enabled", True):
+            return 0
+        player.spell_lines += lines
+        earned = math.floor(player.spell_lines * self.difficulty_parameters()["spell_frequency"])
+        granted = max(0, earned - player.spells_granted)
+        player.spells_granted += granted
+        return granted
+
+    def physics_iterations(self) -> int:
+        return self.game_settings.get("physics_iterations", self.settings.physics_iterations)
+
+    def block_material(self) -> PhysicsMaterial:
+        params = self.difficulty_parameters()
+        return PhysicsMaterial(friction=params["friction"], restitution=params["restitution"])
+
+    def check_invariants(self) -> List[str]:
+        """Описания нарушенных инвариантов состояния; пустой список, если всё согласовано"""
+        violations = []
+        for player in self.players.values():
+            if player.current_block_id is not None and player.current_block_id not in player.block_ids:
+                violations.append(
+                    f"current block {player.current_block_id} of player {player.id} is not in its blocks"
+                )
+        if self.winner_id is not None and not self.finished:
+            violations.append(f"winner {self.winner_id} is set but the game is not finished")
+        if self.running and self.finished:
+            violations.append("game is both running and finished")
+        if len(self.players) > self.game_settings["max_players"]:
+            violations.append(
+                f"{len(self.players)} players exceed max_players={self.game_settings['max_players']}"
+            )
+        for user_id in self.spectators & set(self.players):
+            violations.append(f"user {user_id} is both a player and a spectator")
+        owners = Counter(block_id for player in self.players.values() for block_id in player.block_ids)
+        for block_id, count in owners.items():
+            if count > 1:
+                violations.append(f"block {block_id} is owned by several players")
+        return violations
+
+    def get_state(self) -> GameState:
+        """Состояние игры для рассылки участникам"""
+        return {
+            "game_id": str(self.id),
+            "players": [player.get_state() for player in self.players_in_join_order()],
+            "settings": self.game_settings,
+            "state": self.state(),
+            "is_running": self.running,
+            "countdown": self.countdown,
+            "current_score": sum(player.score for player in self.players.values()),
+            "time_remaining": self.time_remaining(),
+            "fall_interval_ms": self.fall_interval_ms(),
+            "level": self.level,
+            "player_count": len(self.players),
+            "spectator_count": len(self.spectators),
+            "is_finished": self.finished,
+            "winner_id": str(self.winner_id) if self.winner_id else None,
+        }
+
+    def fog_of_war(self) -> bool:
+        return self.game_settings.get("fog_of_war", self.game_settings["game_type"] in FOG_OF_WAR_GAME_TYPES)
+
+    def is_visible_to(self, player: Player, viewer_id: Optional[uuid.UUID], is_spectator: bool = False) -> bool:
+        """Видит ли зритель или игрок viewer_id фигуры игрока player"""
+        if is_spectator or not self.fog_of_war() or player.id == viewer_id:
+            return True
+        viewer = self.players.get(viewer_id) if viewer_id else None
+        # Союзники по команде видят фигуры друг друга
+        return viewer is not None and viewer.team is not None and viewer.team == player.team
+
+    def hides_event_details(self, event: Dict[str, Any]) -> bool:
+        """Может ли событие выглядеть по-разному для разных участников"""
+        return event["type"] in HIDDEN_EVENT_DETAILS and self.fog_of_war()
+
+    def event_view(
+        self, event: Dict[str, Any], viewer_id: Optional[uuid.UUID], is_spectator: bool = False
+    ) -> Dict[str, Any]:
+        """Событие глазами участника: подробности о фигурах соперников скрыты, как и в их состоянии"""
+        if not self.hides_event_details(event):
+            return event
+        player_fields, hidden_fields = HIDDEN_EVENT_DETAILS[event["type"]]
+        for field in player_fields:
+            player = self.players.get(uuid.UUID(event[field])) if event.get(field) else None
+            if player and self.is_visible_to(player, viewer_id, is_spectator):
+                return event
+        return {**event, **{field: None for field in hidden_fields}}
+
+    def player_view(self, viewer_id: Optional[uuid.UUID], is_spectator: bool = False) -> GameState:
+        """Состояние игры глазами участника: в режиме тумана войны фигуры соперников скрыты"""
+        state = self.get_state()
+        state["players"] = [
+            player.get_state(redacted=not self.is_visible_to(player, viewer_id, is_spectator))
+            for player in self.players_in_join_order()
+        ]
+        return state
+
+    def state_json_for(self, viewer_id: Optional[uuid.UUID]) -> str:
+        """Состояние игры в JSON для конкретного участника; без тумана войны — общее для всех"""
+        if not self.fog_of_war():
+            return self.state_json()
+        is_spectator = viewer_id not in self.players
+        return json.dumps(self.player_view(viewer_id, is_spectator), sort_keys=True)
+
+    def players_in_join_order(self) -> List[Player]:
+        return sorted(self.players.values(), key=lambda player: player.join_order)
+
+    def state_json(self) -> str:
+        """Состояние игры в JSON.
+
+        Сериализация кэшируется и повторяется только после mark_changed
+        или когда меняется оставшееся время. Игроки идут в порядке входа, ключи
+        отсортированы, так что одинаковое состояние всегда даёт одинаковую строку.
+        """
+        key = (self.revision, self.time_remaining())
+        if self._state_json is None or self._state_json[0] != key:
+            self._state_json = (key, json.dumps(self.get_state(), sort_keys=True))
+        return self._state_json[1]
+
+    def summary(self) -> GameSummary:
+        """Краткое описание игры для списка игр"""
+        return {
+            "game_id": str(self.id),
+            "game_type": self.game_settings["game_type"],
+            "difficulty": self.game_settings["difficulty"],
+            "player_count": len(self.players),
+            "max_players": self.game_settings["max_players"],
+            "spectator_count": len(self.spectators),
+            "is_running": self.running,
+        }
+
+    async def track_piece(self, block_id: uuid.UUID, piece: Tetromino) -> None:
+        self.pieces[block_id] = piece
+
+    def forget_block(self, block_id: uuid.UUID) -> bool:
+        """Убирает ссылки на блок, которого больше нет в физике; True, если они были"""
+        found = self.pieces.pop(block_id, None) is not None
+        self.piece_input_seqs.pop(block_id, None)
+        for player in self.players.values():
+            if block_id in player.block_ids:
+                player.block_ids.remove(block_id)
+                player.block_scores.pop(block_id, None)
+                found = True
+            if player.current_block_id == block_id:
+                player.current_block_id = None
+        return found
+
+    async def sync_piece(self, block_id: uuid.UUID, position: Tuple[float, float], rotation: float) -> None:
+        if piece := self.pieces.get(block_id):
+            piece.sync_from_physics(position, rotation)
+
+    async def rotate_piece(self, block_id: uuid.UUID, clockwise: bool = True) -> bool:
+        if piece := self.pieces.get(block_id):
+            return self.grid.try_rotate(piece, clockwise)
+        return False
+
+    async def move_piece(self, block_id: uuid.UUID, dx: int, dy: int) -> bool:
+        """Сдвигает фигуру, если новое место свободно"""
+        piece = self.pieces.get(block_id)
+        if not piece:
+            return False
+        candidate = Tetromino(piece.tetromino_type, piece.x + dx, piece.y + dy, piece.rotation)
+        if not self.grid.can_place(candidate):
+            return False
+        piece.x, piece.y = candidate.x, candidate.y
+        return True
+
+    async def drop_piece(self, block_id: uuid.UUID) -> int:
+        """Опускает фигуру до упора и возвращает число пройденных клеток"""
+        distance = 0
+        while await self.move_piece(block_id, 0, 1):
+            distance += 1
+        return distance
+
+    def accept_input(self, block_id: uuid.UUID, input_seq: Optional[int]) -> bool:
+        """Отмечает ввод для фигуры; False, если ввод с таким или более новым номером уже применён"""
+        if input_seq is None:
+            return True
+        last_seq = self.piece_input_seqs.get(block_id)
+        if last_seq is not None and input_seq <= last_seq:
+            return False
+        self.piece_input_seqs[block_id] = input_seq
+        return True
+
+    async def lock_piece(self, block_id: uuid.UUID) -> int:
+        """Фиксирует фигуру на поле и возвращает количество очищенных линий"""
+        piece = self.pieces.pop(block_id, None)
+        self.piece_input_seqs.pop(block_id, None)
+        if not piece:
+            return 0
+        self.grid.place(piece)
+        return self.grid.clear_full_rows()
+
     async def start(self) -> None:
         if self.running:
             return
+        self._require_transition(GameStatus.RUNNING)
         self.running = True
-        self.update_task = asyncio.create_task(self._update_loop())
+        self.started_at = time.monotonic()
+        # Бездействие отсчитывается с момента старта, а не с момента входа в лобби
+        for player in self.players.values():
+            player.last_input_at = self.started_at
+            player.idle_warned = False
+        # Фигуры опускает тик менеджера игр (advance_falling_pieces) с интервалом fall_interval()
+        self.mark_changed()
+
+    def pause(self) -> None:
+        self._require_transition(GameStatus.PAUSED)
+        self.paused_at = time.monotonic()
+        self.mark_changed()
+
+    def resume(self) -> None:
+        self._require_transition(GameStatus.RUNNING)
+        # Таймеры сдвигаются на длительность паузы: она не расходует лимит времени
+        # и не считается бездействием игроков
+        paused_for = time.monotonic() - self.paused_at
+        self.paused_at = None
+        self.started_at += paused_for
+        for player in self.players.values():
+            player.last_input_at += paused_for
+            if player.started_at is not None:
+                player.started_at += paused_for
+            if player.last_fall_at is not None:
+                player.last_fall_at += paused_for
+        self.mark_changed()
+
+    def cancel_countdown(self) -> bool:
+        """Прерывает обратный отсчёт; возвращает False, если его не было"""
+        if self.countdown is None:
+            return False
+        if self.countdown_task:
+            self.countdown_task.cancel()
+        self.countdown = None
+        self.countdown_task = None
+        self.mark_changed()
+        return True
+
+    async def cancel(self) -> None:
+        """Распускает лобби, которое так и не стартовало"""
+        self._require_transition(GameStatus.CANCELLED)
+        self.cancel_countdown()
+        self.cancelled = True
+        self.mark_changed()
 
     async def stop(self) -> None:
+        self.cancel_countdown()
         if not self.running:
             return
         self.running = False
-        if self.update_task:
-            self.update_task.cancel()
-            try:
-                await self.update_task
-            except asyncio.CancelledError:
-                pass
+        self.mark_changed()
+
+class GameManager:
+    def __init__(
+        self,
+        physics_manager: Optional[PhysicsManager] = None,
+        metrics: Optional[Metrics] = None,
+        settings: Optional[Settings] = None
+    ):
+        self.games: Dict[uuid.UUID, Game] = {}
+        self.settings = settings or Settings()
+        self.physics_manager = physics_manager
+        self.metrics = metrics or Metrics()
+        for state in GAME_STATES:
+            self.metrics.register_gauge(
+                f"games.state.{state}",
+                lambda state=state: sum(1 for game in self.games.values() if game.state() == state)
+            )
+        self.cleanup_task: Optional[asyncio.Task] = None
+        self.idle_task: Optional[asyncio.Task] = None
+        self.tick_task: Optional[asyncio.Task] = None
+        self.listeners: List[GameEventListener] = []
+        # Фоновые задачи рассылки событий; ссылки держим, чтобы задачи не собрал GC
+        self.background_tasks: Set[asyncio.Task] = set()
+        # Сводки игр для лобби: ключ "all" — все игры, "available" — открытые для входа
+        self.lobby_cache: TtlCache[List[GameSummary]] = TtlCache()
+        self.leaderboard = Leaderboard(self.settings.leaderboard_file, self.settings.leaderboard_size)
+        # Ответы на запросы таблицы рекордов по значению limit; сбрасываются при новой записи
+        self.leaderboard_cache: TtlCache[List[LeaderboardEntry]] = TtlCache()
+        # Записи файла таблицы рекордов идут по одной и в порядке снимков
+        self.leaderboard_lock = asyncio.Lock()
 
-    async def _update_loop(self) -> None:
-        while self.running:
+    def add_listener(self, listener: GameEventListener) -> None:
+        self.listeners.append(listener)
+
+    async def _emit(self, game_id: uuid.UUID, event: Dict[str, Any]) -> None:
+        for listener in self.listeners:
             try:
-                # Здесь будет логика обновления игры
-                await asyncio.sleep(self.settings.game_update_interval)
-            except asyncio.CancelledError:
-                break
+                await listener(game_id, event)
             except Exception as e:
-                logger.error(f"Error in game update loop: {e}")
+                logger.error(f"Error in game event listener: {e}")
 
-class GameManager:
-    def __init__(self):
-        self.games: Dict[uuid.UUID, Game] = {}
-        self.settings = Settings()
+    def _emit_later(self, game_id: uuid.UUID, event: Dict[str, Any]) -> None:
+        """Рассылает событие в фоне, не задерживая вызывающий код"""
+        if not self.listeners:
+            return
+        task = asyncio.create_task(self._emit(game_id, event))
+        self.background_tasks.add(task)
+        task.add_done_callback(self.background_tasks.discard)
 
-    async def create_game(self) -> uuid.UUID:
+    async def create_game(
+        self,
+        settings: Optional[Dict[str, Any]] = None,
+        creator_id: Optional[uuid.UUID] = None
+    ) -> uuid.UUID:
+        if self.active_game_count() >= self.settings.max_games:
+            raise ServerAtCapacityError(
+                f"Server is at capacity: {self.settings.max_games} games are already open"
+            )
+        if creator_id and self.games_created_by(creator_id) >= self.settings.max_games_per_user:
+            raise GameLimitExceededError(
+                f"User {creator_id} already has {self.settings.max_games_per_user} open games"
+            )
         game_id = uuid.uuid4()
-        self.games[game_id] = Game(game_id, self.settings)
+        game = Game(game_id, self.settings)
+        if settings:
+            game.set_game_settings(self._merge_settings(game, settings))
+        game.creator_id = creator_id
+        self.games[game_id] = game
+        self.metrics.increment("games.created")
+        self._lobby_changed()
         return game_id
 
+    def active_game_count(self) -> int:
+        return sum(1 for game in self.games.values() if not game.finished)
+
+    def capacity(self) -> Dict[str, int]:
+        """Текущее и максимальное число незавершённых игр"""
+        return {"current": self.active_game_count(), "max": self.settings.max_games}
+
+    def games_created_by(self, creator_id: uuid.UUID) -> int:
+        """Количество незавершённых игр, созданных пользователем"""
+        return sum(
+            1 for game in self.games.values()
+            if game.creator_id == creator_id and not game.finished
+        )
+
     async def get_game(self, game_id: uuid.UUID) -> Optional[Game]:
         return self.games.get(game_id)
 
+    async def get_game_state_json(
+        self,
+        game_id: uuid.UUID,
+        viewer_id: Optional[uuid.UUID] = None
+    ) -> Optional[str]:
+        """Сериализованное состояние игры; без тумана войны одно на всех получателей рассылки"""
+        game = self.games.get(game_id)
+        if not game:
+            return None
+        return game.state_json_for(viewer_id) if viewer_id else game.state_json()
+
     async def remove_game(self, game_id: uuid.UUID) -> None:
         if game := self.games.get(game_id):
             await game.stop()
             del self.games[game_id]
+            self._lobby_changed()
 
-    async def add_player_to_game(self, player_id: uuid.UUID, game_id: uuid.UUID) -> None:
-        if game := self.games.get(game_id):
-            await game.add_player(player_id)
+    async def add_player_to_game(
+        self,
+        player_id: uuid.UUID,
+        game_id: uuid.UUID,
+        color: Optional[str] = None,
+        team: Optional[int] = None,
+        name: Optional[str] = None
+    ) -> None:
+        game = self.games.get(game_id)
+        if not game:
+            raise GameNotFoundError(f"Game {game_id} not found")
+        await game.add_player(player_id, color, team, name)
+        self._mutated(game)
+        self._lobby_changed()
 
     async def remove_player_from_game(self, player_id: uuid.UUID, game_id: uuid.UUID) -> None:
         if game := self.games.get(game_id):
             await game.remove_player(player_id)
+            self._lobby_changed()
+            # Состав участников изменился — отсчёт начинается заново по команде хоста
+            if game.cancel_countdown():
+                self._emit_later(game_id, {"type": "countdown_cancelled"})
+            if game.active_player_count() == 0 and await game.is_running():
+                await self._finish(game)
             if await game.is_empty():
+                await self._cancel(game, "all players left")
                 await self.remove_game(game_id)
+            else:
+                self._mutated(game)
+
+    async def cleanup_empty_games(self) -> int:
+        """Удаляет незапущенные игры без игроков, созданные дольше empty_game_timeout назад"""
+        now = time.monotonic()
+        expired = [
+            game_id
+            for game_id, game in self.games.items()
+            if not game.running
+            and not game.players
+            and now - game.created_at > self.settings.empty_game_timeout
+        ]
+        for game_id in expired:
+            await self._cancel(self.games[game_id], "empty")
+            await self.remove_game(game_id)
+        return len(expired)
+
+    async def sweep_stale_games(self) -> int:
+        """Удаляет незапущенные игры без активности дольше stale_game_timeout"""
+        now = time.monotonic()
+        stale = [
+            game_id
+            for game_id, game in self.games.items()
+            if not game.running
+            and not game.finished
+            and now - game.last_activity_at > self.settings.stale_game_timeout
+        ]
+        for game_id in stale:
+            logger.info(f"Removing stale waiting game {game_id}")
+            await self._cancel(self.games[game_id], "stale")
+            await self.remove_game(game_id)
+        return len(stale)
+
+    async def record_input(self, game_id: uuid.UUID, player_id: uuid.UUID) -> None:
+        if game := self.games.get(game_id):
+            if player := game.players.get(player_id):
+                player.last_input_at = time.monotonic()
+                player.idle_warned = False
+                game.touch()
+
+    async def check_idle_players(self) -> List[uuid.UUID]:
+        """Предупреждает и исключает игроков, давно не присылавших ввод.
+
+        В режиме battle игрок выбывает, в остальных режимах удаляется из игры.
+        """
+        now = time.monotonic()
+        kicked: List[Tuple[uuid.UUID, uuid.UUID]] = []
+        for game_id, game in list(self.games.items()):
+            if not game.running or game.is_paused():
+                continue
+            for player in list(game.players.values()):
+                if not player.is_active:
+                    continue
+                idle_for = now - player.last_input_at
+                if idle_for >= self.settings.idle_timeout:
+                    kicked.append((game_id, player.id))
+                elif idle_for >= self.settings.idle_timeout - self.settings.idle_warning and not player.idle_warned:
+                    player.idle_warned = True
+                    await self._emit(game_id, {
+                        "type": "idle_warning",
+                        "player_id": str(player.id),
+                        "seconds_left": self.settings.idle_timeout - idle_for
+                    })
+
+        for game_id, player_id in kicked:
+            game = self.games.get(game_id)
+            if not game:
+                continue
+            await self._emit(game_id, {"type": "player_idle_kicked", "player_id": str(player_id)})
+            if game.game_settings["game_type"] == GameType.BATTLE:
+                game.players[player_id].is_active = False
+                game.mark_changed()
+                if game.active_player_count() == 0:
+                    await self._finish(game)
+            else:
+                await self.remove_player_from_game(player_id, game_id)
+        return [player_id for _, player_id in kicked]
+
+    async def _idle_loop(self) -> None:
+        while True:
+            try:
+                await asyncio.sleep(self.settings.idle_check_interval)
+                await self.check_idle_players()
+            except asyncio.CancelledError:
+                break
+            except Exception as e:
+                logger.error(f"Error in idle check loop: {e}")
+
+    async def _tick_loop(self) -> None:
+        while True:
+            try:
+                await asyncio.sleep(self.settings.game_update_interval)
+                for game in list(self.games.values()):
+                    if game.state() == GameStatus.RUNNING:
+                        await self.advance_falling_pieces(game.id)
+                        await self.sync_tower_heights(game.id)
+            except asyncio.CancelledError:
+                break
+            except Exception as e:
+                logger.error(f"Error in game tick loop: {e}")
+
+    async def advance_falling_pieces(self, game_id: uuid.UUID) -> int:
+        """Опускает текущие фигуры на клетку, если с прошлого шага прошёл интервал падения игры.
+
+        Фигура, которой некуда опуститься, фиксируется. Возвращает число фигур, сделавших шаг.
+        """
+        game = self.games.get(game_id)
+        if not game or game.state() != GameStatus.RUNNING:
+            return 0
+        now = time.monotonic()
+        interval = game.fall_interval()
+        advanced = 0
+        for player in list(game.players.values()):
+            block_id = player.current_block_id
+            if not player.is_active or block_id not in game.pieces:
+                continue
+            if player.last_fall_at is not None and now - player.last_fall_at < interval:
+                continue
+            player.last_fall_at = now
+            if await game.move_piece(block_id, 0, 1):
+                await self._sync_block_to_piece(game, block_id)
+            else:
+                await self.lock_block(game_id, block_id)
+            advanced += 1
+        if advanced:
+            self._mutated(game)
+        return advanced
+
+    async def sync_tower_heights(self, game_id: uuid.UUID) -> None:
+        """Пересчитывает высоту башен игроков по положению их зафиксированных блоков в физике"""
+        game = self.games.get(game_id)
+        if not game or not self.physics_manager:
+            return
+        for player in list(game.players.values()):
+            height = 0.0
+            for block_id in list(player.block_ids):
+                if block_id == player.current_block_id:
+                    continue
+                try:
+                    _, y = await self.get_block_position(game_id, block_id)
+                except BlockNotFoundError:
+                    continue
+                # Ось y направлена вниз, позиция блока — его верхний край
+                height = max(height, game.grid.height - y)
+            if height == player.tower_height:
+                continue
+            player.tower_height = height
+            game.mark_changed()
+            if player.take_report():
+                self._emit_later(game_id, {"type": "player_changed", "player_id": str(player.id)})
+
+    async def _cleanup_loop(self) -> None:
+        while True:
+            try:
+                await asyncio.sleep(self.settings.game_cleanup_interval)
+                await self.cleanup_empty_games()
+                await self.sweep_stale_games()
+            except asyncio.CancelledError:
+                break
+            except Exception as e:
+                logger.error(f"Error in game cleanup loop: {e}")
+
+    async def add_spectator_to_game(self, user_id: uuid.UUID, game_id: uuid.UUID) -> None:
+        require_feature(self.settings.enable_spectators, "spectators")
+        if game := self.games.get(game_id):
+            await game.add_spectator(user_id)
+            self._lobby_changed()
+
+    async def remove_spectator_from_game(self, user_id: uuid.UUID, game_id: uuid.UUID) -> None:
+        if game := self.games.get(game_id):
+            await game.remove_spectator(user_id)
+            self._lobby_changed()
+
+    async def get_game_summaries(self) -> List[GameSummary]:
+        summaries = self._cached_summaries("all", lambda game: True)
+        return summaries[:self.settings.max_games_listed]
+
+    async def get_available_games(
+        self,
+        game_type: Optional[str] = None,
+        difficulty: Optional[str] = None
+    ) -> List[GameSummary]:
+        """Игры, к которым можно присоединиться, с фильтром по типу и сложности"""
+        summaries = [
+            summary
+            for summary in self._cached_summaries("available", Game.can_join)
+            if (game_type is None or summary["game_type"] == game_type)
+            and (difficulty is None or summary["difficulty"] == difficulty)
+        ]
+        return summaries[:self.settings.max_games_listed]
+
+    def _cached_summaries(self, key: str, include: Callable[[Game], bool]) -> List[GameSummary]:
+        def scan() -> List[GameSummary]:
+            self.metrics.increment("games.lobby_scans")
+            return [game.summary() for game in self.games.values() if include(game)]
+        return self.lobby_cache.get_or_insert_with(key, self.settings.lobby_cache_ttl, scan)
+
+    def _lobby_changed(self) -> None:
+        """Сбрасывает кэш сводок после изменений, заметных в лобби"""
+        self.lobby_cache.invalidate("all")
+        self.lobby_cache.invalidate("available")
+
+    async def finish_game(
+        self,
+        game_id: uuid.UUID,
+        winner_id: Optional[uuid.UUID] = None
+    ) -> Optional[uuid.UUID]:
+        game = self.games.get(game_id)
+        if not game:
+            raise GameNotFoundError(f"Game {game_id} not found")
+        return await self._finish(game, winner_id)
+
+    async def _finish(self, game: Game, winner_id: Optional[uuid.UUID] = None) -> Optional[uuid.UUID]:
+        if game.finished:
+            return game.winner_id
+        winner_id = await game.finish(winner_id)
+        self._mutated(game)
+        self._lobby_changed()
+        self.metrics.increment("games.finished")
+        if game.started_at is not None:
+            self.metrics.record("games.duration", time.monotonic() - game.started_at)
+        self._emit_later(game.id, {"type": "game_over", "results": game.results()})
+        await self.record_finished_game(game)
+        return winner_id
+
+    async def record_finished_game(self, game: Game) -> bool:
+        """Заносит победителя завершённой игры в таблицу рекордов; игры без победителя пропускаются"""
+        winner = game.players.get(game.winner_id) if game.winner_id else None
+        if not winner:
+            return False
+        self.leaderboard.record({
+            "game_id": str(game.id),
+            "player_id": str(winner.id),
+            "player_name": winner.name,
+            "score": winner.score,
+            "tower_height": winner.tower_height,
+            "finished_at": time.time(),
+        })
+        self.leaderboard_cache.clear()
+        # Снимок делается в цикле событий: поток записи не читает таблицу, которую меняет record
+        snapshot = self.leaderboard.dump()
+        try:
+            async with self.leaderboard_lock:
+                await asyncio.to_thread(self.leaderboard.write, snapshot)
+        except OSError as e:
+            logger.error(f"Could not save leaderboard: {e}")
+        return True
+
+    async def get_leaderboard(self, limit: int = 10) -> List[LeaderboardEntry]:
+        """Лучшие результаты по убыванию счёта; при равенстве раньше завершённые игры выше"""
+        async def compute() -> List[LeaderboardEntry]:
+            return self.leaderboard.top(limit)
+        return await self.leaderboard_cache.get_or_insert_with_async(
+            limit, self.settings.leaderboard_cache_ttl, compute
+        )
+
+    async def _cancel(self, game: Game, reason: str) -> None:
+        """Отменяет ещё не стартовавшую игру перед удалением; начатые и завершённые не трогает"""
+        if GameStatus.CANCELLED not in GAME_TRANSITIONS[game.state()]:
+            return
+        await game.cancel()
+        self.metrics.increment("games.cancelled")
+        self._lobby_changed()
+        self._emit_later(game.id, {"type": "game_cancelled", "reason": reason})
+
+    def _mutated(self, game: Game) -> None:
+        game.mark_changed()
+        self._check_invariants(game)
+
+    def _check_invariants(self, game: Game) -> None:
+        # Входные данные проверяются до изменения, поэтому нарушение здесь — ошибка сервера;
+        # клиент получает типизированную ошибку вместо падения обработчика
+        if violations := game.check_invariants():
+            message = f"Game {game.id} is inconsistent: {'; '.join(violations)}"
+            logger.error(message)
+            raise InconsistentStateError(message)
+
+    async def start_game(self, game_id: uuid.UUID) -> int:
+        """Запускает обратный отсчёт перед стартом; возвращает его длительность в секундах.
+
+        По окончании отсчёта игра запускается и все игроки получают первые фигуры.
+        """
+        game = self.games.get(game_id)
+        if not game:
+            raise GameNotFoundError(f"Game {game_id} not found")
+        game._require_transition(GameStatus.STARTING)
+        if not game.is_ready_to_start():
+            raise InvalidActionError(f"Game {game_id} has no players")
+        countdown = max(0, self.settings.start_countdown)
+        if countdown == 0:
+            await self._begin(game)
+            return 0
+        game.countdown = countdown
+        game.countdown_task = asyncio.create_task(self._run_countdown(game))
+        self._emit_later(game_id, {"type": "countdown", "remaining": countdown})
+        self._mutated(game)
+        self._lobby_changed()
+        return countdown
+
+    async def _run_countdown(self, game: Game) -> None:
+        try:
+            while game.countdown:
+                await asyncio.sleep(COUNTDOWN_TICK)
+                game.countdown -= 1
+                game.mark_changed()
+                if game.countdown:
+                    self._emit_later(game.id, {"type": "countdown", "remaining": game.countdown})
+            game.countdown = None
+            game.countdown_task = None
+            await self._begin(game)
+        except Exception as e:
+            logger.error(f"Error starting game {game.id}: {e}")
+
+    async def _begin(self, game: Game) -> None:
+        await game.start()
+        for player_id, player in list(game.players.items()):
+            if player.is_active and player.current_block_id is None:
+                await self.spawn_block(game.id, player_id)
+        self._emit_later(game.id, {"type": "game_started"})
+        self._mutated(game)
+        self._lobby_changed()
+
+    async def pause_all(self) -> int:
+        """Приостанавливает все идущие игры (например, перед обслуживанием); возвращает их число"""
+        paused = 0
+        for game in list(self.games.values()):
+            if game.state() != GameStatus.RUNNING:
+                continue
+            game.pause()
+            await self._set_pieces_active(game, False)
+            self._emit_later(game.id, {"type": "game_paused"})
+            self._mutated(game)
+            paused += 1
+        return paused
+
+    async def resume_all(self) -> int:
+        """Возобновляет все приостановленные игры; возвращает их число"""
+        resumed = 0
+        for game in list(self.games.values()):
+            if not game.is_paused():
+                continue
+            game.resume()
+            await self._set_pieces_active(game, True)
+            self._emit_later(game.id, {"type": "game_resumed"})
+            self._mutated(game)
+            resumed += 1
+        return resumed
+
+    async def _set_pieces_active(self, game: Game, is_active: bool) -> None:
+        # На паузе падающие фигуры замораживаются в физике
+        if not self.physics_manager:
+            return
+        for player in game.players.values():
+            if player.current_block_id is not None:
+                await self.physics_manager.set_block_active(player.current_block_id, is_active)
+
+    async def start_practice(self, game_id: uuid.UUID) -> Optional[uuid.UUID]:
+        """Запускает тренировочную игру и выдаёт игроку первую фигуру"""
+        game = self.games.get(game_id)
+        if not game:
+            raise GameNotFoundError(f"Game {game_id} not found")
+        if len(game.players) != 1:
+            raise InvalidActionError("Practice game must have exactly one player")
+        game.practice = True
+        await game.start()
+        self._lobby_changed()
+        return await self.spawn_block(game_id, next(iter(game.players)))
+
+    async def apply_settings(self, game_id: uuid.UUID, changes: Dict[str, Any]) -> GameSettings:
+        """Изменяет настройки игры, которая ещё не запущена"""
+        game = self.games.get(game_id)
+        if not game:
+            raise GameNotFoundError(f"Game {game_id} not found")
+        if await game.is_running() or game.countdown is not None:
+            raise InvalidActionError("Cannot change settings of a running game")
+        game.set_game_settings(self._merge_settings(game, changes))
+        game.touch()
+        game.mark_changed()
+        self._lobby_changed()
+        return game.game_settings
+
+    def _merge_settings(self, game: Game, changes: Dict[str, Any]) -> GameSettings:
+        unknown = set(changes) - set(GameSettings.__annotations__)
+        if unknown:
+            raise InvalidGameSettingsError(f"Unknown settings: {', '.join(sorted(unknown))}")
+        updated = {**game.game_settings, **changes}
+        updated["game_type"] = GameType.parse(updated["game_type"])
+        updated["difficulty"] = DifficultyLevel.parse(updated["difficulty"])
+        validate_game_settings(updated)
+        # Новые настройки не должны противоречить уже вошедшим игрокам
+        if updated["max_players"] < len(game.players):
+            raise InvalidGameSettingsError(
+                f"max_players: game already has {len(game.players)} players"
+            )
+        if updated["game_type"] == GameType.TEAM and any(
+            player.team is None for player in game.players.values()
+        ):
+            raise InvalidGameSettingsError("game_type: players who joined without a team cannot play a team game")
+        return updated
+
+    async def can_place_at(
+        self, game_id: uuid.UUID, position: Tuple[int, int], size: Tuple[int, int]
+    ) -> bool:
+        """Свободен ли на поле игры прямоугольник клеток size с левым верхним углом в position"""
+        game = self.games.get(game_id)
+        if not game:
+            raise GameNotFoundError(f"Game {game_id} not found")
+        width, height = size
+        if width <= 0 or height <= 0:
+            raise InvalidValueError(f"Region size must be positive, got {size}")
+        x, y = position
+        return game.grid.is_region_free(x, y, width, height)
+
+    async def spawn_block(self, game_id: uuid.UUID, player_id: uuid.UUID) -> Optional[uuid.UUID]:
+        """Выдаёт игроку следующую фигуру из очереди.
+
+        Возвращает id нового блока или None, если у игрока исчерпан лимит блоков
+        либо место появления занято (в этом случае игрок выбывает).
+        """
+        game = self.games.get(game_id)
+        if not game:
+            raise GameNotFoundError(f"Game {game_id} not found")
+        player = game.players.get(player_id)
+        if not player or not player.is_active:
+            raise InvalidActionError(f"Player {player_id} cannot spawn blocks")
+        if player.current_block_id is not None:
+            raise InvalidActionError("Current block has not been locked yet")
+        if len(player.block_ids) >= self.settings.max_blocks_per_player:
+            return None
+
+        tetromino_type = player.next_piece()
+        size, _ = SPAWN_CELLS[tetromino_type]
+        piece = Tetromino(tetromino_type, x=(game.grid.width - size) // 2, y=0)
+        if not game.grid.can_place(piece):
+            # Фигуре некуда появиться — башня достигла верха
+            player.is_active = False
+            game.mark_changed()
+            if game.active_player_count() == 0 and await game.is_running():
+                await self._finish(game)
+            return None
+
+        block_id = uuid.uuid4()
+        if self.physics_manager:
+            await self.physics_manager.add_block(
+                block_id, (float(piece.x), float(piece.y)), 0.0,
+                material=game.block_material(), iterations=game.physics_iterations()
+            )
+        await game.track_piece(block_id, piece)
+        player.block_ids.append(block_id)
+        player.current_block_id = block_id
+        player.last_fall_at = time.monotonic()
+        if player.started_at is None:
+            player.started_at = time.monotonic()
+        self._mutated(game)
+        return block_id
+
+    async def move_block(
+        self,
+        game_id: uuid.UUID,
+        player_id: uuid.UUID,
+        dx: int,
+        dy: int,
+        input_seq: Optional[int] = None
+    ) -> Optional[Dict[str, Any]]:
+        """Сдвигает текущую фигуру игрока и возвращает её итоговое положение.
+
+        Повторно присланный ввод (input_seq не больше уже применённого) фигуру не сдвигает.
+        """
+        game, block_id = self._current_block(game_id, player_id)
+        if block_id is None:
+            return None
+        if not game.accept_input(block_id, input_seq):
+            return piece_position(block_id, game.pieces[block_id])
+        await game.move_piece(block_id, dx, dy)
+        return await self._sync_block_to_piece(game, block_id)
+
+    async def rotate_block(
+        self,
+        game_id: uuid.UUID,
+        player_id: uuid.UUID,
+        clockwise: bool = True,
+        input_seq: Optional[int] = None
+    ) -> Optional[Dict[str, Any]]:
+        """Поворачивает текущую фигуру игрока и возвращает её итоговое положение"""
+        game, block_id = self._current_block(game_id, player_id)
+        if block_id is None:
+            return None
+        piece = game.pieces[block_id]
+        if not game.accept_input(block_id, input_seq) or not piece.can_rotate():
+            # Повтор ввода или поворот, не меняющий фигуру: физический блок не трогаем, чтобы он не дёргался
+            return piece_position(block_id, piece)
+        await game.rotate_piece(block_id, clockwise)
+        return await self._sync_block_to_piece(game, block_id)
+
+    async def drop_block(
+        self,
+        game_id: uuid.UUID,
+        player_id: uuid.UUID,
+        input_seq: Optional[int] = None
+    ) -> Dict[str, Any]:
+        """Мгновенно опускает текущую фигуру игрока до упора и фиксирует её.
+
+        Возвращает положение, в котором фигура легла. Пока игра не идёт, фигуру бросить нельзя.
+        """
+        game, block_id = self._current_block(game_id, player_id)
+        if game.state() != GameStatus.RUNNING:
+            raise InvalidActionError(f"Game {game_id} is not running")
+        if block_id is None:
+            raise InvalidActionError(f"Player {player_id} has no current block")
+        if not game.accept_input(block_id, input_seq):
+            return piece_position(block_id, game.pieces[block_id])
+        await game.drop_piece(block_id)
+        position = await self._sync_block_to_piece(game, block_id)
+        await self.lock_block(game_id, block_id)
+        return position
+
+    def _current_block(self, game_id: uuid.UUID, player_id: uuid.UUID) -> Tuple[Game, Optional[uuid.UUID]]:
+        game = self.games.get(game_id)
+        if not game:
+            raise GameNotFoundError(f"Game {game_id} not found")
+        player = game.players.get(player_id)
+        if not player or not player.is_active:
+            raise InvalidActionError(f"Player {player_id} cannot move blocks")
+        if game.is_paused():
+            raise InvalidActionError(f"Game {game_id} is paused")
+        return game, player.current_block_id
+
+    async def _sync_block_to_piece(self, game: Game, block_id: uuid.UUID) -> Dict[str, Any]:
+        # Логическая фигура авторитетна: физический блок переносится на её место
+        piece = game.pieces[block_id]
+        if self.physics_manager:
+            await self.physics_manager.update_block_position(block_id, (float(piece.x), float(piece.y)))
+            await self.physics_manager.update_block_rotation(block_id, piece.rotation * 90.0)
+        return piece_position(block_id, piece)
+
+    async def get_block_position(self, game_id: uuid.UUID, block_id: uuid.UUID) -> Tuple[float, float]:
+        """Позиция блока в физике; ссылки на исчезнувший блок при этом удаляются из игры"""
+        game = self.games.get(game_id)
+        if not game:
+            raise GameNotFoundError(f"Game {game_id} not found")
+        if not self.physics_manager:
+            raise BlockNotFoundError(f"Block {block_id} not found")
+        try:
+            return await self.physics_manager.get_block_position(block_id)
+        except BlockNotFoundError:
+            self._forget_block(game, block_id)
+            raise
+
+    def _forget_block(self, game: Game, block_id: uuid.UUID) -> None:
+        if game.forget_block(block_id):
+            logger.warning(f"Dropped dangling reference to block {block_id} in game {game.id}")
+            self._mutated(game)
+
+    async def lock_block(self, game_id: uuid.UUID, block_id: uuid.UUID) -> int:
+        """Фиксирует блок и возвращает количество очищенных линий"""
+        game = self.games.get(game_id)
+        if not game:
+            return 0
+        if self.physics_manager:
+            if block := await self.physics_manager.get_block(block_id):
+                if self.settings.snap_on_lock:
+                    # Выравниваем блок по сетке, чтобы строки были ровными
+                    x, y = block["position"]
+                    await self.physics_manager.update_block_position(
+                        block_id, (float(round(x)), float(round(y)))
+                    )
+                    await self.physics_manager.update_block_rotation(
+                        block_id, float(round(block["rotation"] / 90.0) * 90 % 360)
+                    )
+                await self.physics_manager.set_block_static(block_id, True)
+                await game.sync_piece(block_id, block["position"], block["rotation"])
+        piece = game.pieces.get(block_id)
+        completed_rows = game.grid.rows_completed_by(piece) if piece else []
+        lines_cleared = await game.lock_piece(block_id)
+
+        owner = game.block_owner(block_id)
+        if owner:
+            owner.blocks_placed += 1
+            if points := owner.add_cleared_lines(lines_cleared):
+                owner.block_scores[block_id] = points
+            game.grant_spells(owner, lines_cleared)
+            game.level = max(game.level, owner.level)
+            if owner.take_report():
+                self._emit_later(game_id, {"type": "player_changed", "player_id": str(owner.id)})
+        if piece:
+            placement = {
+                "type": "block_placed",
+                "player_id": str(owner.id) if owner else None,
+                "block_id": str(block_id),
+                "tetromino_type": piece.tetromino_type.value,
+                "x": piece.x,
+                "y": piece.y,
+                "rotation": piece.rotation,
+                "cleared_lines": bool(completed_rows),
+            }
+            if self.settings.placement_log:
+                game.placement_log.append(placement)
+            self._emit_later(game_id, placement)
+        if owner and owner.current_block_id == block_id:
+            owner.current_block_id = None
+            if (self.settings.auto_spawn or game.practice) and owner.is_active:
+                await self.spawn_block(game_id, owner.id)
+        self._mutated(game)
+        return lines_cleared
+
+    async def apply_wind(
+        self, game_id: uuid.UUID, player_id: uuid.UUID, direction: Tuple[float, float], strength: float
+    ) -> int:
+        """Направленное заклинание ветра: толкает блоки башни игрока, включая уже поставленные.
+
+        Фигуры соперников, оказавшиеся в пределах башни, ветер не задевает.
+        """
+        game = self.games.get(game_id)
+        if not game:
+            raise GameNotFoundError(f"Game {game_id} not found")
+        player = game.players.get(player_id)
+        if not player:
+            raise PlayerNotFoundError(f"Player {player_id} not found in game {game_id}")
+        if not self.physics_manager:
+            return 0
+        positions = []
+        for block_id in list(player.block_ids):
+            if block := await self.physics_manager.get_block(block_id):
+                positions.append(block["position"])
+            else:
+                self._forget_block(game, block_id)
+        if not positions:
+            return 0
+        xs = [x for x, _ in positions]
+        ys = [y for _, y in positions]
+        return await self.physics_manager.apply_wind_region(
+            (min(xs), min(ys)), (max(xs), max(ys)), direction, strength, player.block_ids
+        )
+
+    async def transfer_block_ownership(
+        self,
+        game_id: uuid.UUID,
+        block_id: uuid.UUID,
+        from_player_id: uuid.UUID,
+        to_player_id: uuid.UUID
+    ) -> None:
+        """Передаёт уже поставленный блок другому игроку (заклинание кражи блока)"""
+        game = self.games.get(game_id)
+        if not game:
+            raise GameNotFoundError(f"Game {game_id} not found")
+        source = game.players.get(from_player_id)
+        target = game.players.get(to_player_id)
+        if not source or not target:
+            missing = to_player_id if source else from_player_id
+            raise PlayerNotFoundError(f"Player {missing} not found in game {game_id}")
+        if source is target:
+            raise InvalidActionError("Cannot transfer a block to its owner")
+        if block_id not in source.block_ids:
+            raise InvalidActionError(f"Block {block_id} does not belong to player {from_player_id}")
+        if block_id == source.current_block_id:
+            raise InvalidActionError(f"Block {block_id} is still falling")
+        if not target.is_active:
+            raise InvalidActionError(f"Player {to_player_id} is no longer in play")
+        if len(target.block_ids) >= self.settings.max_blocks_per_player:
+            raise InvalidActionError(f"Player {to_player_id} already has the maximum number of blocks")
+        source.block_ids.remove(block_id)
+        source.blocks_placed = max(0, source.blocks_placed - 1)
+        target.block_ids.append(block_id)
+        target.blocks_placed += 1
+        if points := source.block_scores.pop(block_id, 0):
+            source.score -= points
+            target.score += points
+            target.block_scores[block_id] = points
+            for player in (source, target):
+                if player.take_report():
+                    self._emit_later(game_id, {"type": "player_changed", "player_id": str(player.id)})
+        self._emit_later(game_id, {
+            "type": "block_transferred",
+            "block_id": str(block_id),
+            "from_player_id": str(from_player_id),
+            "to_player_id": str(to_player_id),
+        })
+        self._mutated(game)
+
+    async def is_running(self) -> bool:
+        return all(
+            task is not None and not task.done()
+            for task in (self.cleanup_task, self.idle_task, self.tick_task)
+        )
 
     async def start(self) -> None:
+        await asyncio.to_thread(self.leaderboard.load)
+        self.cleanup_task = asyncio.create_task(self._cleanup_loop())
+        self.idle_task = asyncio.create_task(self._idle_loop())
+        self.tick_task = asyncio.create_task(self._tick_loop())
         logger.info("Game manager started")
 
     async def stop(self) -> None:
+        for task in (self.cleanup_task, self.idle_task, self.tick_task):
+            if task:
+                task.cancel()
+                try:
+                    await task
+                except asyncio.CancelledError:
+                    pass
+
         for game in self.games.values():
             await game.stop()
         self.games.clear()
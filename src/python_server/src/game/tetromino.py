@@ -0,0 +1,177 @@
+from dataclasses import dataclass
+from enum import Enum
+from typing import Dict, Iterable, List, Set, Tuple
+
+Cell = Tuple[int, int]
+
+class TetrominoType(str, Enum):
+    I = "I"
+    J = "J"
+    L = "L"
+    O = "O"
+    S = "S"
+    T = "T"
+    Z = "Z"
+
+# Клетки фигуры в начальном состоянии внутри ограничивающего квадрата (ось y направлена вниз)
+SPAWN_CELLS: Dict[TetrominoType, Tuple[int, List[Cell]]] = {
+    TetrominoType.I: (4, [(0, 1), (1, 1), (2, 1), (3, 1)]),
+    TetrominoType.J: (3, [(0, 0), (0, 1), (1, 1), (2, 1)]),
+    TetrominoType.L: (3, [(2, 0), (0, 1), (1, 1), (2, 1)]),
+    TetrominoType.O: (2, [(0, 0), (1, 0), (0, 1), (1, 1)]),
+    TetrominoType.S: (3, [(1, 0), (2, 0), (0, 1), (1, 1)]),
+    TetrominoType.T: (3, [(1, 0), (0, 1), (1, 1), (2, 1)]),
+    TetrominoType.Z: (3, [(0, 0), (1, 0), (1, 1), (2, 1)]),
+}
+
+# Число различимых состояний поворота: O при повороте не меняется,
+# I, S и Z переключаются между горизонтальным и вертикальным положением
+ROTATION_STATES: Dict[TetrominoType, int] = {
+    TetrominoType.I: 2,
+    TetrominoType.J: 4,
+    TetrominoType.L: 4,
+    TetrominoType.O: 1,
+    TetrominoType.S: 2,
+    TetrominoType.T: 4,
+    TetrominoType.Z: 2,
+}
+
+# Смещения SRS (wall kicks) для переходов между состояниями 0, R, 2, L.
+# Заданы в стандартной нотации с осью y вверх; при применении y инвертируется.
+JLSTZ_KICKS: Dict[Tuple[int, int], List[Cell]] = {
+    (0, 1): [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+    (1, 0): [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+    (1, 2): [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+    (2, 1): [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+    (2, 3): [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+    (3, 2): [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+    (3, 0): [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+    (0, 3): [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+}
+
+I_KICKS: Dict[Tuple[int, int], List[Cell]] = {
+    (0, 1): [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],
+    (1, 0): [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
+    (1, 2): [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+    (2, 1): [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],
+    (2, 3): [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
+    (3, 2): [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],
+    (3, 0): [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],
+    (0, 3): [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+}
+
+def rotation_offsets(tetromino_type: TetrominoType, rotation: int) -> List[Cell]:
+    """Возвращает клетки фигуры для состояния поворота 0..3 (по часовой стрелке)"""
+    size, cells = SPAWN_CELLS[tetromino_type]
+    for _ in range(rotation % 4):
+        cells = [(size - 1 - y, x) for x, y in cells]
+    return sorted(cells)
+
+@dataclass
+class Tetromino:
+    """Логическая фигура: тип, состояние поворота и позиция на сетке"""
+    tetromino_type: TetrominoType
+    x: int = 0
+    y: int = 0
+    rotation: int = 0
+
+    def can_rotate(self) -> bool:
+        return ROTATION_STATES[self.tetromino_type] > 1
+
+    def offsets(self) -> List[Cell]:
+        return rotation_offsets(self.tetromino_type, self.rotation)
+
+    def cells(self) -> List[Cell]:
+        return [(self.x + dx, self.y + dy) for dx, dy in self.offsets()]
+
+    def kicks(self, clockwise: bool = True) -> List[Cell]:
+        """Возвращает смещения SRS для поворота в заданном направлении (ось y вниз).
+
+        Строка таблицы выбирается по переходу между четырьмя состояниями SRS,
+        поэтому у I, S и Z поворот против часовой стрелки не берёт смещения поворота по часовой.
+        """
+        if self.tetromino_type == TetrominoType.O:
+            return [(0, 0)]
+        table = I_KICKS if self.tetromino_type == TetrominoType.I else JLSTZ_KICKS
+        target = (self.rotation + (1 if clockwise else -1)) % 4
+        return [(dx, -dy) for dx, dy in table[(self.rotation % 4, target)]]
+
+    def sync_from_physics(self, position: Tuple[float, float], rotation: float) -> None:
+        """Приводит логическую фигуру к ближайшей клетке и повороту, кратному 90°"""
+        self.x = round(position[0])
+        self.y = round(position[1])
+        self.rotation = round(rotation / 90.0) % ROTATION_STATES[self.tetromino_type]
+
+class Grid:
+    """Логическая занятость клеток игрового поля"""
+
+    def __init__(self, width: int, height: int):
+        self.width = width
+        self.height = height
+        self.occupied: Set[Cell] = set()
+
+    def is_free(self, cell: Cell) -> bool:
+        x, y = cell
+        return 0 <= x < self.width and 0 <= y < self.height and cell not in self.occupied
+
+    def can_place_cells(self, cells: Iterable[Cell]) -> bool:
+        return all(self.is_free(cell) for cell in cells)
+
+    def can_place(self, tetromino: Tetromino) -> bool:
+        return self.can_place_cells(tetromino.cells())
+
+    def is_region_free(self, x: int, y: int, width: int, height: int) -> bool:
+        """Свободен ли прямоугольник клеток целиком и лежит ли он внутри поля"""
+        return self.can_place_cells(
+            (cx, cy) for cx in range(x, x + width) for cy in range(y, y + height)
+        )
+
+    def try_rotate(self, tetromino: Tetromino, clockwise: bool = True) -> bool:
+        """Поворачивает фигуру с учётом смещений SRS; возвращает False, если места нет.
+
+        Поворот фигуры, которая от него не меняется (O), всегда успешен и ничего не делает.
+        """
+        if not tetromino.can_rotate():
+            return True
+        states = ROTATION_STATES[tetromino.tetromino_type]
+        target = (tetromino.rotation + (1 if clockwise else -1)) % states
+        for dx, dy in tetromino.kicks(clockwise):
+            candidate = Tetromino(
+                tetromino.tetromino_type, tetromino.x + dx, tetromino.y + dy, target
+            )
+            if self.can_place(candidate):
+                tetromino.x, tetromino.y, tetromino.rotation = candidate.x, candidate.y, target
+                return True
+        return False
+
+    def place(self, tetromino: Tetromino) -> None:
+        self.occupied.update(tetromino.cells())
+
+    def rows_completed_by(self, tetromino: Tetromino) -> List[int]:
+        """Строки, которые станут заполненными после установки фигуры"""
+        cells = set(tetromino.cells())
+        rows = sorted({y for _, y in cells})
+        return [
+            y for y in rows
+            if all((x, y) in self.occupied or (x, y) in cells for x in range(self.width))
+        ]
+
+    def full_rows(self) -> List[int]:
+        return [
+            y for y in range(self.height)
+            if all((x, y) in self.occupied for x in range(self.width))
+        ]
+
+    def clear_full_rows(self) -> int:
+        """Удаляет заполненные строки, сдвигая верхние вниз; возвращает их количество"""
+        rows = self.full_rows()
+        if not rows:
+            return 0
+        remaining: Set[Cell] = set()
+        for x, y in self.occupied:
+            if y in rows:
+                continue
+            shift = sum(1 for row in rows if row > y)
+            remaining.add((x, y + shift))
+        self.occupied = remaining
+        return len(rows)
@@ -1,16 +1,56 @@
 from enum import Enum
-from typing import TypedDict, Optional
-from uuid import UUID
+from typing import Any, NotRequired, TypedDict, Optional
+from ..exceptions import InvalidValueError
 
-class GameType(str, Enum):
+class WireEnum(str, Enum):
+    """Перечисление, которое передаётся по сети строковым значением"""
+
+    @classmethod
+    def parse(cls, value: Any) -> "WireEnum":
+        if isinstance(value, cls):
+            return value
+        if isinstance(value, str):
+            for member in cls:
+                if member.value == value.strip().lower():
+                    return member
+        allowed = ", ".join(member.value for member in cls)
+        raise InvalidValueError(f"Unknown {cls.__name__} '{value}', expected one of: {allowed}")
+
+    def __str__(self) -> str:
+        return self.value
+
+class GameType(WireEnum):
     CLASSIC = "classic"
     BATTLE = "battle"
     COOPERATIVE = "cooperative"
+    TEAM = "team"
+    # Игра на очки без ограничений: скорость растёт с уровнем, конец — только когда фигуре некуда появиться
+    ENDLESS = "endless"
 
-class DifficultyLevel(str, Enum):
+class DifficultyLevel(WireEnum):
     EASY = "easy"
     MEDIUM = "medium"
     HARD = "hard"
+    # Параметры задаются явно в custom_difficulty настроек игры
+    CUSTOM = "custom"
+
+class GameStatus(WireEnum):
+    WAITING = "waiting"  # лобби, игроки собираются
+    STARTING = "starting"  # идёт обратный отсчёт перед стартом
+    RUNNING = "running"
+    PAUSED = "paused"  # приостановлена, например на время обслуживания сервера
+    FINISHED = "finished"
+    CANCELLED = "cancelled"  # лобби распущено до старта
+
+class SpellType(WireEnum):
+    LIGHT = "light"  # помогающие заклинания
+    DARK = "dark"  # мешающие заклинания
+
+class DifficultyParameters(TypedDict):
+    fall_interval_ms: int  # интервал падения фигуры на клетку
+    friction: float
+    restitution: float
+    spell_frequency: float  # доля очищенных линий, за которые выдаётся заклинание
 
 class GameSettings(TypedDict):
     game_type: GameType
@@ -18,18 +58,85 @@ class GameSettings(TypedDict):
     max_players: int
     time_limit: Optional[int]  # в секундах
     score_limit: Optional[int]
+    target_height: NotRequired[int]  # в клетках
+    garbage_enabled: NotRequired[bool]
+    special_block_probability: NotRequired[float]
+    spells_enabled: NotRequired[bool]
+    custom_difficulty: NotRequired[DifficultyParameters]  # только для DifficultyLevel.CUSTOM
+    physics_iterations: NotRequired[int]  # подшаги физики; по умолчанию PHYSICS_ITERATIONS
+    field_width: NotRequired[int]  # в клетках; по умолчанию GAME_FIELD_WIDTH
+    field_height: NotRequired[int]  # в клетках; по умолчанию GAME_FIELD_HEIGHT
+    fog_of_war: NotRequired[bool]  # скрывать от игрока фигуры соперников; по умолчанию по типу игры
 
 class GameState(TypedDict):
-    game_id: UUID
-    players: list[UUID]
+    game_id: str
+    players: list["PlayerState"]
     settings: GameSettings
+    state: GameStatus
     is_running: bool
+    countdown: Optional[int]  # секунд до старта, пока идёт обратный отсчёт
     current_score: int
     time_remaining: Optional[int]  # в секундах
+    fall_interval_ms: int
+    level: int
+    player_count: int
+    spectator_count: int
+    is_finished: bool
+    winner_id: Optional[str]
+
+class GameSummary(TypedDict):
+    game_id: str
+    game_type: GameType
+    difficulty: DifficultyLevel
+    player_count: int
+    max_players: int
+    spectator_count: int
+    is_running: bool
+
+class PlayerStats(TypedDict):
+    blocks_placed: int
+    blocks_destroyed: int
+    efficiency: float  # placed / (placed + destroyed)
+    tower_height: float
+    score: int
+    spells_cast: int
+    spells_granted: int  # за очищенные линии, по spell_frequency сложности
+    score_rate: float  # очков в минуту с первой фигуры игрока
+
+class PlayerResult(TypedDict):
+    rank: int  # с 1; победитель всегда первый
+    player_id: str
+    team: Optional[int]
+    score: int
+    tower_height: float
+    blocks_placed: int
+    lines_cleared: int
+    spells_cast: int
+
+class GameResults(TypedDict):
+    game_id: str
+    winner_id: Optional[str]
+    duration: float  # в секундах, от старта до завершения
+    standings: list[PlayerResult]
+
+class LeaderboardEntry(TypedDict):
+    game_id: str
+    player_id: str  # победитель игры
+    player_name: str
+    score: int
+    tower_height: float
+    finished_at: float  # Unix-время завершения игры, в секундах
 
 class PlayerState(TypedDict):
-    player_id: UUID
+    player_id: str
+    name: str
     score: int
     level: int
     lines_cleared: int
-    is_active: bool 
\ No newline at end of file
+    is_active: bool
+    tower_height: float
+    current_block_id: Optional[str]
+    next_pieces: list[str]
+    color: str
+    team: Optional[int]
+    stats: PlayerStats 
\ No newline at end of file
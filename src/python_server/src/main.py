@@ -1,52 +1,47 @@
 import asyncio
+import os
 import uvicorn
-from fastapi import FastAPI, WebSocket
+from fastapi import FastAPI, Header, HTTPException, Request, WebSocket
 from .logger import logger
 import uuid
-from typing import Dict, Set
+from typing import Dict, Optional, Set
 import uvicorn.logging
 from .config import Settings
-from .game.manager import GameManager
-from .session.manager import SessionManager
-from .network.manager import NetworkManager
-from .physics.manager import PhysicsManager
-from .exceptions import GameError, SessionNotFoundError, NetworkError
+from .server import GameServer
+from .cors import add_cors
+from .exceptions import GameError, InvalidValueError, SessionNotFoundError, NetworkError
+from .utils import constant_time_eq
 
 app = FastAPI(title="Tetris Game Server")
-settings = Settings()
+# Настройки можно загрузить из файла TOML/JSON/YAML, указанного в CONFIG_FILE
+config_file = os.getenv("CONFIG_FILE")
+settings = Settings.from_file(config_file) if config_file else Settings()
 
-# Инициализация менеджеров
-game_manager = GameManager()
-session_manager = SessionManager(game_manager)
-network_manager = NetworkManager(settings)
-physics_manager = PhysicsManager()
+add_cors(app, settings)
 
-# Хранение активных WebSocket соединений
-active_connections: Dict[uuid.UUID, WebSocket] = {}
+# Инициализация менеджеров
+server = GameServer(settings)
+physics_manager = server.physics_manager
+game_manager = server.game_manager
+session_manager = server.session_manager
+network_manager = server.network_manager
 
 @app.on_event("startup")
 async def startup_event():
     logger.info("Starting server...")
-    await physics_manager.start()
-    await game_manager.start()
-    await session_manager.start()
-    await network_manager.start()
-    logger.info("Server started successfully")
+    await server.start()
 
 @app.on_event("shutdown")
 async def shutdown_event():
     logger.info("Stopping server...")
-    await network_manager.stop()
-    await session_manager.stop()
-    await game_manager.stop()
-    await physics_manager.stop()
-    logger.info("Server stopped successfully")
+    await server.stop()
 
 @app.websocket("/ws")
 async def websocket_endpoint(websocket: WebSocket):
     connection_id = uuid.uuid4()
     await websocket.accept()
-    active_connections[connection_id] = websocket
+    if not await network_manager.register(connection_id, websocket):
+        return
     
     try:
         while True:
@@ -55,12 +50,59 @@ async def websocket_endpoint(websocket: WebSocket):
     except Exception as e:
         logger.error(f"WebSocket error: {e}")
     finally:
-        if connection_id in active_connections:
-            del active_connections[connection_id]
+        network_manager.disconnect(connection_id)
 
 @app.get("/health")
 async def health_check():
-    return {"status": "ok"}
+    return {
+        "status": "ok" if await server.ready() else "starting",
+        "games": server.game_manager.capacity()
+    }
+
+@app.get("/metrics")
+async def metrics():
+    if not settings.enable_metrics:
+        raise HTTPException(status_code=404, detail="Feature 'metrics' is disabled")
+    return server.metrics.export()
+
+@app.get("/games")
+async def list_games():
+    return await game_manager.get_game_summaries()
+
+@app.get("/leaderboard")
+async def leaderboard(limit: int = 10):
+    return await game_manager.get_leaderboard(limit)
+
+def require_admin(authorization: Optional[str]) -> None:
+    if not settings.admin_token:
+        raise HTTPException(status_code=404, detail="Admin API is disabled")
+    if not authorization or not constant_time_eq(authorization, f"Bearer {settings.admin_token}"):
+        raise HTTPException(status_code=401, detail="Invalid admin token")
+
+@app.get("/admin/connections")
+async def list_connections(authorization: Optional[str] = Header(None)):
+    require_admin(authorization)
+    return network_manager.connection_report()
+
+@app.post("/admin/announce")
+async def announce(request: Request, authorization: Optional[str] = Header(None)):
+    require_admin(authorization)
+    text = (await request.body()).decode("utf-8", errors="replace")
+    try:
+        delivered = await network_manager.announce(text)
+    except InvalidValueError as e:
+        raise HTTPException(status_code=400, detail=str(e))
+    return {"delivered": delivered}
+
+@app.post("/admin/pause")
+async def pause_games(authorization: Optional[str] = Header(None)):
+    require_admin(authorization)
+    return {"paused": await game_manager.pause_all()}
+
+@app.post("/admin/resume")
+async def resume_games(authorization: Optional[str] = Header(None)):
+    require_admin(authorization)
+    return {"resumed": await game_manager.resume_all()}
 
 if __name__ == "__main__":
     uvicorn.run(
@@ -0,0 +1,106 @@
+import time
+from dataclasses import dataclass
+from typing import Any, Callable, Dict, Optional
+
+@dataclass
+class Metric:
+    """Простая метрика: количество, сумма, минимум и максимум значений"""
+    name: str
+    count: int = 0
+    total: float = 0.0
+    min: Optional[float] = None
+    max: Optional[float] = None
+
+    def record(self, value: float) -> None:
+        self.count += 1
+        self.total += value
+        self.min = value if self.min is None else min(self.min, value)
+        self.max = value if self.max is None else max(self.max, value)
+
+    @property
+    def average(self) -> float:
+        return self.total / self.count if self.count else 0.0
+
+class TimerGuard:
+    """Замеряет время от создания до выхода из блока with (или вызова stop)
+    и записывает его в метрику. Время записывается и при выходе по исключению.
+    """
+
+    def __init__(self, metrics: "Metrics", name: str):
+        self.metrics = metrics
+        self.name = name
+        self.started_at = metrics.clock()
+        self.elapsed: Optional[float] = None
+
+    def stop(self) -> float:
+        # Повторная остановка не записывает время ещё раз
+        if self.elapsed is None:
+            self.elapsed = self.metrics.clock() - self.started_at
+            self.metrics.record(self.name, self.elapsed)
+        return self.elapsed
+
+    def __enter__(self) -> "TimerGuard":
+        return self
+
+    def __exit__(self, *exc_info: Any) -> None:
+        self.stop()
+
+@dataclass
+class MetricsSnapshot:
+    """Значения счётчиков на момент снимка"""
+    taken_at: float
+    counts: Dict[str, int]
+
+class Metrics:
+    def __init__(self, clock: Callable[[], float] = time.monotonic):
+        self.clock = clock
+        self.metrics: Dict[str, Metric] = {}
+        # Значения, вычисляемые в момент чтения (например, текущее число игр)
+        self.gauges: Dict[str, Callable[[], float]] = {}
+
+    def get(self, name: str) -> Metric:
+        if name not in self.metrics:
+            self.metrics[name] = Metric(name)
+        return self.metrics[name]
+
+    def increment(self, name: str) -> None:
+        self.get(name).record(1.0)
+
+    def record(self, name: str, value: float) -> None:
+        self.get(name).record(value)
+
+    def start_timer(self, name: str) -> TimerGuard:
+        return TimerGuard(self, name)
+
+    def register_gauge(self, name: str, read: Callable[[], float]) -> None:
+        self.gauges[name] = read
+
+    def export(self) -> Dict[str, Any]:
+        """Текущие значения всех метрик для отдачи наружу"""
+        result: Dict[str, Any] = {
+            name: {
+                "count": metric.count,
+                "total": metric.total,
+                "min": metric.min,
+                "max": metric.max,
+                "average": metric.average,
+            }
+            for name, metric in self.metrics.items()
+        }
+        for name, read in self.gauges.items():
+            result[name] = read()
+        return result
+
+    def snapshot(self) -> MetricsSnapshot:
+        return MetricsSnapshot(
+            taken_at=self.clock(),
+            counts={name: metric.count for name, metric in self.metrics.items()}
+        )
+
+    def rate_since(self, snapshot: MetricsSnapshot, name: str) -> float:
+        """Количество событий в секунду с момента снимка"""
+        elapsed = self.clock() - snapshot.taken_at
+        if elapsed <= 0:
+            return 0.0
+        current = self.metrics[name].count if name in self.metrics else 0
+        return (current - snapshot.counts.get(name, 0)) / elapsed
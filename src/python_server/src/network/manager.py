@@ -1,73 +1,518 @@
+import asyncio
 import json
+import time
 import uuid
-from typing import Dict, Any, Optional
+from collections import OrderedDict, deque
+from dataclasses import asdict, dataclass
+from enum import Enum
+from typing import Awaitable, Callable, Deque, Dict, Any, List, Optional, Set, Tuple, Union
 from ..logger import logger
 from ..config import Settings
-from ..game.manager import GameManager
-from ..session.manager import SessionManager
+from ..game.manager import Game, GameManager
+from ..session.manager import Session, SessionManager
+from ..exceptions import (
+    GameError,
+    GameNotFoundError,
+    InvalidActionError,
+    InvalidValueError,
+    NotAuthenticatedError,
+    NotInGameError,
+    ProtocolError,
+    RateLimitedError
+)
+from ..utils import format_error, measure_time_async, now_unix_millis, require_feature
+
+# Действия, затрагивающие только поле самого игрока
+PLAYER_SCOPED_ACTIONS = {"move", "rotate", "drop"}
+# Смещение фигуры по направлению действия move (ось y направлена вниз)
+MOVE_OFFSETS = {"left": (-1, 0), "right": (1, 0), "down": (0, 1)}
+
+def protocol_error(message: str, position: int) -> ProtocolError:
+    """Ошибка разбора с позицией (в символах), где разбор остановился"""
+    error = ProtocolError(message)
+    error.position = position
+    return error
+
+class CloseReason(Enum):
+    """Причины закрытия соединения сервером: код и текст close-фрейма"""
+    SERVER_FULL = (1013, "server full")
+    SHUTDOWN = (1001, "server shutting down")
+    PROTOCOL_ERROR = (1002, "too many malformed messages")
+    SESSION_LIMIT = (4000, "too many connections for session")
+
+    @property
+    def code(self) -> int:
+        return self.value[0]
+
+    @property
+    def reason(self) -> str:
+        return self.value[1]
+
+@dataclass
+class ConnectionStats:
+    """Исходящий трафик соединения"""
+    messages_sent: int = 0
+    bytes_sent: int = 0
+    # Начатые, но не завершённые отправки; растут, если клиент не успевает читать
+    pending: int = 0
+    slow: bool = False
+
+class ConnectionObserver:
+    """Наблюдатель за жизненным циклом соединений, например для аналитики.
+
+    Методы вызываются синхронно из обработки соединения, поэтому должны быть быстрыми;
+    по умолчанию ничего не делают — достаточно переопределить нужные.
+    """
+
+    def on_connect(self, connection_id: uuid.UUID) -> None:
+        pass
+
+    def on_authenticate(self, connection_id: uuid.UUID, session_id: uuid.UUID) -> None:
+        pass
+
+    def on_message(self, connection_id: uuid.UUID, data: Dict[str, Any]) -> None:
+        pass
+
+    def on_disconnect(self, connection_id: uuid.UUID) -> None:
+        pass
+
+# Рассылка по игре: получает набор id игроков (None — полное состояние),
+# возвращает False, если игры уже нет
+BroadcastFn = Callable[[Set[Optional[uuid.UUID]]], Awaitable[bool]]
+
+class BroadcastWorker:
+    """Рассылает состояние одной игры в отдельной задаче, не задерживая приём сообщений.
+
+    Запросы объединяются: пока идёт рассылка, копится не больше одного запроса
+    на игрока и одного на полное состояние.
+    """
+
+    def __init__(self, broadcast: BroadcastFn):
+        self.broadcast = broadcast
+        self.pending: Set[Optional[uuid.UUID]] = set()
+        self.wakeup = asyncio.Event()
+        self.idle = asyncio.Event()
+        self.idle.set()
+        self.task = asyncio.create_task(self._run())
+
+    def request(self, player_id: Optional[uuid.UUID] = None) -> None:
+        self.pending.add(player_id)
+        self.idle.clear()
+        self.wakeup.set()
+
+    async def flush(self) -> None:
+        """Ждёт, пока не будут разосланы все запрошенные состояния"""
+        if not self.task.done():
+            await self.idle.wait()
+
+    async def stop(self) -> None:
+        self.task.cancel()
+        try:
+            await self.task
+        except asyncio.CancelledError:
+            pass
+
+    async def _run(self) -> None:
+        try:
+            while True:
+                await self.wakeup.wait()
+                self.wakeup.clear()
+                requests, self.pending = self.pending, set()
+                try:
+                    game_exists = await self.broadcast(requests)
+                except Exception as e:
+                    logger.error(f"Error broadcasting game state: {e}")
+                    game_exists = True
+                if not self.pending:
+                    self.idle.set()
+                if not game_exists:
+                    break
+        finally:
+            self.idle.set()
 
 class NetworkManager:
-    def __init__(self, settings: Settings):
+    def __init__(self, settings: Settings, observers: Optional[List[ConnectionObserver]] = None):
         self.settings = settings
+        self.observers: List[ConnectionObserver] = list(observers or [])
         self.game_manager: Optional[GameManager] = None
         self.session_manager: Optional[SessionManager] = None
         self.active_connections: Dict[uuid.UUID, Any] = {}
+        self.connection_stats: Dict[uuid.UUID, ConnectionStats] = {}
+        # Сессия, от имени которой соединение вошло в игру
+        self.connection_sessions: Dict[uuid.UUID, uuid.UUID] = {}
+        # Время последней полной рассылки состояния по играм
+        self.last_full_sync: Dict[uuid.UUID, float] = {}
+        # Время последнего запроса get_my_state по сессиям
+        self.last_resync: Dict[uuid.UUID, float] = {}
+        # Последний обработанный input_seq по соединениям
+        self.last_input_seq: Dict[uuid.UUID, int] = {}
+        # Число подряд идущих сообщений, которые не удалось разобрать
+        self.parse_failures: Dict[uuid.UUID, int] = {}
+        # (соединение, message_id клиента) -> время, когда сообщение было получено впервые;
+        # записи идут в порядке получения, поэтому устаревшие всегда в начале
+        self.seen_message_ids: OrderedDict[Tuple[uuid.UUID, str], float] = OrderedDict()
+        self.broadcast_workers: Dict[uuid.UUID, BroadcastWorker] = {}
+        # Последние игровые события с номерами, для повтора после переподключения
+        self.event_history: Dict[uuid.UUID, Deque[Dict[str, Any]]] = {}
+        self.event_seq: Dict[uuid.UUID, int] = {}
+        self.running = False
+        # Сбрасывается при остановке, чтобы новые сообщения не доходили до остальных менеджеров
+        self.accepting = True
+        self.in_flight = 0
+        self.handlers_idle = asyncio.Event()
+        self.handlers_idle.set()
+        # Ответы на запросы внутри batch копятся здесь, а не отправляются клиенту сразу
+        self.batch_responses: Dict[uuid.UUID, List[Dict[str, Any]]] = {}
 
     def set_managers(self, game_manager: GameManager, session_manager: SessionManager) -> None:
         self.game_manager = game_manager
         self.session_manager = session_manager
+        game_manager.add_listener(self._on_game_event)
+
+    def connect(self, connection_id: uuid.UUID, websocket: Any) -> None:
+        self.active_connections[connection_id] = websocket
+        self.connection_stats[connection_id] = ConnectionStats()
+        if self.observers:
+            self._notify("on_connect", connection_id)
+
+    async def register(self, connection_id: uuid.UUID, websocket: Any) -> bool:
+        """Подключает принятое соединение; при превышении лимита закрывает его и возвращает False"""
+        if not self.accepting:
+            await self._close(websocket, CloseReason.SHUTDOWN)
+            return False
+        if len(self.active_connections) >= self.settings.max_connections:
+            logger.warning(f"Connection {connection_id} rejected: server full")
+            await self._close(websocket, CloseReason.SERVER_FULL)
+            return False
+        self.connect(connection_id, websocket)
+        return True
+
+    async def close_connection(self, connection_id: uuid.UUID, reason: CloseReason) -> None:
+        if websocket := self.active_connections.get(connection_id):
+            await self._close(websocket, reason)
+        self.disconnect(connection_id)
+
+    async def _close(self, websocket: Any, reason: CloseReason) -> None:
+        try:
+            await websocket.close(code=reason.code, reason=reason.reason)
+        except Exception as e:
+            logger.error(f"Error closing connection: {e}")
+
+    def disconnect(self, connection_id: uuid.UUID) -> None:
+        websocket = self.active_connections.pop(connection_id, None)
+        self.connection_stats.pop(connection_id, None)
+        if (session_id := self.connection_sessions.pop(connection_id, None)) is not None:
+            self._forget_session(session_id)
+        self.last_input_seq.pop(connection_id, None)
+        self.parse_failures.pop(connection_id, None)
+        if websocket is not None and self.observers:
+            self._notify("on_disconnect", connection_id)
+
+    def _forget_session(self, session_id: uuid.UUID) -> None:
+        """Удаляет отметки синхронизации, когда у сессии и её игры не осталось соединений"""
+        if session_id in self.connection_sessions.values():
+            return
+        self.last_resync.pop(session_id, None)
+        session = self.session_manager.sessions.get(session_id) if self.session_manager else None
+        if not session or not session.game_id:
+            return
+        sessions = self.session_manager.sessions
+        if not any(
+            (other := sessions.get(other_id)) and other.game_id == session.game_id
+            for other_id in self.connection_sessions.values()
+        ):
+            self.last_full_sync.pop(session.game_id, None)
+
+    def _notify(self, event: str, *args: Any) -> None:
+        for observer in self.observers:
+            try:
+                getattr(observer, event)(*args)
+            except Exception as e:
+                logger.error(f"Error in connection observer {event}: {e}")
 
     async def handle_message(self, connection_id: uuid.UUID, message: str) -> None:
+        if not self.accepting:
+            logger.warning(f"Message from {connection_id} dropped: server is shutting down")
+            return
+        self.in_flight += 1
+        self.handlers_idle.clear()
         try:
-            data = json.loads(message)
-            message_type = data.get("type")
-            
-            if not message_type:
-                logger.error(f"Message type not found in message: {message}")
-                return
-
-            handler = getattr(self, f"_handle_{message_type}", None)
-            if handler:
-                await handler(connection_id, data)
-            else:
-                logger.error(f"Unknown message type: {message_type}")
+            await self._dispatch(connection_id, message)
+        finally:
+            self.in_flight -= 1
+            if not self.in_flight:
+                self.handlers_idle.set()
+
+    async def drain(self, timeout: float) -> bool:
+        """Перестаёт принимать сообщения, дожидается начатых обработчиков и рассылок.
+
+        Возвращает False, если не уложились в timeout.
+        """
+        self.accepting = False
+        try:
+            await asyncio.wait_for(self._drain(), timeout)
+        except asyncio.TimeoutError:
+            logger.warning(f"Shutdown drain timed out with {self.in_flight} handlers in flight")
+            return False
+        return True
+
+    async def _drain(self) -> None:
+        await self.handlers_idle.wait()
+        await self.flush_broadcasts()
 
-        except json.JSONDecodeError:
-            logger.error(f"Invalid JSON message: {message}")
+    async def _dispatch(self, connection_id: uuid.UUID, message: str) -> None:
+        data: Dict[str, Any] = {}
+        try:
+            try:
+                data = json.loads(message)
+            except json.JSONDecodeError as e:
+                raise protocol_error(f"Invalid JSON at position {e.pos}: {e.msg}", e.pos) from e
+            if not isinstance(data, dict):
+                raise protocol_error("Message must be a JSON object", 0)
+            self.parse_failures.pop(connection_id, None)
+            await self._process(connection_id, data)
+
+        except ProtocolError as e:
+            await self._handle_protocol_error(connection_id, e)
+        except GameError as e:
+            logger.warning(f"Game error handling message: {e}")
+            await self._send_response(connection_id, format_error(e), data.get("message_id"))
         except Exception as e:
             logger.error(f"Error handling message: {e}")
 
+    async def _process(self, connection_id: uuid.UUID, data: Dict[str, Any]) -> None:
+        message_type = data.get("type")
+        if not message_type:
+            logger.error(f"Message type not found in message: {data}")
+            return
+
+        message_id = data.get("message_id")
+        if message_id is not None and self._is_duplicate(connection_id, str(message_id)):
+            logger.warning(f"Duplicate message ignored: {message_id}")
+            return
+        if self.observers:
+            self._notify("on_message", connection_id, data)
+
+        handler = getattr(self, f"_handle_{message_type}", None)
+        if handler:
+            await measure_time_async(
+                f"handle {message_type}", handler(connection_id, data), self.settings.slow_operation_threshold
+            )
+        else:
+            logger.error(f"Unknown message type: {message_type}")
+
+    async def _handle_protocol_error(self, connection_id: uuid.UUID, error: ProtocolError) -> None:
+        """Сообщает об ошибке разбора; после серии таких ошибок подряд закрывает соединение"""
+        logger.warning(f"Malformed message from {connection_id}: {error}")
+        await self._send_response(connection_id, format_error(error))
+        failures = self.parse_failures.get(connection_id, 0) + 1
+        self.parse_failures[connection_id] = failures
+        if failures >= self.settings.max_parse_failures:
+            await self.close_connection(connection_id, CloseReason.PROTOCOL_ERROR)
+
+    async def _bind_session(self, connection_id: uuid.UUID, session_id: uuid.UUID) -> None:
+        """Привязывает соединение к сессии; сверх лимита закрываются самые старые соединения сессии"""
+        if self.connection_sessions.get(connection_id) == session_id:
+            return
+        self.connection_sessions[connection_id] = session_id
+        if self.observers:
+            self._notify("on_authenticate", connection_id, session_id)
+        bound = [
+            bound_id for bound_id, bound_session in self.connection_sessions.items()
+            if bound_session == session_id
+        ]
+        for oldest in bound[:max(0, len(bound) - self.settings.max_connections_per_session)]:
+            logger.warning(f"Closing connection {oldest}: too many connections for session {session_id}")
+            await self.close_connection(oldest, CloseReason.SESSION_LIMIT)
+
+    def _is_duplicate(self, connection_id: uuid.UUID, message_id: str) -> bool:
+        """Проверяет, получало ли соединение сообщение с таким id в пределах окна дедупликации"""
+        now = time.monotonic()
+        window = self.settings.message_dedup_window
+        while self.seen_message_ids:
+            key, seen_at = next(iter(self.seen_message_ids.items()))
+            if now - seen_at < window:
+                break
+            del self.seen_message_ids[key]
+        key = (connection_id, message_id)
+        if key in self.seen_message_ids:
+            return True
+        self.seen_message_ids[key] = now
+        return False
+
+    async def _session(self, data: Dict[str, Any]) -> Session:
+        """Сессия, указанная в сообщении; её активность при этом продлевается"""
+        raw_session_id = data.get("session_id")
+        if not raw_session_id:
+            raise NotAuthenticatedError("Message requires a session_id")
+        try:
+            session_id = uuid.UUID(str(raw_session_id))
+        except ValueError:
+            raise NotAuthenticatedError(f"Invalid session_id '{raw_session_id}'")
+        return await self.session_manager.touch_and_get(session_id)
+
+    async def _session_in_game(self, data: Dict[str, Any]) -> Tuple[Session, uuid.UUID]:
+        """Сессия из сообщения и id игры, в которой она находится"""
+        session = await self._session(data)
+        if not session.game_id:
+            raise NotInGameError(f"Session {session.id} is not in a game")
+        return session, session.game_id
+
+    async def _hosted_game(self, data: Dict[str, Any], action: str) -> Game:
+        """Игра из сообщения, если его отправил её хост"""
+        session = await self._session(data)
+        game_id = uuid.UUID(data.get("game_id"))
+        game = await self.game_manager.get_game(game_id)
+        if not game:
+            raise GameNotFoundError(f"Game {game_id} not found")
+        if game.host_id != session.user_id:
+            raise InvalidActionError(f"Only the host can {action} the game")
+        return game
+
+    async def _handle_batch(self, connection_id: uuid.UUID, data: Dict[str, Any]) -> None:
+        """Выполняет разнотипные запросы по порядку и отвечает на все одним сообщением"""
+        if connection_id in self.batch_responses:
+            raise InvalidActionError("Batches cannot be nested")
+        requests = data.get("requests")
+        if not isinstance(requests, list) or not requests:
+            raise InvalidValueError("Batch must contain a non-empty list of requests")
+        if len(requests) > self.settings.max_batch_size:
+            raise InvalidValueError(
+                f"Batch has {len(requests)} requests, at most {self.settings.max_batch_size} allowed"
+            )
+        results = [await self._process_batched(connection_id, request) for request in requests]
+        await self._send_response(connection_id, {
+            "type": "batch_result",
+            "results": results
+        }, data.get("message_id"))
+
+    async def _process_batched(self, connection_id: uuid.UUID, request: Any) -> Dict[str, Any]:
+        if not isinstance(request, dict):
+            error = InvalidValueError("Batch request must be a JSON object")
+            return {"message_id": None, "ok": False, "error": format_error(error)}
+        result: Dict[str, Any] = {"message_id": request.get("message_id")}
+        responses = self.batch_responses[connection_id] = []
+        try:
+            await self._process(connection_id, request)
+        except GameError as e:
+            logger.warning(f"Game error handling batched message: {e}")
+            result.update(ok=False, error=format_error(e))
+            return result
+        except Exception as e:
+            # Сбой одного запроса не должен обрывать остальные; подробности остаются в логе
+            logger.error(f"Error handling batched message: {e}")
+            result.update(ok=False, error=format_error(GameError("Request could not be processed")))
+            return result
+        finally:
+            self.batch_responses.pop(connection_id, None)
+        result.update(ok=True, responses=responses)
+        return result
+
     async def _handle_create_game(self, connection_id: uuid.UUID, data: Dict[str, Any]) -> None:
         if not self.game_manager:
             return
-        game_id = await self.game_manager.create_game()
+        creator_id = None
+        if self.session_manager and data.get("session_id"):
+            creator_id = (await self._session(data)).user_id
+        game_id = await self.game_manager.create_game(data.get("settings"), creator_id)
         await self._send_response(connection_id, {
             "type": "game_created",
             "game_id": str(game_id)
-        })
+        }, data.get("message_id"))
+
+    async def _handle_list_games(self, connection_id: uuid.UUID, data: Dict[str, Any]) -> None:
+        if not self.game_manager:
+            return
+        games = await self.game_manager.get_available_games(
+            data.get("game_type"), data.get("difficulty")
+        )
+        await self._send_response(connection_id, {
+            "type": "games_list",
+            "games": games
+        }, data.get("message_id"))
 
     async def _handle_join_game(self, connection_id: uuid.UUID, data: Dict[str, Any]) -> None:
         if not self.session_manager or not self.game_manager:
             return
         
         game_id = uuid.UUID(data.get("game_id"))
-        session_id = uuid.UUID(data.get("session_id"))
+        session_id = (await self._session(data)).id
         
-        await self.session_manager.join_game(session_id, game_id)
+        await self.session_manager.join_game(
+            session_id, game_id, data.get("color"), data.get("team"), data.get("name")
+        )
+        await self._bind_session(connection_id, session_id)
         await self._send_response(connection_id, {
             "type": "game_joined",
-            "game_id": str(game_id)
-        })
+            "game_id": str(game_id),
+            "reconnect_token": await self.session_manager.issue_reconnect_token(session_id)
+        }, data.get("message_id"))
+        self.request_broadcast(game_id)
+
+    async def _handle_reconnect(self, connection_id: uuid.UUID, data: Dict[str, Any]) -> None:
+        """Восстанавливает сессию по токену переподключения вместо session_id"""
+        if not self.session_manager:
+            return
+
+        session, token = await self.session_manager.reconnect(data.get("reconnect_token"))
+        await self._bind_session(connection_id, session.id)
+        await self._send_response(connection_id, {
+            "type": "reconnected",
+            "session_id": str(session.id),
+            "game_id": str(session.game_id) if session.game_id else None,
+            "reconnect_token": token
+        }, data.get("message_id"))
+        if session.game_id:
+            self.request_broadcast(session.game_id)
+
+    async def _handle_rejoin_last(self, connection_id: uuid.UUID, data: Dict[str, Any]) -> None:
+        if not self.session_manager:
+            return
+
+        session_id = (await self._session(data)).id
+        last_event_seq = data.get("last_event_seq")
+        if last_event_seq is not None:
+            require_feature(self.settings.enable_replay, "replay")
+        game_id = await self.session_manager.rejoin_last_game(session_id)
+        await self._bind_session(connection_id, session_id)
+        await self._send_response(connection_id, {
+            "type": "game_joined",
+            "game_id": str(game_id),
+            "reconnect_token": await self.session_manager.issue_reconnect_token(session_id)
+        }, data.get("message_id"))
+        # Пропущенные за время обрыва события досылаются до возобновления рассылки
+        if last_event_seq is not None:
+            game = await self.game_manager.get_game(game_id) if self.game_manager else None
+            for message in self.event_history.get(game_id, ()):
+                if message["seq"] > last_event_seq:
+                    missed = self._event_view(game, connection_id, message) if game else dict(message)
+                    await self._send_response(connection_id, missed)
+        self.request_broadcast(game_id)
+
+    async def _handle_start_practice(self, connection_id: uuid.UUID, data: Dict[str, Any]) -> None:
+        if not self.session_manager:
+            return
+
+        session_id = (await self._session(data)).id
+        game_id = await self.session_manager.start_practice(session_id)
+        await self._bind_session(connection_id, session_id)
+        await self._send_response(connection_id, {
+            "type": "practice_started",
+            "game_id": str(game_id),
+            "reconnect_token": await self.session_manager.issue_reconnect_token(session_id)
+        }, data.get("message_id"))
+        self.request_broadcast(game_id)
 
     async def _handle_leave_game(self, connection_id: uuid.UUID, data: Dict[str, Any]) -> None:
         if not self.session_manager:
             return
         
-        session_id = uuid.UUID(data.get("session_id"))
-        await self.session_manager.leave_game(session_id)
+        session_id = (await self._session(data)).id
+        game_id = await self.session_manager.leave_game(session_id)
         await self._send_response(connection_id, {
-            "type": "game_left"
-        })
+            "type": "game_left",
+            "game_id": str(game_id) if game_id else None
+        }, data.get("message_id"))
 
     async def _handle_game_action(self, connection_id: uuid.UUID, data: Dict[str, Any]) -> None:
         if not self.game_manager:
@@ -75,24 +520,354 @@ class NetworkManager:
         
         game_id = uuid.UUID(data.get("game_id"))
         action = data.get("action")
+        player_id: Optional[uuid.UUID] = None
+        input_seq = data.get("input_seq")
+        if input_seq is not None and (not isinstance(input_seq, int) or isinstance(input_seq, bool)):
+            raise InvalidValueError("input_seq must be an integer")
+
+        if self.session_manager and data.get("session_id"):
+            player_id = (await self._session(data)).user_id
+            await self.game_manager.record_input(game_id, player_id)
         
         if game := await self.game_manager.get_game(game_id):
-            # Здесь будет обработка игровых действий
-            await self._send_response(connection_id, {
-                "type": "action_processed",
-                "action": action
+            response: Dict[str, Any] = {"type": "action_processed", "action": action}
+
+            if input_seq is not None:
+                # Ввод, пришедший после более нового, устарел и не обрабатывается;
+                # клиент узнаёт последний принятый номер, чтобы сверить свои предсказания
+                last_seq = self.last_input_seq.get(connection_id)
+                if last_seq is not None and input_seq <= last_seq:
+                    logger.warning(f"Stale input_seq {input_seq} from {connection_id} rejected")
+                    await self._send_response(connection_id, {
+                        "type": "action_rejected",
+                        "action": action,
+                        "input_seq": input_seq,
+                        "last_input_seq": last_seq
+                    }, data.get("message_id"))
+                    return
+                self.last_input_seq[connection_id] = input_seq
+                response["input_seq"] = input_seq
+
+            # В ответ на перемещение сервер сообщает авторитетное положение фигуры
+            if player_id in game.players and action == "move":
+                dx, dy = MOVE_OFFSETS.get(data.get("direction"), (0, 0))
+                response["position"] = await self.game_manager.move_block(game_id, player_id, dx, dy, input_seq)
+            elif player_id in game.players and action == "rotate":
+                response["position"] = await self.game_manager.rotate_block(
+                    game_id, player_id, data.get("clockwise", True), input_seq
+                )
+            elif player_id in game.players and action == "drop":
+                response["position"] = await self.game_manager.drop_block(game_id, player_id, input_seq)
+
+            await self._send_response(connection_id, response, data.get("message_id"))
+            # Действие одного игрока меняет только его поле, остальные (например,
+            # заклинания) могут затронуть всех участников
+            if action in PLAYER_SCOPED_ACTIONS and player_id in game.players:
+                self.request_broadcast(game_id, player_id)
+            else:
+                self.request_broadcast(game_id)
+
+    def request_broadcast(self, game_id: uuid.UUID, player_id: Optional[uuid.UUID] = None) -> None:
+        """Ставит рассылку в очередь рабочей задачи игры.
+
+        player_id задаёт рассылку состояния одного игрока, без него рассылается полное состояние.
+        """
+        worker = self.broadcast_workers.get(game_id)
+        if worker is None or worker.task.done():
+            worker = BroadcastWorker(lambda requests: measure_time_async(
+                f"broadcast {game_id}", self._broadcast(game_id, requests), self.settings.slow_operation_threshold
+            ))
+            self.broadcast_workers[game_id] = worker
+        worker.request(player_id)
+
+    async def flush_broadcasts(self) -> None:
+        for worker in list(self.broadcast_workers.values()):
+            await worker.flush()
+
+    async def _broadcast(self, game_id: uuid.UUID, requests: Set[Optional[uuid.UUID]]) -> bool:
+        if not self.game_manager or not await self.game_manager.get_game(game_id):
+            self.broadcast_workers.pop(game_id, None)
+            self.event_history.pop(game_id, None)
+            self.event_seq.pop(game_id, None)
+            return False
+        # Полное состояние включает состояния всех игроков
+        if None in requests:
+            await self.broadcast_game_state(game_id)
+        else:
+            for player_id in requests:
+                await self.broadcast_player_state(game_id, player_id)
+        return True
+
+    async def _game_connections(self, game_id: uuid.UUID) -> List[uuid.UUID]:
+        """Соединения, чьи сессии сейчас находятся в игре"""
+        if not self.session_manager:
+            return []
+        sessions = self.session_manager.sessions
+        return [
+            connection_id
+            for connection_id, session_id in self.connection_sessions.items()
+            if (session := sessions.get(session_id)) and session.game_id == game_id
+        ]
+
+    async def _on_game_event(self, game_id: uuid.UUID, event: Dict[str, Any]) -> None:
+        if event["type"] == "player_changed":
+            # Табло обновляется состоянием одного игрока, а не отдельным событием
+            self.request_broadcast(game_id, uuid.UUID(event["player_id"]))
+            return
+        if event["type"] == "game_over":
+            # Итоги приходят отдельным сообщением один раз и не повторяются после переподключения
+            await self.broadcast_to_game(game_id, {"type": "game_over", **event["results"]})
+            return
+        seq = self.event_seq.get(game_id, 0) + 1
+        self.event_seq[game_id] = seq
+        message = {"type": "game_event", "game_id": str(game_id), "seq": seq, "event": event}
+        if self.settings.enable_replay:
+            history = self.event_history.setdefault(
+                game_id, deque(maxlen=self.settings.event_history_size)
+            )
+            history.append(message)
+        game = await self.game_manager.get_game(game_id) if self.game_manager else None
+        if not game or not game.hides_event_details(event):
+            await self.broadcast_to_game(game_id, message)
+            return
+        # В тумане войны у каждого участника своё представление события
+        for connection_id in await self._game_connections(game_id):
+            await self._send_text(connection_id, json.dumps(self._event_view(game, connection_id, message)))
+
+    def _event_view(self, game: Game, connection_id: uuid.UUID, message: Dict[str, Any]) -> Dict[str, Any]:
+        """Сообщение game_event в том виде, в каком его должно получить соединение"""
+        viewer_id = self._viewer(connection_id)
+        return {**message, "event": game.event_view(message["event"], viewer_id, viewer_id not in game.players)}
+
+    async def broadcast_to_game(self, game_id: uuid.UUID, message: Union[Dict[str, Any], str]) -> int:
+        """Отправляет сообщение всем участникам игры; возвращает число получателей.
+
+        Сообщение сериализуется один раз, и всем получателям уходит один и тот же текст;
+        строка считается уже сериализованным JSON.
+        """
+        connections = await self._game_connections(game_id)
+        if connections and not isinstance(message, str):
+            message = json.dumps(message)
+        for connection_id in connections:
+            await self._send_text(connection_id, message)
+        return len(connections)
+
+    async def broadcast_message(self, message: Union[Dict[str, Any], str]) -> int:
+        """Отправляет сообщение всем подключённым клиентам; возвращает число получателей"""
+        connections = list(self.active_connections)
+        if connections and not isinstance(message, str):
+            message = json.dumps(message)
+        for connection_id in connections:
+            await self._send_text(connection_id, message)
+        return len(connections)
+
+    async def announce(self, text: str) -> int:
+        """Объявление оператора (например, о технических работах) для всех клиентов"""
+        text = text.strip()
+        if not text:
+            raise InvalidValueError("Announcement text is empty")
+        if len(text) > self.settings.max_announcement_length:
+            raise InvalidValueError(
+                f"Announcement is longer than {self.settings.max_announcement_length} characters"
+            )
+        return await self.broadcast_message({"type": "announcement", "text": text})
+
+    async def broadcast_game_state(self, game_id: uuid.UUID) -> None:
+        """Рассылает участникам полное состояние игры"""
+        if not self.game_manager:
+            return
+        game = await self.game_manager.get_game(game_id)
+        if not game:
+            return
+        self.last_full_sync[game_id] = time.monotonic()
+        if not game.fog_of_war():
+            await self.broadcast_to_game(game_id, f'{{"type": "game_state", "state": {game.state_json()}}}')
+            return
+        # В тумане войны у каждого участника своё представление игры
+        for connection_id in await self._game_connections(game_id):
+            state_json = game.state_json_for(self._viewer(connection_id))
+            await self._send_text(connection_id, f'{{"type": "game_state", "state": {state_json}}}')
+
+    async def broadcast_player_state(self, game_id: uuid.UUID, player_id: uuid.UUID) -> None:
+        """Рассылает участникам состояние одного игрока.
+
+        Если полной рассылки не было дольше full_sync_interval, отправляется полное состояние.
+        """
+        if not self.game_manager:
+            return
+        game = await self.game_manager.get_game(game_id)
+        if not game or player_id not in game.players:
+            return
+        last_sync = self.last_full_sync.get(game_id)
+        if last_sync is None or time.monotonic() - last_sync >= self.settings.full_sync_interval:
+            await self.broadcast_game_state(game_id)
+            return
+        player = game.players[player_id]
+        if not game.fog_of_war():
+            await self.broadcast_to_game(game_id, {
+                "type": "player_state",
+                "game_id": str(game_id),
+                "player": player.get_state()
             })
+            return
+        for connection_id in await self._game_connections(game_id):
+            viewer_id = self._viewer(connection_id)
+            redacted = not game.is_visible_to(player, viewer_id, viewer_id not in game.players)
+            await self._send_text(connection_id, json.dumps({
+                "type": "player_state",
+                "game_id": str(game_id),
+                "player": player.get_state(redacted)
+            }))
+
+    def _viewer(self, connection_id: uuid.UUID) -> Optional[uuid.UUID]:
+        """Пользователь, от имени которого соединение находится в игре"""
+        session_id = self.connection_sessions.get(connection_id)
+        if not self.session_manager or not session_id:
+            return None
+        session = self.session_manager.sessions.get(session_id)
+        return session.user_id if session else None
+
+    async def _handle_heartbeat(self, connection_id: uuid.UUID, data: Dict[str, Any]) -> None:
+        if not self.session_manager:
+            return
+
+        session = await self._session(data)
+        # Клиент может показать обратный отсчёт и заранее продлить сессию
+        await self._send_response(connection_id, {
+            "type": "heartbeat_ack",
+            "session_id": str(session.id),
+            "time_remaining": session.time_remaining(self.settings.session_cleanup_interval)
+        }, data.get("message_id"))
+
+    async def _handle_ping(self, connection_id: uuid.UUID, data: Dict[str, Any]) -> None:
+        """Синхронизация часов: клиент по client_time и server_time оценивает задержку и смещение"""
+        client_time = data.get("client_time")
+        if client_time is not None and (isinstance(client_time, bool) or not isinstance(client_time, (int, float))):
+            raise InvalidValueError(f"client_time must be a number, got {client_time!r}")
+        await self._send_response(connection_id, {
+            "type": "pong",
+            "client_time": client_time,
+            "server_time": now_unix_millis()
+        }, data.get("message_id"))
+
+    async def _handle_get_my_state(self, connection_id: uuid.UUID, data: Dict[str, Any]) -> None:
+        """Отправляет запросившему полное состояние его игры в обход очереди рассылок"""
+        if not self.session_manager or not self.game_manager:
+            return
+
+        session, game_id = await self._session_in_game(data)
+        now = time.monotonic()
+        last_resync = self.last_resync.get(session.id)
+        if last_resync is not None and now - last_resync < self.settings.resync_interval:
+            retry_in = self.settings.resync_interval - (now - last_resync)
+            raise RateLimitedError(f"State resync requested too often, retry in {retry_in:.1f}s")
+        state_json = await self.game_manager.get_game_state_json(game_id, session.user_id)
+        if state_json is None:
+            raise GameNotFoundError(f"Game {game_id} not found")
+        self.last_resync[session.id] = now
+        message_id = data.get("message_id")
+        suffix = f', "message_id": {json.dumps(message_id)}' if message_id is not None else ""
+        text = f'{{"type": "game_state", "state": {state_json}{suffix}}}'
+        if (batched := self.batch_responses.get(connection_id)) is not None:
+            batched.append(json.loads(text))
+        else:
+            await self._send_text(connection_id, text)
+
+    async def _handle_start_game(self, connection_id: uuid.UUID, data: Dict[str, Any]) -> None:
+        if not self.session_manager or not self.game_manager:
+            return
+
+        game = await self._hosted_game(data, "start")
+        countdown = await self.game_manager.start_game(game.id)
+        await self._send_response(connection_id, {
+            "type": "game_starting",
+            "game_id": str(game.id),
+            "countdown": countdown
+        }, data.get("message_id"))
+        self.request_broadcast(game.id)
+
+    async def _handle_configure_game(self, connection_id: uuid.UUID, data: Dict[str, Any]) -> None:
+        if not self.session_manager or not self.game_manager:
+            return
+
+        game = await self._hosted_game(data, "configure")
+        settings = await self.game_manager.apply_settings(game.id, data.get("settings", {}))
+        await self._send_response(connection_id, {
+            "type": "game_configured",
+            "game_id": str(game.id),
+            "settings": settings
+        }, data.get("message_id"))
+
+    async def _send_response(
+        self,
+        connection_id: uuid.UUID,
+        data: Dict[str, Any],
+        message_id: Optional[str] = None
+    ) -> None:
+        # Ответ несёт message_id запроса, чтобы клиент мог сопоставить их
+        if message_id is not None:
+            data["message_id"] = message_id
+        if (batched := self.batch_responses.get(connection_id)) is not None:
+            batched.append(data)
+            return
+        await self._send_text(connection_id, json.dumps(data))
 
-    async def _send_response(self, connection_id: uuid.UUID, data: Dict[str, Any]) -> None:
+    async def _send_text(self, connection_id: uuid.UUID, text: str) -> None:
         if connection := self.active_connections.get(connection_id):
+            stats = self.connection_stats.setdefault(connection_id, ConnectionStats())
+            stats.pending += 1
+            self._update_slow(connection_id, stats)
             try:
-                await connection.send_json(data)
+                await connection.send_text(text)
+                stats.messages_sent += 1
+                stats.bytes_sent += len(text.encode("utf-8"))
             except Exception as e:
                 logger.error(f"Error sending response: {e}")
+            finally:
+                stats.pending -= 1
+                self._update_slow(connection_id, stats)
+
+    def _update_slow(self, connection_id: uuid.UUID, stats: ConnectionStats) -> None:
+        slow = stats.pending > self.settings.slow_client_threshold
+        if slow and not stats.slow:
+            logger.warning(f"Connection {connection_id} is slow: {stats.pending} messages pending")
+        stats.slow = slow
+
+    def connection_report(self) -> List[Dict[str, Any]]:
+        """Трафик по соединениям для администратора; медленные клиенты отмечены slow"""
+        report = []
+        for connection_id, stats in self.connection_stats.items():
+            session_id = self.connection_sessions.get(connection_id)
+            report.append({
+                "connection_id": str(connection_id),
+                "session_id": str(session_id) if session_id else None,
+                **asdict(stats)
+            })
+        return report
+
+    async def is_running(self) -> bool:
+        # Без подключённых менеджеров сообщения обрабатывать некому
+        return self.running and self.game_manager is not None and self.session_manager is not None
 
     async def start(self) -> None:
+        self.running = True
+        self.accepting = True
         logger.info("Network manager started")
 
     async def stop(self) -> None:
+        self.running = False
+        for connection_id in list(self.active_connections):
+            await self.close_connection(connection_id, CloseReason.SHUTDOWN)
+        for worker in self.broadcast_workers.values():
+            await worker.stop()
+        self.broadcast_workers.clear()
         self.active_connections.clear()
+        self.connection_sessions.clear()
+        self.last_full_sync.clear()
+        self.last_resync.clear()
+        self.last_input_seq.clear()
+        self.parse_failures.clear()
+        self.seen_message_ids.clear()
+        self.event_history.clear()
+        self.event_seq.clear()
         logger.info("Network manager stopped") 
\ No newline at end of file
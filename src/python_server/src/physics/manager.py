@@ -1,23 +1,151 @@
 import asyncio
+import json
+import math
+import time
 import uuid
-from typing import Dict, Optional, Tuple
+from dataclasses import dataclass
+from typing import Any, Collection, Dict, List, Optional, Tuple
 from ..logger import logger
 from ..config import Settings
+from ..exceptions import BlockNotFoundError, PhysicsError
+from ..utils import measure_time_async, normalize_angle_degrees
+
+# Максимальная сила, которую можно приложить к блоку за одно воздействие
+MAX_BLOCK_FORCE = 100.0
+# Угол поворота блока хранится в диапазоне [0, MAX_BLOCK_ROTATION)
+MAX_BLOCK_ROTATION = 360.0
+# Ограничения параметров материала блока
+MAX_BLOCK_DENSITY = 10.0
+MAX_BLOCK_FRICTION = 1.0
+MAX_BLOCK_RESTITUTION = 1.0
+# Ограничение числа подшагов симуляции блока за одно обновление
+MAX_PHYSICS_ITERATIONS = 64
+# Скорость, ниже которой блок считается покоящимся
+REST_VELOCITY = 1e-3
+# Сколько шагов можно догнать за один проход цикла; большее отставание отбрасывается
+MAX_CATCH_UP_STEPS = 5
+
+@dataclass(frozen=True)
+class PhysicsMaterial:
+    """Материал блока; диапазоны значений проверяются при создании"""
+    density: float = 1.0
+    friction: float = 0.5
+    restitution: float = 0.1
+    # Сенсор регистрирует касания, но не участвует в столкновениях
+    is_sensor: bool = False
+
+    def __post_init__(self):
+        if not 0 < self.density <= MAX_BLOCK_DENSITY:
+            raise PhysicsError(f"Block density must be in (0, {MAX_BLOCK_DENSITY}], got {self.density}")
+        if not 0 <= self.friction <= MAX_BLOCK_FRICTION:
+            raise PhysicsError(f"Block friction must be in [0, {MAX_BLOCK_FRICTION}], got {self.friction}")
+        if not 0 <= self.restitution <= MAX_BLOCK_RESTITUTION:
+            raise PhysicsError(
+                f"Block restitution must be in [0, {MAX_BLOCK_RESTITUTION}], got {self.restitution}"
+            )
+        if not isinstance(self.is_sensor, bool):
+            raise PhysicsError(f"is_sensor must be a boolean, got {self.is_sensor!r}")
+
+    def to_dict(self) -> Dict[str, Any]:
+        return {
+            "density": self.density,
+            "friction": self.friction,
+            "restitution": self.restitution,
+            "is_sensor": self.is_sensor,
+        }
+
+# Поля блока, которые хранятся как пары координат
+VECTOR_BLOCK_FIELDS = ("position", "size", "velocity")
+
+@dataclass(frozen=True)
+class PhysicsSnapshot:
+    """Состояние симуляции в JSON для отката (пересчёта после запоздавшего ввода)"""
+    data: str
 
 class PhysicsManager:
-    def __init__(self):
-        self.settings = Settings()
+    def __init__(self, settings: Optional[Settings] = None):
+        self.settings = settings or Settings()
         self.blocks: Dict[uuid.UUID, Dict] = {}
         self.running = False
         self.update_task: Optional[asyncio.Task] = None
+        self.gravity: Tuple[float, float] = (0.0, self.settings.physics_gravity)
+        # Гравитация, которую нужно вернуть по окончании временного эффекта
+        self.base_gravity: Optional[Tuple[float, float]] = None
+        self.gravity_effect_expires_at: Optional[float] = None
+        # Есть ли движущиеся блоки; пока их нет, шаги симуляции пропускаются
+        self.active = False
+        self.steps = 0
+        # Реальное время, ещё не отработанное шагами симуляции
+        self.lag = 0.0
+
+    async def add_block(
+        self,
+        block_id: uuid.UUID,
+        position: Tuple[float, float],
+        rotation: float,
+        size: Tuple[float, float] = (1.0, 1.0),
+        material: Optional[PhysicsMaterial] = None,
+        iterations: Optional[int] = None
+    ) -> None:
+        """Добавляет блок в симуляцию.
 
-    async def add_block(self, block_id: uuid.UUID, position: Tuple[float, float], rotation: float) -> None:
+        iterations задаёт число подшагов за обновление (например, для точной укладки
+        на высокой сложности); по умолчанию берётся PHYSICS_ITERATIONS.
+        """
+        if iterations is None:
+            iterations = self.settings.physics_iterations
+        if not isinstance(iterations, int) or not 1 <= iterations <= MAX_PHYSICS_ITERATIONS:
+            raise PhysicsError(
+                f"Physics iterations must be an integer in [1, {MAX_PHYSICS_ITERATIONS}], got {iterations}"
+            )
+        if not all(math.isfinite(value) for value in (*position, rotation)):
+            raise PhysicsError(f"Block position and rotation must be finite, got {position}, {rotation}")
+        if not all(math.isfinite(value) and value > 0 for value in size):
+            raise PhysicsError(f"Block size must be positive, got {size}")
+        material = material or PhysicsMaterial()
         self.blocks[block_id] = {
             "position": position,
-            "rotation": rotation,
+            "rotation": normalize_angle_degrees(rotation),
+            "size": size,
+            **material.to_dict(),
+            "iterations": iterations,
             "velocity": (0.0, 0.0),
-            "angular_velocity": 0.0
+            "angular_velocity": 0.0,
+            "is_static": False,
+            "is_active": True
         }
+        self.active = True
+
+    async def snapshot(self) -> PhysicsSnapshot:
+        return PhysicsSnapshot(json.dumps({
+            "gravity": self.gravity,
+            "blocks": {str(block_id): block for block_id, block in self.blocks.items()},
+        }))
+
+    async def restore(self, snapshot: PhysicsSnapshot) -> None:
+        """Возвращает блоки и гравитацию к состоянию снимка"""
+        try:
+            state = json.loads(snapshot.data)
+            blocks = {}
+            for block_id, block in state["blocks"].items():
+                for field in VECTOR_BLOCK_FIELDS:
+                    block[field] = tuple(block[field])
+                blocks[uuid.UUID(block_id)] = block
+            gravity = tuple(state["gravity"])
+        except (ValueError, KeyError, TypeError) as e:
+            raise PhysicsError(f"Invalid physics snapshot: {e}") from e
+        self.blocks = blocks
+        self.gravity = gravity
+        self.active = True
+
+    async def get_block(self, block_id: uuid.UUID) -> Optional[Dict]:
+        return self.blocks.get(block_id)
+
+    async def get_block_position(self, block_id: uuid.UUID) -> Tuple[float, float]:
+        block = self.blocks.get(block_id)
+        if block is None:
+            raise BlockNotFoundError(f"Block {block_id} not found")
+        return block["position"]
 
     async def remove_block(self, block_id: uuid.UUID) -> None:
         self.blocks.pop(block_id, None)
@@ -30,45 +158,250 @@ class PhysicsManager:
         if block := self.blocks.get(block_id):
             block["rotation"] = rotation
 
+    async def set_block_static(self, block_id: uuid.UUID, is_static: bool) -> None:
+        if block := self.blocks.get(block_id):
+            block["is_static"] = is_static
+            if is_static:
+                block["velocity"] = (0.0, 0.0)
+                block["angular_velocity"] = 0.0
+            else:
+                self.active = True
+
+    async def set_block_active(self, block_id: uuid.UUID, is_active: bool) -> None:
+        """Приостанавливает симуляцию блока (например, вне видимой части поля), сохраняя его скорость"""
+        if block := self.blocks.get(block_id):
+            block["is_active"] = is_active
+            if is_active:
+                self.active = True
+
+    async def set_gravity(self, x: float, y: float) -> None:
+        if not (math.isfinite(x) and math.isfinite(y)):
+            raise PhysicsError(f"Gravity must be finite, got {(x, y)}")
+        self.gravity = (x, y)
+        self.active = True
+
+    async def get_gravity(self) -> Tuple[float, float]:
+        return self.gravity
+
+    async def apply_gravity_effect(self, gravity: Tuple[float, float], duration: float) -> None:
+        """Временно меняет гравитацию (невесомость, обратная гравитация).
+
+        Гравитация общая для всего мира, поэтому эффект действует на всех игроков.
+        """
+        if self.base_gravity is None:
+            self.base_gravity = self.gravity
+        await self.set_gravity(*gravity)
+        self.gravity_effect_expires_at = time.monotonic() + duration
+
+    async def zero_gravity(self, duration: float) -> None:
+        await self.apply_gravity_effect((0.0, 0.0), duration)
+
+    async def reverse_gravity(self, duration: float) -> None:
+        base_x, base_y = self.base_gravity or self.gravity
+        await self.apply_gravity_effect((-base_x, -base_y), duration)
+
+    def _expire_gravity_effect(self) -> None:
+        if self.gravity_effect_expires_at is None or self.base_gravity is None:
+            return
+        if time.monotonic() >= self.gravity_effect_expires_at:
+            self.gravity = self.base_gravity
+            self.base_gravity = None
+            self.gravity_effect_expires_at = None
+            # Блоки, успевшие замереть в невесомости, снова падают
+            self.active = True
+
     async def apply_force(self, block_id: uuid.UUID, force: Tuple[float, float]) -> None:
         if block := self.blocks.get(block_id):
             vx, vy = block["velocity"]
             fx, fy = force
             block["velocity"] = (vx + fx, vy + fy)
+            self.active = True
+
+    async def apply_explosion(self, center: Tuple[float, float], radius: float, force: float) -> int:
+        """Отталкивает блоки от центра взрыва; возвращает количество задетых блоков.
+
+        Сила ограничивается MAX_BLOCK_FORCE, радиус — размерами поля,
+        чтобы ошибка в заклинании не выбрасывала блоки в бесконечность.
+        """
+        if not all(math.isfinite(coordinate) for coordinate in center):
+            raise PhysicsError(f"Explosion center must be finite, got {center}")
+        if not math.isfinite(radius) or radius <= 0:
+            raise PhysicsError(f"Explosion radius must be positive, got {radius}")
+        if not math.isfinite(force) or force < 0:
+            raise PhysicsError(f"Explosion force must be non-negative, got {force}")
+
+        radius = min(radius, float(max(self.settings.field_width, self.settings.field_height)))
+        force = min(force, MAX_BLOCK_FORCE)
+
+        affected = 0
+        cx, cy = center
+        for block in self.blocks.values():
+            if block["is_static"]:
+                continue
+            x, y = block["position"]
+            dx, dy = x - cx, y - cy
+            distance = math.hypot(dx, dy)
+            if distance > radius:
+                continue
+            # Сила убывает линейно к краю радиуса
+            strength = force * (1.0 - distance / radius)
+            if distance > 0:
+                fx, fy = dx / distance * strength, dy / distance * strength
+            else:
+                fx, fy = 0.0, -strength
+            vx, vy = block["velocity"]
+            block["velocity"] = (vx + fx, vy + fy)
+            affected += 1
+        if affected:
+            self.active = True
+        return affected
+
+    async def query_aabb(
+        self,
+        min_corner: Tuple[float, float],
+        max_corner: Tuple[float, float],
+        include_static: bool = False
+    ) -> List[uuid.UUID]:
+        """Возвращает блоки, центр которых лежит в прямоугольнике (границы включительно).
+
+        Зафиксированные блоки попадают в выборку, только если include_static.
+        """
+        (min_x, min_y), (max_x, max_y) = min_corner, max_corner
+        return [
+            block_id
+            for block_id, block in self.blocks.items()
+            if (include_static or not block["is_static"])
+            and min_x <= block["position"][0] <= max_x
+            and min_y <= block["position"][1] <= max_y
+        ]
+
+    async def apply_wind_region(
+        self,
+        min_corner: Tuple[float, float],
+        max_corner: Tuple[float, float],
+        direction: Tuple[float, float],
+        strength: float,
+        block_ids: Optional[Collection[uuid.UUID]] = None,
+    ) -> int:
+        """Толкает блоки внутри прямоугольника в направлении ветра; возвращает количество задетых блоков.
+
+        Сила, как и у взрыва, ограничивается MAX_BLOCK_FORCE. Если задан block_ids, задеваются только
+        эти блоки, в том числе зафиксированные: ветер срывает их с места.
+        """
+        if not all(math.isfinite(value) for value in (*min_corner, *max_corner)):
+            raise PhysicsError(f"Wind region must be finite, got {min_corner}..{max_corner}")
+        if min_corner[0] > max_corner[0] or min_corner[1] > max_corner[1]:
+            raise PhysicsError(f"Wind region is empty: {min_corner}..{max_corner}")
+        if not all(math.isfinite(value) for value in direction):
+            raise PhysicsError(f"Wind direction must be finite, got {direction}")
+        length = math.hypot(*direction)
+        if length == 0:
+            raise PhysicsError("Wind direction must be non-zero")
+        if not math.isfinite(strength) or strength < 0:
+            raise PhysicsError(f"Wind strength must be non-negative, got {strength}")
+
+        strength = min(strength, MAX_BLOCK_FORCE)
+        force = (direction[0] / length * strength, direction[1] / length * strength)
+        if block_ids is None:
+            affected = await self.query_aabb(min_corner, max_corner)
+        else:
+            targets = set(block_ids)
+            affected = [
+                block_id
+                for block_id in await self.query_aabb(min_corner, max_corner, include_static=True)
+                if block_id in targets
+            ]
+        for block_id in affected:
+            if self.blocks[block_id]["is_static"]:
+                await self.set_block_static(block_id, False)
+            await self.apply_force(block_id, force)
+        return len(affected)
 
     async def apply_torque(self, block_id: uuid.UUID, torque: float) -> None:
         if block := self.blocks.get(block_id):
             block["angular_velocity"] += torque
+            self.active = True
 
     async def _update_loop(self) -> None:
+        last_tick = time.monotonic()
         while self.running:
             try:
-                await self._update_physics()
-                await asyncio.sleep(self.settings.game_update_interval)
+                now = time.monotonic()
+                await self.advance(now - last_tick)
+                last_tick = now
+                await asyncio.sleep(self.settings.physics_time_step)
             except asyncio.CancelledError:
                 break
             except Exception as e:
                 logger.error(f"Error in physics update loop: {e}")
 
+    async def advance(self, elapsed: float) -> int:
+        """Продвигает симуляцию на прошедшее реальное время фиксированными шагами.
+
+        Остаток меньше шага переносится на следующий вызов, поэтому медленный проход цикла
+        не замедляет падение блоков. Возвращает число сделанных шагов.
+        """
+        step = self.settings.physics_time_step
+        self.lag = min(self.lag + max(0.0, elapsed), step * MAX_CATCH_UP_STEPS)
+        steps = 0
+        while self.lag >= step:
+            await measure_time_async(
+                "physics update", self._update_physics(), self.settings.slow_operation_threshold
+            )
+            self.lag -= step
+            steps += 1
+        return steps
+
     async def _update_physics(self) -> None:
-        for block_id, block in self.blocks.items():
-            # Применяем гравитацию
-            vx, vy = block["velocity"]
-            vy += self.settings.physics_gravity * self.settings.game_update_interval
+        self._expire_gravity_effect()
+        if self.settings.physics_pause_when_idle and not self.active:
+            return
+        self.steps += 1
+        moving = False
+        for block in self.blocks.values():
+            # Зафиксированные и приостановленные блоки не участвуют в симуляции
+            if block["is_static"] or not block["is_active"]:
+                continue
+            iterations = block["iterations"]
+            dt = self.settings.physics_time_step / iterations
+            # Трение за подшаг подобрано так, чтобы за обновление оно было тем же
+            damping = (1.0 - self.settings.physics_friction) ** (1.0 / iterations)
+            for _ in range(iterations):
+                self._step_block(block, dt, damping)
+            moving = moving or self._is_moving(block)
+        # Симуляция возобновится при появлении блока или внешнем воздействии
+        self.active = moving
 
-            # Применяем трение
-            vx *= (1.0 - self.settings.physics_friction)
-            vy *= (1.0 - self.settings.physics_friction)
+    def _is_moving(self, block: Dict) -> bool:
+        return (
+            math.hypot(*block["velocity"]) >= REST_VELOCITY
+            or abs(block["angular_velocity"]) >= REST_VELOCITY
+        )
 
-            # Обновляем позицию
-            x, y = block["position"]
-            x += vx * self.settings.game_update_interval
-            y += vy * self.settings.game_update_interval
+    def _step_block(self, block: Dict, dt: float, damping: float) -> None:
+        gx, gy = self.gravity
+
+        # Применяем гравитацию
+        vx, vy = block["velocity"]
+        vx += gx * dt
+        vy += gy * dt
+
+        # Применяем трение
+        vx *= damping
+        vy *= damping
+
+        # Обновляем позицию
+        x, y = block["position"]
+        x += vx * dt
+        y += vy * dt
+
+        # Обновляем состояние блока
+        block["position"] = (x, y)
+        block["velocity"] = (vx, vy)
+        block["angular_velocity"] *= damping
 
-            # Обновляем состояние блока
-            block["position"] = (x, y)
-            block["velocity"] = (vx, vy)
-            block["angular_velocity"] *= (1.0 - self.settings.physics_friction)
+    async def is_running(self) -> bool:
+        return self.running and self.update_task is not None and not self.update_task.done()
 
     async def start(self) -> None:
         if self.running:
@@ -0,0 +1,83 @@
+import asyncio
+from typing import List, Optional, Tuple, Any
+from .logger import logger
+from .config import Settings
+from .metrics import Metrics
+from .game.manager import GameManager
+from .session.manager import SessionManager
+from .network.manager import ConnectionObserver, NetworkManager
+from .physics.manager import PhysicsManager
+from .exceptions import StartupError
+
+READY_POLL_INTERVAL = 0.01
+
+class GameServer:
+    """Создаёт менеджеры и управляет их запуском и остановкой"""
+
+    def __init__(
+        self,
+        settings: Optional[Settings] = None,
+        observers: Optional[List[ConnectionObserver]] = None
+    ):
+        self.settings = settings or Settings()
+        self.metrics = Metrics()
+        # Все подсистемы работают с одними настройками, в том числе загруженными из файла
+        self.physics_manager = PhysicsManager(self.settings)
+        self.game_manager = GameManager(self.physics_manager, self.metrics, self.settings)
+        self.session_manager = SessionManager(self.game_manager, self.settings)
+        self.network_manager = NetworkManager(self.settings, observers)
+        self.network_manager.set_managers(self.game_manager, self.session_manager)
+        self.started: List[Any] = []
+
+    def subsystems(self) -> List[Tuple[str, Any]]:
+        # Порядок запуска; останавливаются в обратном порядке
+        return [
+            ("physics", self.physics_manager),
+            ("game", self.game_manager),
+            ("session", self.session_manager),
+            ("network", self.network_manager),
+        ]
+
+    async def ready(self) -> bool:
+        for _, manager in self.subsystems():
+            if not await manager.is_running():
+                return False
+        return True
+
+    async def _wait_ready(self, manager: Any) -> bool:
+        deadline = asyncio.get_event_loop().time() + self.settings.startup_timeout
+        while not await manager.is_running():
+            if asyncio.get_event_loop().time() >= deadline:
+                return False
+            await asyncio.sleep(READY_POLL_INTERVAL)
+        return True
+
+    async def start(self) -> None:
+        """Запускает подсистемы по очереди; при сбое останавливает уже запущенные"""
+        for name, manager in self.subsystems():
+            try:
+                await manager.start()
+            except Exception as e:
+                await self.stop()
+                raise StartupError(f"Subsystem '{name}' failed to start: {e}") from e
+            self.started.append(manager)
+            if not await self._wait_ready(manager):
+                await self.stop()
+                raise StartupError(f"Subsystem '{name}' did not become ready")
+        logger.info("Server started successfully")
+
+    async def stop(self) -> None:
+        """Прекращает приём ввода, дожидается начатых обработчиков и рассылок,
+        затем останавливает подсистемы в обратном порядке, проверяя каждую
+        """
+        await self.network_manager.drain(self.settings.shutdown_timeout)
+        while self.started:
+            manager = self.started.pop()
+            try:
+                await manager.stop()
+            except Exception as e:
+                logger.error(f"Error stopping {type(manager).__name__}: {e}")
+                continue
+            if await manager.is_running():
+                logger.error(f"{type(manager).__name__} is still running after stop")
+        logger.info("Server stopped")
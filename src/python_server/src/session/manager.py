@@ -1,15 +1,25 @@
 import asyncio
+import hashlib
+import hmac
+import secrets
 import uuid
-from typing import Dict, Optional
+from typing import Dict, Optional, Tuple
 from ..logger import logger
 from ..config import Settings
 from ..game.manager import GameManager
+from ..game.types import GameType
+from ..utils import constant_time_eq, require_feature
+from ..exceptions import GameNotFoundError, InvalidActionError, InvalidTokenError, SessionNotFoundError
 
 class Session:
     def __init__(self, session_id: uuid.UUID, user_id: uuid.UUID):
         self.id = session_id
         self.user_id = user_id
         self.game_id: Optional[uuid.UUID] = None
+        # Последняя игра сессии; сохраняется и после выхода, чтобы в неё можно было вернуться
+        self.last_game_id: Optional[uuid.UUID] = None
+        # Идентификатор единственного действующего токена переподключения
+        self.token_id: Optional[str] = None
         self.last_activity = asyncio.get_event_loop().time()
 
     def update_activity(self) -> None:
@@ -18,13 +28,18 @@ class Session:
     def is_expired(self, timeout: float) -> bool:
         return (asyncio.get_event_loop().time() - self.last_activity) > timeout
 
+    def time_remaining(self, timeout: float) -> float:
+        """Сколько секунд осталось до истечения сессии (0, если она уже истекла)"""
+        return max(0.0, timeout - (asyncio.get_event_loop().time() - self.last_activity))
+
 class SessionManager:
-    def __init__(self, game_manager: GameManager):
+    def __init__(self, game_manager: GameManager, settings: Optional[Settings] = None):
         self.sessions: Dict[uuid.UUID, Session] = {}
         self.game_manager = game_manager
-        self.settings = Settings()
+        self.settings = settings or Settings()
         self.cleanup_task: Optional[asyncio.Task] = None
         self.heartbeat_task: Optional[asyncio.Task] = None
+        self.reconnect_secret = self.settings.reconnect_secret or secrets.token_hex(32)
 
     async def create_session(self, user_id: uuid.UUID) -> uuid.UUID:
         session_id = uuid.uuid4()
@@ -34,6 +49,14 @@ class SessionManager:
     async def get_session(self, session_id: uuid.UUID) -> Optional[Session]:
         return self.sessions.get(session_id)
 
+    async def touch_and_get(self, session_id: uuid.UUID) -> Session:
+        """Возвращает сессию, отмечая активность; для обработчиков входящих сообщений"""
+        session = self.sessions.get(session_id)
+        if not session:
+            raise SessionNotFoundError(f"Session {session_id} not found")
+        session.update_activity()
+        return session
+
     async def remove_session(self, session_id: uuid.UUID) -> None:
         if session := self.sessions.get(session_id):
             if session.game_id:
@@ -42,24 +65,101 @@ class SessionManager:
                 )
             del self.sessions[session_id]
 
-    async def join_game(self, session_id: uuid.UUID, game_id: uuid.UUID) -> None:
+    def _sign(self, session_id: uuid.UUID, token_id: str) -> str:
+        payload = f"{session_id}.{token_id}".encode()
+        return hmac.new(self.reconnect_secret.encode(), payload, hashlib.sha256).hexdigest()
+
+    async def issue_reconnect_token(self, session_id: uuid.UUID) -> str:
+        """Выдаёт новый подписанный токен переподключения; прежний токен сессии перестаёт действовать"""
+        session = self.sessions.get(session_id)
+        if not session:
+            raise SessionNotFoundError(f"Session {session_id} not found")
+        session.token_id = secrets.token_urlsafe(16)
+        return f"{session_id}.{session.token_id}.{self._sign(session_id, session.token_id)}"
+
+    async def reconnect(self, token: str) -> Tuple[Session, str]:
+        """Находит сессию по токену и возвращает её вместе с токеном для следующего переподключения.
+
+        При включённой ротации токен заменяется новым, так что перехваченный токен
+        нельзя использовать после того, как владелец переподключился.
+        """
+        try:
+            raw_session_id, token_id, signature = token.split(".")
+            session_id = uuid.UUID(raw_session_id)
+        except (AttributeError, ValueError):
+            raise InvalidTokenError("Malformed reconnect token")
+        if not constant_time_eq(signature, self._sign(session_id, token_id)):
+            raise InvalidTokenError("Reconnect token signature mismatch")
+        session = self.sessions.get(session_id)
+        if not session or session.token_id is None or not constant_time_eq(token_id, session.token_id):
+            raise InvalidTokenError("Reconnect token has been revoked")
+        session.update_activity()
+        if self.settings.rotate_reconnect_tokens:
+            token = await self.issue_reconnect_token(session_id)
+        return session, token
+
+    async def join_game(
+        self,
+        session_id: uuid.UUID,
+        game_id: uuid.UUID,
+        color: Optional[str] = None,
+        team: Optional[int] = None,
+        name: Optional[str] = None
+    ) -> None:
         if session := self.sessions.get(session_id):
-            if session.game_id:
+            # Сначала занимаем место в новой игре: если она заполнена,
+            # игрок остаётся в прежней
+            await self.game_manager.add_player_to_game(session.user_id, game_id, color, team, name)
+            if session.game_id and session.game_id != game_id:
                 await self.game_manager.remove_player_from_game(
                     session.user_id, session.game_id
                 )
             session.game_id = game_id
-            await self.game_manager.add_player_to_game(session.user_id, game_id)
+            session.last_game_id = game_id
             session.update_activity()
 
-    async def leave_game(self, session_id: uuid.UUID) -> None:
+    async def leave_game(self, session_id: uuid.UUID) -> Optional[uuid.UUID]:
+        """Выводит сессию из игры и возвращает id покинутой игры"""
+        game_id: Optional[uuid.UUID] = None
         if session := self.sessions.get(session_id):
-            if session.game_id:
-                await self.game_manager.remove_player_from_game(
-                    session.user_id, session.game_id
-                )
+            game_id = session.game_id
+            if game_id:
                 session.game_id = None
+                # Опустевшая игра удаляется менеджером игр
+                await self.game_manager.remove_player_from_game(session.user_id, game_id)
             session.update_activity()
+        return game_id
+
+    async def rejoin_last_game(self, session_id: uuid.UUID) -> uuid.UUID:
+        """Возвращает сессию в последнюю игру; если игрок ещё в ней, ничего не меняет"""
+        session = self.sessions.get(session_id)
+        if not session:
+            raise SessionNotFoundError(f"Session {session_id} not found")
+        game_id = session.last_game_id
+        if not game_id:
+            raise InvalidActionError("Session has no previous game")
+        game = await self.game_manager.get_game(game_id)
+        if not game:
+            raise GameNotFoundError(f"Game {game_id} no longer exists")
+        if game.finished:
+            raise InvalidActionError(f"Game {game_id} has finished")
+        if session.user_id not in game.players and game.running:
+            raise InvalidActionError(f"Game {game_id} has already started")
+        await self.join_game(session_id, game_id)
+        return game_id
+
+    async def start_practice(self, session_id: uuid.UUID) -> uuid.UUID:
+        """Создаёт одиночную тренировочную игру, переводит в неё сессию и сразу запускает"""
+        require_feature(self.settings.enable_practice, "practice")
+        session = self.sessions.get(session_id)
+        if not session:
+            raise SessionNotFoundError(f"Session {session_id} not found")
+        game_id = await self.game_manager.create_game(
+            {"game_type": GameType.ENDLESS, "max_players": 1}, session.user_id
+        )
+        await self.join_game(session_id, game_id)
+        await self.game_manager.start_practice(game_id)
+        return game_id
 
     async def _cleanup_loop(self) -> None:
         while True:
@@ -96,6 +196,12 @@ class SessionManager:
                 # Здесь будет логика отправки heartbeat
                 session.update_activity()
 
+    async def is_running(self) -> bool:
+        return all(
+            task is not None and not task.done()
+            for task in (self.cleanup_task, self.heartbeat_task)
+        )
+
     async def start(self) -> None:
         self.cleanup_task = asyncio.create_task(self._cleanup_loop())
         self.heartbeat_task = asyncio.create_task(self._heartbeat_loop())
@@ -1,26 +1,219 @@
+import asyncio
+import hmac
 import json
+import re
+import time
 import uuid
-from typing import Any, Dict, Optional
+from datetime import timedelta
+from typing import (
+    Any,
+    Awaitable,
+    Callable,
+    Dict,
+    Generic,
+    Hashable,
+    Optional,
+    Tuple,
+    TypeVar,
+    Union
+)
+# Валидатор общий для всех Python-сервисов; сервер использует его отсюда
+from common_utils.validation import ValidationError, Validator  # noqa: F401
 from .logger import logger
-from .exceptions import GameError
+from .exceptions import FeatureDisabledError, GameError, InvalidGameSettingsError
+
+# Наибольшее число игроков в одной игре
+MAX_PLAYERS_PER_GAME = 8
+# Допустимые размеры поля в клетках
+FIELD_SIZE_RANGES = {
+    "field_width": (4, 20),
+    "field_height": (10, 40),
+}
 
 def validate_game_settings(settings: Dict[str, Any]) -> None:
-    """Проверяет корректность настроек игры"""
-    required_fields = ["game_type", "difficulty", "max_players"]
-    for field in required_fields:
-        if field not in settings:
-            raise GameError(f"Missing required field: {field}")
+    """Проверяет корректность настроек игры и сообщает обо всех ошибках сразу"""
+    validator = Validator()
+    for field in ("game_type", "difficulty", "max_players"):
+        validator.check(field in settings, field, "required field is missing")
+
+    if "max_players" in settings:
+        max_players = settings["max_players"]
+        validator.check(
+            isinstance(max_players, int) and not isinstance(max_players, bool)
+            and 1 <= max_players <= MAX_PLAYERS_PER_GAME,
+            "max_players", f"must be an integer between 1 and {MAX_PLAYERS_PER_GAME}"
+        )
+
+    for field in ("time_limit", "score_limit"):
+        if settings.get(field) is not None:
+            validator.check(
+                isinstance(settings[field], int) and settings[field] >= 0,
+                field, "must be a non-negative integer"
+            )
+
+    if "target_height" in settings:
+        target_height = settings["target_height"]
+        validator.check(
+            isinstance(target_height, int) and 1 <= target_height <= 100,
+            "target_height", "must be an integer between 1 and 100"
+        )
+
+    if "special_block_probability" in settings:
+        probability = settings["special_block_probability"]
+        validator.check(
+            isinstance(probability, (int, float)) and 0.0 <= probability <= 1.0,
+            "special_block_probability", "must be between 0 and 1"
+        )
+
+    for field in ("garbage_enabled", "spells_enabled", "fog_of_war"):
+        if field in settings:
+            validator.check(isinstance(settings[field], bool), field, "must be a boolean")
+
+    if "physics_iterations" in settings:
+        iterations = settings["physics_iterations"]
+        validator.check(
+            isinstance(iterations, int) and 1 <= iterations <= 64,
+            "physics_iterations", "must be an integer between 1 and 64"
+        )
+
+    # Поле вне этих размеров ломает расчёт места появления фигур
+    for field, (low, high) in FIELD_SIZE_RANGES.items():
+        if field in settings:
+            value = settings[field]
+            validator.check(
+                isinstance(value, int) and not isinstance(value, bool) and low <= value <= high,
+                field, f"must be an integer between {low} and {high}"
+            )
+
+    if settings.get("difficulty") == "custom":
+        _validate_custom_difficulty(validator, settings.get("custom_difficulty"))
+
+    validator.into_result(InvalidGameSettingsError)
+
+# Допустимые диапазоны параметров пользовательской сложности
+CUSTOM_DIFFICULTY_RANGES = {
+    "fall_interval_ms": (50, 10000),
+    "friction": (0.0, 1.0),
+    "restitution": (0.0, 1.0),
+    "spell_frequency": (0.0, 1.0),
+}
+
+def _validate_custom_difficulty(validator: Validator, params: Any) -> None:
+    if not validator.check(
+        isinstance(params, dict), "custom_difficulty", "is required for custom difficulty"
+    ):
+        return
+    unknown = set(params) - set(CUSTOM_DIFFICULTY_RANGES)
+    validator.check(
+        not unknown, "custom_difficulty", f"unknown parameters: {', '.join(sorted(unknown))}"
+    )
+    for name, (low, high) in CUSTOM_DIFFICULTY_RANGES.items():
+        field = f"custom_difficulty.{name}"
+        if not validator.check(name in params, field, "required field is missing"):
+            continue
+        value = params[name]
+        if name == "fall_interval_ms":
+            validator.check(
+                isinstance(value, int) and low <= value <= high,
+                field, f"must be an integer between {low} and {high}"
+            )
+        else:
+            validator.check(
+                isinstance(value, (int, float)) and low <= value <= high,
+                field, f"must be between {low:g} and {high:g}"
+            )
+
+V = TypeVar("V")
+
+class TtlCache(Generic[V]):
+    """Кэш значений с ограниченным сроком жизни (ttl в секундах)"""
+
+    def __init__(self, clock: Callable[[], float] = time.monotonic):
+        self.clock = clock
+        self.entries: Dict[Hashable, Tuple[float, V]] = {}
+        self.locks: Dict[Hashable, asyncio.Lock] = {}
+
+    def get(self, key: Hashable) -> Optional[V]:
+        entry = self.entries.get(key)
+        if entry is None:
+            return None
+        expires_at, value = entry
+        if self.clock() >= expires_at:
+            del self.entries[key]
+            return None
+        return value
 
-    if not isinstance(settings["max_players"], int) or settings["max_players"] < 1:
-        raise GameError("max_players must be a positive integer")
+    def set(self, key: Hashable, value: V, ttl: float) -> None:
+        self.entries[key] = (self.clock() + ttl, value)
 
-    if "time_limit" in settings and settings["time_limit"] is not None:
-        if not isinstance(settings["time_limit"], int) or settings["time_limit"] < 0:
-            raise GameError("time_limit must be a non-negative integer")
+    def invalidate(self, key: Hashable) -> None:
+        self.entries.pop(key, None)
 
-    if "score_limit" in settings and settings["score_limit"] is not None:
-        if not isinstance(settings["score_limit"], int) or settings["score_limit"] < 0:
-            raise GameError("score_limit must be a non-negative integer")
+    def clear(self) -> None:
+        self.entries.clear()
+
+    def get_or_insert_with(self, key: Hashable, ttl: float, compute: Callable[[], V]) -> V:
+        """Возвращает значение из кэша, а при промахе вычисляет и сохраняет его"""
+        value = self.get(key)
+        if value is None:
+            value = compute()
+            self.set(key, value, ttl)
+        return value
+
+    async def get_or_insert_with_async(
+        self,
+        key: Hashable,
+        ttl: float,
+        compute: Callable[[], Awaitable[V]]
+    ) -> V:
+        """Асинхронный вариант: одновременные промахи по одному ключу вычисляют значение один раз"""
+        value = self.get(key)
+        if value is not None:
+            return value
+        lock = self.locks.setdefault(key, asyncio.Lock())
+        async with lock:
+            # Пока ждали блокировку, значение мог вычислить другой вызов
+            value = self.get(key)
+            if value is None:
+                value = await compute()
+                self.set(key, value, ttl)
+        if not lock.locked():
+            self.locks.pop(key, None)
+        return value
+
+T = TypeVar("T")
+
+async def measure_time_async(name: str, operation: Awaitable[T], threshold: float) -> T:
+    """Выполняет операцию и предупреждает в логе, если она длилась дольше threshold секунд"""
+    started = time.perf_counter()
+    try:
+        return await operation
+    finally:
+        elapsed = time.perf_counter() - started
+        if elapsed > threshold:
+            logger.warning(f"Slow operation '{name}' took {elapsed * 1000:.1f} ms")
+
+def require_feature(enabled: bool, name: str) -> None:
+    if not enabled:
+        raise FeatureDisabledError(f"Feature '{name}' is disabled")
+
+def now_unix_millis() -> int:
+    """Текущее время сервера в миллисекундах с начала эпохи Unix"""
+    return time.time_ns() // 1_000_000
+
+def normalize_angle_degrees(angle: float) -> float:
+    """Приводит угол в градусах к диапазону [0, 360)"""
+    normalized = angle % 360.0
+    # Для очень малых отрицательных углов остаток может округлиться до 360
+    return 0.0 if normalized >= 360.0 else normalized
+
+def constant_time_eq(a: Union[str, bytes], b: Union[str, bytes]) -> bool:
+    """Сравнивает секреты (токены, подписи) за время, не зависящее от совпадающего префикса"""
+    if isinstance(a, str):
+        a = a.encode("utf-8")
+    if isinstance(b, str):
+        b = b.encode("utf-8")
+    return hmac.compare_digest(a, b)
 
 def parse_uuid(uuid_str: str) -> Optional[uuid.UUID]:
     """Преобразует строку в UUID"""
@@ -31,11 +224,18 @@ def parse_uuid(uuid_str: str) -> Optional[uuid.UUID]:
 
 def format_error(error: Exception) -> Dict[str, Any]:
     """Форматирует ошибку для отправки клиенту"""
-    return {
+    result = {
         "type": "error",
         "error": error.__class__.__name__,
         "message": str(error)
     }
+    # Ошибки проверки передаются по полям, чтобы клиент мог показать их рядом с полями формы
+    if errors := getattr(error, "errors", None):
+        result["errors"] = [{"field": e.field, "message": e.message} for e in errors]
+    # Для ошибок разбора — место в сообщении, где разбор остановился
+    if (position := getattr(error, "position", None)) is not None:
+        result["position"] = position
+    return result
 
 def format_success(data: Dict[str, Any]) -> Dict[str, Any]:
     """Форматирует успешный ответ для отправки клиенту"""
@@ -58,4 +258,26 @@ def safe_json_dumps(data: Dict[str, Any]) -> Optional[str]:
         return json.dumps(data)
     except (TypeError, ValueError) as e:
         logger.error(f"Failed to serialize to JSON: {e}")
-        return None 
\ No newline at end of file
+        return None 
+
+_DURATION_RE = re.compile(r"^\s*(\d+(?:\.\d+)?)\s*(ms|s|m|h)\s*$")
+_DURATION_UNITS = {"ms": 0.001, "s": 1.0, "m": 60.0, "h": 3600.0}
+
+def parse_duration(value: str) -> timedelta:
+    """Преобразует строку вида "500ms", "30s", "5m" или "1h" в timedelta"""
+    match = _DURATION_RE.match(value)
+    if not match:
+        raise ValueError(
+            f"Invalid duration {value!r}: expected a number followed by ms, s, m or h"
+        )
+    amount, unit = match.groups()
+    return timedelta(seconds=float(amount) * _DURATION_UNITS[unit])
+
+def parse_interval(value: Union[str, int, float]) -> float:
+    """Возвращает интервал в секундах; число без единиц трактуется как секунды"""
+    if isinstance(value, (int, float)):
+        return float(value)
+    try:
+        return float(value)
+    except ValueError:
+        return parse_duration(value).total_seconds()
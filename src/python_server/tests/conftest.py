@@ -0,0 +1,33 @@
+import json
+import os
+import sys
+
+# Сервер импортирует common_utils из каталога src; путь нужен и при запуске тестов без пакета python_server
+_SHARED_PATH = os.path.abspath(os.path.join(os.path.dirname(__file__), "..", ".."))
+if _SHARED_PATH not in sys.path:
+    sys.path.append(_SHARED_PATH)
+
+class FakeWebSocket:
+    """WebSocket, который запоминает отправленные сообщения и код закрытия"""
+
+    def __init__(self):
+        self.sent = []
+        self.closed = None
+
+    async def send_json(self, data):
+        self.sent.append(data)
+
+    async def send_text(self, text):
+        await self.send_json(json.loads(text))
+
+    async def close(self, code=1000, reason=""):
+        self.closed = (code, reason)
+
+class FakeClock:
+    """Часы, время которых тест двигает вручную через now"""
+
+    def __init__(self, now=0.0):
+        self.now = now
+
+    def __call__(self):
+        return self.now
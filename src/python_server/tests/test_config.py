@@ -0,0 +1,52 @@
+import os
+import pytest
+from pydantic import ValidationError
+from ..src.config import Settings, MAX_PHYSICS_TIME_STEP
+from ..src.exceptions import ConfigError
+
+FIXTURES = os.path.join(os.path.dirname(__file__), "fixtures")
+
+FORMATS = ("config.toml", "config.json", "config.yaml")
+
+def test_from_file_formats():
+    for name in FORMATS:
+        settings = Settings.from_file(os.path.join(FIXTURES, name))
+        assert settings.server_port == 9000
+        assert settings.log_level == "DEBUG"
+        assert settings.field_width == 12
+        assert settings.idle_timeout == 120.0
+        assert settings.auto_spawn is False
+
+def test_from_file_formats_identical():
+    loaded = [Settings.from_file(os.path.join(FIXTURES, name)).model_dump() for name in FORMATS]
+    assert loaded[0] == loaded[1] == loaded[2]
+
+def test_from_file_unknown_extension_falls_back(tmp_path):
+    path = tmp_path / "server.conf"
+    path.write_text('{"server_port": 9100}')
+    assert Settings.from_file(str(path)).server_port == 9100
+
+def test_from_file_invalid(tmp_path):
+    path = tmp_path / "server.conf"
+    path.write_text("not: [valid")
+    with pytest.raises(ConfigError) as exc_info:
+        Settings.from_file(str(path))
+    assert "TOML" in str(exc_info.value)
+    assert "JSON" in str(exc_info.value)
+
+@pytest.mark.asyncio
+async def test_save_and_reload_async(tmp_path):
+    settings = Settings.from_file(os.path.join(FIXTURES, "config.toml"))
+    for name in ("saved.toml", "saved.json", "saved.yaml"):
+        path = str(tmp_path / name)
+        await settings.save_to_file_async(path)
+        reloaded = Settings.from_file(path)
+        assert reloaded.model_dump() == settings.model_dump()
+    # Временные файлы атомарной записи не остаются в каталоге
+    assert sorted(p.name for p in tmp_path.iterdir()) == ["saved.json", "saved.toml", "saved.yaml"]
+
+def test_physics_time_step_validated():
+    assert Settings(physics_time_step="10ms").physics_time_step == pytest.approx(0.01)
+    for step in (0, -0.01, 0.001, MAX_PHYSICS_TIME_STEP * 2):
+        with pytest.raises(ValidationError):
+            Settings(physics_time_step=step)
@@ -0,0 +1,45 @@
+import importlib
+import pytest
+from fastapi.testclient import TestClient
+from ..src.cors import split_list
+
+@pytest.fixture
+def client(monkeypatch):
+    # CORS подключается при импорте main, поэтому модуль перезагружается с нужными настройками
+    monkeypatch.setenv("CORS_ALLOWED_ORIGINS", "https://tetris.example.com")
+    monkeypatch.delenv("CONFIG_FILE", raising=False)
+    from ..src import main
+    main = importlib.reload(main)
+    monkeypatch.setattr(main.settings, "admin_token", "secret")
+    return TestClient(main.app)
+
+def test_split_list():
+    assert split_list("GET, POST,,OPTIONS ") == ["GET", "POST", "OPTIONS"]
+
+def test_admin_preflight(client):
+    response = client.options("/admin/announce", headers={
+        "Origin": "https://tetris.example.com",
+        "Access-Control-Request-Method": "POST",
+        "Access-Control-Request-Headers": "Authorization",
+    })
+    assert response.status_code == 200
+    assert response.headers["access-control-allow-origin"] == "https://tetris.example.com"
+    assert "POST" in response.headers["access-control-allow-methods"]
+    assert "authorization" in response.headers["access-control-allow-headers"].lower()
+
+def test_admin_request_from_allowed_origin(client):
+    response = client.post("/admin/announce", content="Плановые работы", headers={
+        "Origin": "https://tetris.example.com",
+        "Authorization": "Bearer secret",
+    })
+    assert response.status_code == 200
+    assert response.json() == {"delivered": 0}
+    assert response.headers["access-control-allow-origin"] == "https://tetris.example.com"
+
+def test_preflight_from_unknown_origin_is_rejected(client):
+    response = client.options("/admin/announce", headers={
+        "Origin": "https://evil.example.com",
+        "Access-Control-Request-Method": "POST",
+    })
+    assert response.status_code == 400
+    assert "access-control-allow-origin" not in response.headers
@@ -1,8 +1,26 @@
 import pytest
+import asyncio
+import json
+import time
 import uuid
-from ..src.game.manager import GameManager, Game
-from ..src.game.types import GameType, DifficultyLevel, GameSettings
-from ..src.exceptions import GameNotFoundError, GameAlreadyExistsError
+from ..src.game import manager as game_module
+from ..src.game.manager import GameManager, Game, Player, LINE_CLEAR_SCORES, ENDLESS_SPEEDUP
+from ..src.physics.manager import PhysicsManager
+from ..src.game.types import GameType, DifficultyLevel, GameSettings, GameStatus, SpellType
+from ..src.game.tetromino import TetrominoType
+from ..src.exceptions import (
+    BlockNotFoundError,
+    GameNotFoundError,
+    GameAlreadyExistsError,
+    GameLimitExceededError,
+    InconsistentStateError,
+    InvalidActionError,
+    InvalidGameSettingsError,
+    FeatureDisabledError,
+    InvalidValueError,
+    PlayerNotFoundError,
+    ServerAtCapacityError
+)
 
 @pytest.fixture
 def game_manager():
@@ -73,4 +91,1240 @@ async def test_game_is_empty_after_removing_last_player(game_manager, game_setti
     await game_manager.add_player_to_game(player_id, game_id)
     await game_manager.remove_player_from_game(player_id, game_id)
     game = await game_manager.get_game(game_id)
-    assert game is None 
\ No newline at end of file
+    assert game is None 
+
+@pytest.mark.asyncio
+async def test_lock_block_snaps_to_grid():
+    physics_manager = PhysicsManager()
+    game_manager = GameManager(physics_manager)
+    game_id = await game_manager.create_game()
+    block_id = uuid.uuid4()
+    await physics_manager.add_block(block_id, (3.2, 17.8), 87.0)
+    await physics_manager.apply_force(block_id, (0.5, 2.0))
+    await game_manager.lock_block(game_id, block_id)
+    block = physics_manager.blocks[block_id]
+    assert block["position"] == (3.0, 18.0)
+    assert block["rotation"] == 90.0
+    assert block["is_static"]
+    await physics_manager._update_physics()
+    assert block["position"] == (3.0, 18.0)
+
+@pytest.mark.asyncio
+async def test_lock_block_without_snap_keeps_position():
+    physics_manager = PhysicsManager()
+    game_manager = GameManager(physics_manager)
+    game_manager.settings.snap_on_lock = False
+    game_id = await game_manager.create_game()
+    block_id = uuid.uuid4()
+    await physics_manager.add_block(block_id, (3.2, 17.8), 87.0)
+    await game_manager.lock_block(game_id, block_id)
+    block = physics_manager.blocks[block_id]
+    assert block["position"] == (3.2, 17.8)
+    assert block["is_static"]
+
+@pytest.mark.asyncio
+async def test_apply_settings_in_waiting_game(game_manager):
+    game_id = await game_manager.create_game()
+    settings = await game_manager.apply_settings(game_id, {
+        "target_height": 15,
+        "time_limit": 180,
+        "special_block_probability": 0.5,
+        "spells_enabled": False
+    })
+    game = await game_manager.get_game(game_id)
+    assert settings["target_height"] == 15
+    assert game.game_settings["time_limit"] == 180
+    assert game.game_settings["special_block_probability"] == 0.5
+    assert game.game_settings["spells_enabled"] is False
+
+@pytest.mark.asyncio
+async def test_apply_settings_rejected_after_start(game_manager):
+    game_id = await game_manager.create_game()
+    game = await game_manager.get_game(game_id)
+    await game.start()
+    with pytest.raises(InvalidActionError):
+        await game_manager.apply_settings(game_id, {"target_height": 15})
+    await game.stop()
+
+@pytest.mark.asyncio
+async def test_apply_settings_rejects_out_of_range_values(game_manager):
+    game_id = await game_manager.create_game()
+    with pytest.raises(InvalidGameSettingsError):
+        await game_manager.apply_settings(game_id, {"special_block_probability": 1.5})
+    with pytest.raises(InvalidGameSettingsError):
+        await game_manager.apply_settings(game_id, {"unknown_option": 1})
+    game = await game_manager.get_game(game_id)
+    assert game.game_settings["special_block_probability"] == 0.1
+
+@pytest.mark.asyncio
+async def test_apply_settings_must_fit_joined_players(game_manager):
+    game, _ = await _game_with_players(game_manager, 3)
+    with pytest.raises(InvalidGameSettingsError):
+        await game_manager.apply_settings(game.id, {"max_players": 2})
+    # Игроки обычной игры вошли без команды, поэтому командной она стать не может
+    with pytest.raises(InvalidGameSettingsError):
+        await game_manager.apply_settings(game.id, {"game_type": "team"})
+    assert game.game_settings["max_players"] == 4
+    assert game.game_settings["game_type"] == GameType.CLASSIC
+    await game_manager.apply_settings(game.id, {"max_players": 3})
+
+@pytest.mark.asyncio
+async def test_join_rejected_once_game_started(game_manager):
+    game_manager.settings.start_countdown = 0
+    game, players = await _game_with_players(game_manager, 1)
+    await game_manager.start_game(game.id)
+    with pytest.raises(InvalidActionError):
+        await game_manager.add_player_to_game(uuid.uuid4(), game.id)
+    assert list(game.players) == players
+    # Уже вошедший игрок может вернуться в идущую игру
+    await game_manager.add_player_to_game(players[0], game.id)
+    await game.stop()
+
+@pytest.mark.asyncio
+async def test_host_role_passes_to_next_player(game_manager):
+    game, players = await _game_with_players(game_manager, 3)
+    assert game.host_id == players[0]
+    await game_manager.remove_player_from_game(players[0], game.id)
+    assert game.host_id == players[1]
+    await game_manager.remove_player_from_game(players[2], game.id)
+    assert game.host_id == players[1]
+
+@pytest.mark.asyncio
+async def test_field_dimensions_resize_grid(game_manager):
+    game_id = await game_manager.create_game({"field_width": 12, "field_height": 30})
+    game = await game_manager.get_game(game_id)
+    assert (game.grid.width, game.grid.height) == (12, 30)
+
+    await game_manager.apply_settings(game_id, {"field_width": 4, "field_height": 40})
+    assert (game.grid.width, game.grid.height) == (4, 40)
+
+@pytest.mark.asyncio
+async def test_field_dimensions_out_of_range_rejected(game_manager):
+    with pytest.raises(InvalidGameSettingsError) as exc_info:
+        await game_manager.create_game({"field_width": 3})
+    assert [e.field for e in exc_info.value.errors] == ["field_width"]
+    assert len(game_manager.games) == 0
+
+    game_id = await game_manager.create_game()
+    with pytest.raises(InvalidGameSettingsError) as exc_info:
+        await game_manager.apply_settings(game_id, {"field_height": 41})
+    assert [e.field for e in exc_info.value.errors] == ["field_height"]
+    game = await game_manager.get_game(game_id)
+    assert game.grid.height == game_manager.settings.field_height
+
+@pytest.mark.asyncio
+async def test_apply_settings_unknown_game(game_manager):
+    with pytest.raises(GameNotFoundError):
+        await game_manager.apply_settings(uuid.uuid4(), {"target_height": 15})
+
+@pytest.mark.asyncio
+async def test_spectator_count_in_state(game_manager):
+    game_id = await game_manager.create_game()
+    await game_manager.add_player_to_game(uuid.uuid4(), game_id)
+    spectator1 = uuid.uuid4()
+    spectator2 = uuid.uuid4()
+    await game_manager.add_spectator_to_game(spectator1, game_id)
+    await game_manager.add_spectator_to_game(spectator2, game_id)
+    game = await game_manager.get_game(game_id)
+    state = game.get_state()
+    assert state["player_count"] == 1
+    assert state["spectator_count"] == 2
+
+    await game_manager.remove_spectator_from_game(spectator1, game_id)
+    summaries = await game_manager.get_game_summaries()
+    assert summaries[0]["spectator_count"] == 1
+    assert summaries[0]["player_count"] == 1
+
+@pytest.mark.asyncio
+async def test_available_games_excludes_full_and_running(game_manager):
+    open_id = await game_manager.create_game()
+    full_id = await game_manager.create_game()
+    await game_manager.apply_settings(full_id, {"max_players": 1})
+    await game_manager.add_player_to_game(uuid.uuid4(), full_id)
+    running_id = await game_manager.create_game()
+    running = await game_manager.get_game(running_id)
+    await running.start()
+
+    available = await game_manager.get_available_games()
+    assert [game["game_id"] for game in available] == [str(open_id)]
+    assert await game_manager.get_available_games(difficulty="hard") == []
+    await running.stop()
+
+@pytest.mark.asyncio
+async def test_available_games_are_cached_until_lobby_changes(game_manager):
+    game_manager.settings.max_games_listed = 2
+    for _ in range(3):
+        await game_manager.create_game()
+    scans = game_manager.metrics.get("games.lobby_scans")
+
+    first = await game_manager.get_available_games()
+    assert len(first) == 2
+    await game_manager.get_available_games()
+    await game_manager.get_available_games(difficulty="hard")
+    assert scans.count == 1
+
+    await game_manager.create_game()
+    await game_manager.get_available_games()
+    assert scans.count == 2
+
+@pytest.mark.asyncio
+async def test_player_colors_are_unique_within_game(game_manager):
+    game_id = await game_manager.create_game()
+    for _ in range(4):
+        await game_manager.add_player_to_game(uuid.uuid4(), game_id)
+    game = await game_manager.get_game(game_id)
+    colors = [player.color for player in game.players.values()]
+    assert len(set(colors)) == len(colors)
+
+@pytest.mark.asyncio
+async def test_requested_color_is_used_when_free(game_manager):
+    game_id = await game_manager.create_game()
+    first = uuid.uuid4()
+    second = uuid.uuid4()
+    await game_manager.add_player_to_game(first, game_id, color="#123456")
+    await game_manager.add_player_to_game(second, game_id, color="#123456")
+    game = await game_manager.get_game(game_id)
+    assert game.players[first].color == "#123456"
+    assert game.players[second].color != "#123456"
+
+@pytest.mark.asyncio
+async def test_team_win_resolution(game_manager):
+    game_id = await game_manager.create_game()
+    await game_manager.apply_settings(game_id, {"game_type": GameType.TEAM, "score_limit": 100})
+    players = [uuid.uuid4() for _ in range(4)]
+    for player_id in players:
+        await game_manager.add_player_to_game(player_id, game_id)
+    game = await game_manager.get_game(game_id)
+    teams = [game.players[player_id].team for player_id in players]
+    assert sorted(teams) == [0, 0, 1, 1]
+
+    team0 = [p for p in players if game.players[p].team == 0]
+    team1 = [p for p in players if game.players[p].team == 1]
+    game.players[team0[0]].score = 40
+    game.players[team1[0]].score = 90
+    assert game.winning_team() is None
+
+    game.players[team0[1]].score = 70
+    assert game.team_scores() == {0: 110, 1: 90}
+    assert game.winning_team() == 0
+
+    state = game.get_state()
+    assert {player["team"] for player in state["players"]} == {0, 1}
+
+async def _game_with_players(game_manager, count, game_type=GameType.CLASSIC):
+    game_id = await game_manager.create_game()
+    await game_manager.apply_settings(game_id, {"game_type": game_type})
+    players = [uuid.uuid4() for _ in range(count)]
+    for player_id in players:
+        await game_manager.add_player_to_game(player_id, game_id)
+    return await game_manager.get_game(game_id), players
+
+@pytest.mark.asyncio
+async def test_player_names_default_to_join_order_and_are_trimmed(game_manager):
+    game_id = await game_manager.create_game()
+    named, unnamed, long_named = uuid.uuid4(), uuid.uuid4(), uuid.uuid4()
+    await game_manager.add_player_to_game(named, game_id, name="  Alice ")
+    await game_manager.add_player_to_game(unnamed, game_id, name=42)
+    await game_manager.add_player_to_game(long_named, game_id, name="x" * 100)
+    game = await game_manager.get_game(game_id)
+    names = {p["player_id"]: p["name"] for p in game.get_state()["players"]}
+    assert names == {str(named): "Alice", str(unnamed): "Player 2", str(long_named): "x" * 32}
+
+@pytest.mark.asyncio
+async def test_determine_winner_by_score(game_manager):
+    game, players = await _game_with_players(game_manager, 3)
+    game.players[players[1]].score = 500
+    game.players[players[2]].score = 300
+    assert game.determine_winner() == players[1]
+
+@pytest.mark.asyncio
+async def test_determine_winner_tiebreak_by_tower_height(game_manager):
+    game, players = await _game_with_players(game_manager, 3, GameType.BATTLE)
+    for player_id in players:
+        game.players[player_id].score = 200
+    game.players[players[2]].tower_height = 9.5
+    game.players[players[0]].tower_height = 7.0
+    assert game.determine_winner() == players[2]
+
+@pytest.mark.asyncio
+async def test_determine_winner_tiebreak_by_join_order(game_manager):
+    game, players = await _game_with_players(game_manager, 3)
+    for player_id in players:
+        game.players[player_id].score = 200
+        game.players[player_id].tower_height = 5.0
+    assert game.determine_winner() == players[0]
+
+@pytest.mark.asyncio
+async def test_determine_winner_in_team_game_prefers_best_team(game_manager):
+    game, players = await _game_with_players(game_manager, 4, GameType.TEAM)
+    team0 = [p for p in players if game.players[p].team == 0]
+    team1 = [p for p in players if game.players[p].team == 1]
+    game.players[team0[0]].score = 60
+    game.players[team0[1]].score = 60
+    game.players[team1[0]].score = 100
+    assert game.determine_winner() == team0[0]
+
+@pytest.mark.asyncio
+async def test_determine_winner_in_cooperative_game_is_none(game_manager):
+    game, players = await _game_with_players(game_manager, 2, GameType.COOPERATIVE)
+    game.players[players[0]].score = 100
+    assert game.determine_winner() is None
+
+@pytest.mark.asyncio
+async def test_finish_game_without_winner_uses_tiebreak(game_manager):
+    game, players = await _game_with_players(game_manager, 2)
+    game.players[players[1]].score = 10
+    winner = await game_manager.finish_game(game.id)
+    assert winner == players[1]
+    assert game.finished
+    assert game.get_state()["winner_id"] == str(players[1])
+
+@pytest.mark.asyncio
+async def test_finish_game_with_explicit_winner(game_manager):
+    game, players = await _game_with_players(game_manager, 2)
+    game.players[players[1]].score = 10
+    assert await game_manager.finish_game(game.id, players[0]) == players[0]
+
+@pytest.mark.asyncio
+async def test_active_player_count(game_manager):
+    game, players = await _game_with_players(game_manager, 3)
+    game.players[players[0]].is_active = False
+    assert game.active_player_count() == 2
+
+@pytest.mark.asyncio
+async def test_removing_last_player_finishes_and_removes_running_game(game_manager):
+    game, players = await _game_with_players(game_manager, 1)
+    await game.start()
+    await game_manager.remove_player_from_game(players[0], game.id)
+    assert game.finished
+    assert not game.running
+    assert await game_manager.get_game(game.id) is None
+
+@pytest.mark.asyncio
+async def test_running_game_without_active_players_is_finished(game_manager):
+    game, players = await _game_with_players(game_manager, 2)
+    await game.start()
+    game.players[players[0]].is_active = False
+    await game_manager.remove_player_from_game(players[1], game.id)
+    assert game.finished
+    assert await game_manager.get_game(game.id) is not None
+
+@pytest.mark.asyncio
+async def test_cleanup_empty_waiting_games(game_manager):
+    old_game_id = await game_manager.create_game()
+    fresh_game_id = await game_manager.create_game()
+    occupied_game, _ = await _game_with_players(game_manager, 1)
+    old_game = await game_manager.get_game(old_game_id)
+    old_game.created_at -= game_manager.settings.empty_game_timeout + 1
+    occupied_game.created_at -= game_manager.settings.empty_game_timeout + 1
+
+    assert await game_manager.cleanup_empty_games() == 1
+    assert await game_manager.get_game(old_game_id) is None
+    assert await game_manager.get_game(fresh_game_id) is not None
+    assert await game_manager.get_game(occupied_game.id) is not None
+
+@pytest.mark.asyncio
+async def test_sweep_stale_waiting_games(game_manager):
+    stale_id = await game_manager.create_game()
+    await game_manager.add_player_to_game(uuid.uuid4(), stale_id)
+    active_id = await game_manager.create_game()
+    await game_manager.add_player_to_game(uuid.uuid4(), active_id)
+    stale_game = await game_manager.get_game(stale_id)
+    active_game = await game_manager.get_game(active_id)
+    stale_game.last_activity_at -= game_manager.settings.stale_game_timeout + 1
+    active_game.last_activity_at -= game_manager.settings.stale_game_timeout + 1
+
+    # Новый игрок считается активностью и продлевает жизнь лобби
+    await game_manager.add_player_to_game(uuid.uuid4(), active_id)
+
+    assert await game_manager.sweep_stale_games() == 1
+    assert await game_manager.get_game(stale_id) is None
+    assert await game_manager.get_game(active_id) is not None
+
+@pytest.mark.asyncio
+async def test_auto_spawn_after_lock_uses_queue():
+    physics_manager = PhysicsManager()
+    game_manager = GameManager(physics_manager)
+    game, players = await _game_with_players(game_manager, 1)
+    player = game.players[players[0]]
+    first_block = await game_manager.spawn_block(game.id, player.id)
+    expected_type = player.next_queue[0]
+    await physics_manager.update_block_position(first_block, (3.0, 16.0))
+
+    await game_manager.lock_block(game.id, first_block)
+
+    assert player.current_block_id is not None
+    assert player.current_block_id != first_block
+    assert game.pieces[player.current_block_id].tetromino_type == expected_type
+    assert player.current_block_id in physics_manager.blocks
+    assert len(player.block_ids) == 2
+
+@pytest.mark.asyncio
+async def test_removed_block_position_raises_and_drops_references():
+    physics_manager = PhysicsManager()
+    game_manager = GameManager(physics_manager)
+    game, players = await _game_with_players(game_manager, 1)
+    player = game.players[players[0]]
+    block_id = await game_manager.spawn_block(game.id, player.id)
+    await physics_manager.remove_block(block_id)
+
+    with pytest.raises(BlockNotFoundError):
+        await game_manager.get_block_position(game.id, block_id)
+    assert player.current_block_id is None
+    assert block_id not in player.block_ids
+    assert block_id not in game.pieces
+
+@pytest.mark.asyncio
+async def test_wind_pushes_target_tower_but_not_opponent_pieces_inside_it():
+    physics_manager = PhysicsManager()
+    game_manager = GameManager(physics_manager)
+    game, (target_id, opponent_id) = await _game_with_players(game_manager, 2, GameType.BATTLE)
+    target, opponent = game.players[target_id], game.players[opponent_id]
+    tower = [uuid.uuid4(), uuid.uuid4()]
+    for block_id, position in zip(tower, [(2.0, 18.0), (4.0, 16.0)]):
+        await physics_manager.add_block(block_id, position, 0.0)
+        await physics_manager.set_block_static(block_id, True)
+        target.block_ids.append(block_id)
+    intruder = uuid.uuid4()
+    await physics_manager.add_block(intruder, (3.0, 17.0), 0.0)
+    opponent.block_ids.append(intruder)
+
+    assert await game_manager.apply_wind(game.id, target_id, (1.0, 0.0), 3.0) == 2
+    for block_id in tower:
+        assert physics_manager.blocks[block_id]["velocity"] == (3.0, 0.0)
+        assert not physics_manager.blocks[block_id]["is_static"]
+    assert physics_manager.blocks[intruder]["velocity"] == (0.0, 0.0)
+
+@pytest.mark.asyncio
+async def test_transfer_block_ownership_moves_block_between_players(game_manager):
+    game_manager.settings.auto_spawn = False
+    game, players = await _game_with_players(game_manager, 2)
+    thief, victim = (game.players[player_id] for player_id in players)
+    victim.next_queue.appendleft(TetrominoType.O)
+    block_id = await game_manager.spawn_block(game.id, victim.id)
+
+    with pytest.raises(InvalidActionError):
+        await game_manager.transfer_block_ownership(game.id, block_id, victim.id, thief.id)
+    # Блок жертвы очищает линию, и очки за неё уходят вместе с блоком
+    bottom = game.grid.height - 1
+    game.grid.occupied = {(x, bottom) for x in range(2, game.grid.width)}
+    piece = game.pieces[block_id]
+    piece.x, piece.y = 0, bottom - 1
+    assert await game_manager.lock_block(game.id, block_id) == 1
+    assert victim.score == LINE_CLEAR_SCORES[1]
+    await game_manager.transfer_block_ownership(game.id, block_id, victim.id, thief.id)
+
+    assert victim.block_ids == []
+    assert thief.block_ids == [block_id]
+    assert game.block_owner(block_id) is thief
+    assert (victim.blocks_placed, thief.blocks_placed) == (0, 1)
+    assert (victim.score, thief.score) == (0, LINE_CLEAR_SCORES[1])
+    assert game.check_invariants() == []
+    with pytest.raises(InvalidActionError):
+        await game_manager.transfer_block_ownership(game.id, block_id, victim.id, thief.id)
+
+@pytest.mark.asyncio
+async def test_no_auto_spawn_when_disabled(game_manager):
+    game_manager.settings.auto_spawn = False
+    game, players = await _game_with_players(game_manager, 1)
+    block_id = await game_manager.spawn_block(game.id, players[0])
+    await game_manager.lock_block(game.id, block_id)
+    assert game.players[players[0]].current_block_id is None
+
+@pytest.mark.asyncio
+async def test_spawn_respects_block_cap(game_manager):
+    game_manager.settings.auto_spawn = False
+    game_manager.settings.max_blocks_per_player = 1
+    game, players = await _game_with_players(game_manager, 1)
+    block_id = await game_manager.spawn_block(game.id, players[0])
+    await game_manager.lock_block(game.id, block_id)
+    assert await game_manager.spawn_block(game.id, players[0]) is None
+
+@pytest.mark.asyncio
+async def test_blocked_spawn_eliminates_player(game_manager):
+    game, players = await _game_with_players(game_manager, 1)
+    await game.start()
+    game.grid.occupied = {(x, y) for x in range(game.grid.width) for y in range(2)}
+    assert await game_manager.spawn_block(game.id, players[0]) is None
+    assert not game.players[players[0]].is_active
+    assert game.finished
+
+@pytest.mark.asyncio
+async def test_can_place_at_checks_occupied_cells_and_bounds(game_manager):
+    game, _ = await _game_with_players(game_manager, 1)
+    bottom = game.grid.height - 1
+    game.grid.occupied = {(3, bottom)}
+    assert await game_manager.can_place_at(game.id, (0, bottom - 1), (3, 2))
+    assert not await game_manager.can_place_at(game.id, (2, bottom - 1), (3, 2))
+    assert not await game_manager.can_place_at(game.id, (game.grid.width - 1, 0), (2, 1))
+    with pytest.raises(InvalidValueError):
+        await game_manager.can_place_at(game.id, (0, 0), (0, 1))
+
+def test_player_stats_efficiency():
+    player = Player(uuid.uuid4(), "#FFFFFF")
+    player.blocks_placed = 30
+    player.blocks_destroyed = 10
+    player.spells_cast = 2
+    player.score = 1200
+    player.tower_height = 8.5
+    stats = player.stats()
+    assert stats["efficiency"] == pytest.approx(0.75)
+    assert stats["blocks_placed"] == 30
+    assert stats["blocks_destroyed"] == 10
+    assert stats["spells_cast"] == 2
+    assert stats["score"] == 1200
+    assert stats["tower_height"] == 8.5
+
+def test_player_stats_without_blocks():
+    assert Player(uuid.uuid4(), "#FFFFFF").stats()["efficiency"] == 0.0
+
+@pytest.mark.asyncio
+async def test_lock_block_counts_placed_blocks(game_manager):
+    game_manager.settings.auto_spawn = False
+    game, players = await _game_with_players(game_manager, 1)
+    block_id = await game_manager.spawn_block(game.id, players[0])
+    await game_manager.lock_block(game.id, block_id)
+    assert game.get_state()["players"][0]["stats"]["blocks_placed"] == 1
+
+@pytest.mark.asyncio
+async def test_idle_player_is_kicked(game_manager):
+    events = []
+
+    async def listener(game_id, event):
+        events.append(event)
+
+    game_manager.add_listener(listener)
+    game, players = await _game_with_players(game_manager, 2)
+    await game.start()
+    idle, active = players
+    game.players[idle].last_input_at -= game_manager.settings.idle_timeout + 1
+    await game_manager.record_input(game.id, active)
+
+    kicked = await game_manager.check_idle_players()
+
+    assert kicked == [idle]
+    assert idle not in game.players
+    assert active in game.players
+    assert events[-1] == {"type": "player_idle_kicked", "player_id": str(idle)}
+    await game.stop()
+
+@pytest.mark.asyncio
+async def test_idle_player_is_warned_before_kick(game_manager):
+    events = []
+
+    async def listener(game_id, event):
+        events.append(event)
+
+    game_manager.add_listener(listener)
+    game, players = await _game_with_players(game_manager, 1)
+    await game.start()
+    settings = game_manager.settings
+    game.players[players[0]].last_input_at -= settings.idle_timeout - settings.idle_warning + 1
+
+    assert await game_manager.check_idle_players() == []
+    assert await game_manager.check_idle_players() == []
+    assert [event["type"] for event in events] == ["idle_warning"]
+    await game.stop()
+
+@pytest.mark.asyncio
+async def test_idle_player_is_eliminated_in_battle(game_manager):
+    game, players = await _game_with_players(game_manager, 2, GameType.BATTLE)
+    await game.start()
+    game.players[players[0]].last_input_at -= game_manager.settings.idle_timeout + 1
+    await game_manager.check_idle_players()
+    assert not game.players[players[0]].is_active
+    assert game.players[players[1]].is_active
+    await game.stop()
+
+def test_enum_parse_valid():
+    assert GameType.parse("battle") == GameType.BATTLE
+    assert GameType.parse(" Team ") == GameType.TEAM
+    assert DifficultyLevel.parse("hard") == DifficultyLevel.HARD
+    assert DifficultyLevel.parse(DifficultyLevel.EASY) == DifficultyLevel.EASY
+    assert SpellType.parse("dark") == SpellType.DARK
+    assert str(GameType.COOPERATIVE) == "cooperative"
+    assert str(SpellType.LIGHT) == "light"
+
+def test_enum_parse_invalid():
+    for enum_type, value in ((GameType, "deathmatch"), (DifficultyLevel, "insane"), (SpellType, 3)):
+        with pytest.raises(InvalidValueError) as exc_info:
+            enum_type.parse(value)
+        assert enum_type.__name__ in str(exc_info.value)
+        assert "expected one of" in str(exc_info.value)
+
+@pytest.mark.asyncio
+async def test_create_game_with_settings(game_manager):
+    game_id = await game_manager.create_game({"game_type": "battle", "difficulty": "hard"})
+    game = await game_manager.get_game(game_id)
+    assert game.game_settings["game_type"] == GameType.BATTLE
+    assert game.game_settings["difficulty"] == DifficultyLevel.HARD
+
+    with pytest.raises(InvalidValueError):
+        await game_manager.create_game({"game_type": "deathmatch"})
+    assert len(game_manager.games) == 1
+
+@pytest.mark.asyncio
+async def test_games_per_user_limit(game_manager):
+    game_manager.settings.max_games_per_user = 2
+    creator = uuid.uuid4()
+    first = await game_manager.create_game(creator_id=creator)
+    await game_manager.create_game(creator_id=creator)
+    with pytest.raises(GameLimitExceededError):
+        await game_manager.create_game(creator_id=creator)
+    # Лимит действует на пользователя, а не на сервер
+    await game_manager.create_game(creator_id=uuid.uuid4())
+
+    # Завершённые и удалённые игры освобождают место
+    await game_manager.finish_game(first)
+    await game_manager.create_game(creator_id=creator)
+
+@pytest.mark.asyncio
+async def test_lifecycle_metrics(game_manager):
+    game, _ = await _game_with_players(game_manager, 2)
+    metrics = game_manager.metrics
+    assert metrics.get("games.created").count == 1
+    assert metrics.export()["games.state.waiting"] == 1
+    await game.start()
+    assert metrics.export()["games.state.running"] == 1
+
+    await game_manager.finish_game(game.id)
+    # Повторное завершение не учитывается
+    await game_manager.finish_game(game.id)
+    exported = metrics.export()
+    assert exported["games.finished"]["count"] == 1
+    assert exported["games.duration"]["count"] == 1
+    assert exported["games.state.running"] == 0
+    assert exported["games.state.finished"] == 1
+
+@pytest.mark.asyncio
+async def test_lock_emits_placement_event():
+    physics_manager = PhysicsManager()
+    game_manager = GameManager(physics_manager)
+    game_manager.settings.auto_spawn = False
+    game_manager.settings.placement_log = True
+    events = []
+
+    async def listener(game_id, event):
+        events.append(event)
+
+    game_manager.add_listener(listener)
+    game, players = await _game_with_players(game_manager, 1)
+    block_id = await game_manager.spawn_block(game.id, players[0])
+    piece = game.pieces[block_id]
+    await physics_manager.update_block_position(block_id, (2.2, 11.9))
+    await game_manager.lock_block(game.id, block_id)
+    # Событие рассылается в фоне
+    await asyncio.gather(*game_manager.background_tasks)
+
+    assert events == [{
+        "type": "block_placed",
+        "player_id": str(players[0]),
+        "block_id": str(block_id),
+        "tetromino_type": piece.tetromino_type.value,
+        "x": 2,
+        "y": 12,
+        "rotation": 0,
+        "cleared_lines": False,
+    }]
+    assert game.placement_log == events
+
+@pytest.mark.asyncio
+async def test_move_block_stops_at_wall():
+    physics_manager = PhysicsManager()
+    game_manager = GameManager(physics_manager)
+    game, players = await _game_with_players(game_manager, 1)
+    block_id = await game_manager.spawn_block(game.id, players[0])
+    for _ in range(game.grid.width):
+        position = await game_manager.move_block(game.id, players[0], -1, 0)
+    piece = game.pieces[block_id]
+    assert min(x for x, _ in piece.cells()) == 0
+    assert position["x"] == piece.x
+    assert physics_manager.blocks[block_id]["position"] == (float(piece.x), float(piece.y))
+
+    position = await game_manager.rotate_block(game.id, players[0])
+    assert position["rotation"] == piece.rotation
+    assert physics_manager.blocks[block_id]["rotation"] == piece.rotation * 90.0
+
+CUSTOM_DIFFICULTY = {
+    "fall_interval_ms": 250,
+    "friction": 0.9,
+    "restitution": 0.4,
+    "spell_frequency": 0.5
+}
+
+@pytest.mark.asyncio
+async def test_custom_difficulty_drives_fall_interval_and_material():
+    physics_manager = PhysicsManager()
+    game_manager = GameManager(physics_manager)
+    game_id = await game_manager.create_game(
+        {"difficulty": "custom", "custom_difficulty": dict(CUSTOM_DIFFICULTY)}
+    )
+    player_id = uuid.uuid4()
+    await game_manager.add_player_to_game(player_id, game_id)
+    game = await game_manager.get_game(game_id)
+    assert game.game_settings["difficulty"] == DifficultyLevel.CUSTOM
+    assert game.fall_interval() == 0.25
+    assert game.get_state()["fall_interval_ms"] == 250
+
+    block_id = await game_manager.spawn_block(game_id, player_id)
+    block = physics_manager.blocks[block_id]
+    assert block["friction"] == 0.9
+    assert block["restitution"] == 0.4
+
+@pytest.mark.asyncio
+async def test_pieces_fall_at_the_difficulty_interval(game_manager):
+    game_manager.settings.auto_spawn = False
+    game_id = await game_manager.create_game(
+        {"difficulty": "custom", "custom_difficulty": dict(CUSTOM_DIFFICULTY)}
+    )
+    player_id = uuid.uuid4()
+    await game_manager.add_player_to_game(player_id, game_id)
+    game = await game_manager.get_game(game_id)
+    await game.start()
+    player = game.players[player_id]
+    player.next_queue.appendleft(TetrominoType.O)
+    block_id = await game_manager.spawn_block(game_id, player_id)
+    piece = game.pieces[block_id]
+    start_y = piece.y
+
+    # Интервал 250 мс ещё не прошёл
+    player.last_fall_at = time.monotonic() - 0.1
+    assert await game_manager.advance_falling_pieces(game_id) == 0
+    assert piece.y == start_y
+
+    player.last_fall_at = time.monotonic() - 0.3
+    assert await game_manager.advance_falling_pieces(game_id) == 1
+    assert piece.y == start_y + 1
+
+    # Фигура, которой некуда опуститься, фиксируется
+    piece.y = game.grid.height - 2
+    player.last_fall_at = time.monotonic() - 0.3
+    assert await game_manager.advance_falling_pieces(game_id) == 1
+    assert block_id not in game.pieces
+    assert player.current_block_id is None
+
+@pytest.mark.asyncio
+async def test_spell_frequency_grants_spells_for_cleared_lines(game_manager):
+    game_manager.settings.auto_spawn = False
+    game_id = await game_manager.create_game(
+        {"difficulty": "custom", "custom_difficulty": dict(CUSTOM_DIFFICULTY)}
+    )
+    player_id = uuid.uuid4()
+    await game_manager.add_player_to_game(player_id, game_id)
+    game = await game_manager.get_game(game_id)
+    player = game.players[player_id]
+    await game.start()
+
+    bottom = game.grid.height - 1
+    granted = []
+    for _ in range(3):
+        game.grid.occupied |= {(x, bottom) for x in range(2, game.grid.width)}
+        player.next_queue.appendleft(TetrominoType.O)
+        block_id = await game_manager.spawn_block(game_id, player_id)
+        piece = game.pieces[block_id]
+        piece.x, piece.y = 0, bottom - 1
+        await game_manager.lock_block(game_id, block_id)
+        game.grid.occupied.clear()
+        granted.append(player.spells_granted)
+    # spell_frequency 0.5: одно заклинание на каждые две очищенные линии
+    assert granted == [0, 1, 1]
+    assert player.stats()["spells_granted"] == 1
+
+@pytest.mark.asyncio
+async def test_preset_difficulty_parameters(game_manager):
+    game_id = await game_manager.create_game({"difficulty": "hard"})
+    game = await game_manager.get_game(game_id)
+    assert game.fall_interval() < 0.7
+    assert game.block_material().friction == game.difficulty_parameters()["friction"]
+
+@pytest.mark.asyncio
+async def test_custom_difficulty_round_trips_through_json(game_manager):
+    game_id = await game_manager.create_game(
+        {"difficulty": "custom", "custom_difficulty": dict(CUSTOM_DIFFICULTY)}
+    )
+    game = await game_manager.get_game(game_id)
+    restored_id = await game_manager.create_game(json.loads(json.dumps(game.game_settings)))
+    restored = await game_manager.get_game(restored_id)
+    assert restored.game_settings == game.game_settings
+    assert restored.difficulty_parameters() == CUSTOM_DIFFICULTY
+
+@pytest.mark.asyncio
+async def test_custom_difficulty_validated_on_creation(game_manager):
+    with pytest.raises(InvalidGameSettingsError) as exc_info:
+        await game_manager.create_game({"difficulty": "custom"})
+    assert "custom_difficulty" in str(exc_info.value)
+
+    invalid = {**CUSTOM_DIFFICULTY, "fall_interval_ms": 0, "friction": 1.5}
+    del invalid["restitution"]
+    with pytest.raises(InvalidGameSettingsError) as exc_info:
+        await game_manager.create_game({"difficulty": "custom", "custom_difficulty": invalid})
+    fields = {error.field for error in exc_info.value.errors}
+    assert fields == {
+        "custom_difficulty.fall_interval_ms",
+        "custom_difficulty.friction",
+        "custom_difficulty.restitution"
+    }
+    assert game_manager.games == {}
+
+@pytest.mark.asyncio
+async def test_invariant_checker_reports_corrupted_state(game_manager):
+    game, players = await _game_with_players(game_manager, 2)
+    assert game.check_invariants() == []
+
+    game.players[players[0]].current_block_id = uuid.uuid4()
+    game.winner_id = players[1]
+    game.game_settings["max_players"] = 1
+    violations = game.check_invariants()
+    assert len(violations) == 3
+    assert any("not in its blocks" in violation for violation in violations)
+    assert any("not finished" in violation for violation in violations)
+    assert any("max_players" in violation for violation in violations)
+
+    # Мутации через менеджер проверяют инварианты после себя
+    game.game_settings["max_players"] = 4
+    with pytest.raises(InconsistentStateError):
+        await game_manager.add_player_to_game(uuid.uuid4(), game.id)
+
+@pytest.mark.asyncio
+async def test_game_physics_iterations_override():
+    physics_manager = PhysicsManager()
+    game_manager = GameManager(physics_manager)
+    game_id = await game_manager.create_game({"difficulty": "hard", "physics_iterations": 8})
+    player_id = uuid.uuid4()
+    await game_manager.add_player_to_game(player_id, game_id)
+    block_id = await game_manager.spawn_block(game_id, player_id)
+    assert physics_manager.blocks[block_id]["iterations"] == 8
+
+    with pytest.raises(InvalidGameSettingsError):
+        await game_manager.create_game({"physics_iterations": 0})
+
+@pytest.mark.asyncio
+async def test_rotating_o_piece_leaves_physics_untouched():
+    physics_manager = PhysicsManager()
+    game_manager = GameManager(physics_manager)
+    game_manager.settings.auto_spawn = False
+    game, players = await _game_with_players(game_manager, 1)
+    player = game.players[players[0]]
+    player.next_queue.appendleft(TetrominoType.O)
+    block_id = await game_manager.spawn_block(game.id, player.id)
+    block = physics_manager.blocks[block_id]
+    block["position"] = (4.3, 0.2)
+    block["rotation"] = 3.0
+
+    position = await game_manager.rotate_block(game.id, player.id)
+    assert position["rotation"] == 0
+    assert block["position"] == (4.3, 0.2)
+    assert block["rotation"] == 3.0
+
+@pytest.mark.asyncio
+async def test_create_game_refused_at_capacity(game_manager):
+    game_manager.settings.max_games = 3
+    game_ids = [await game_manager.create_game() for _ in range(3)]
+    assert game_manager.capacity() == {"current": 3, "max": 3}
+    with pytest.raises(ServerAtCapacityError):
+        await game_manager.create_game()
+    assert len(game_manager.games) == 3
+
+    # Завершённая игра освобождает место
+    await game_manager.finish_game(game_ids[0])
+    await game_manager.create_game()
+
+@pytest.mark.asyncio
+async def test_endless_game_levels_up_and_ends_on_top_out(game_manager):
+    game_manager.settings.auto_spawn = False
+    game, players = await _game_with_players(game_manager, 1, GameType.ENDLESS)
+    player = game.players[players[0]]
+    await game.start()
+    base_interval = game.fall_interval_ms()
+
+    # Высота башни выше цели не завершает бесконечную игру
+    player.tower_height = game.game_settings["target_height"] + 5.0
+    bottom = game.grid.height - 1
+    for _ in range(10):
+        game.grid.occupied |= {(x, bottom) for x in range(2, game.grid.width)}
+        player.next_queue.appendleft(TetrominoType.O)
+        block_id = await game_manager.spawn_block(game.id, player.id)
+        piece = game.pieces[block_id]
+        piece.x, piece.y = 0, bottom - 1
+        await game_manager.lock_block(game.id, block_id)
+        game.grid.occupied.clear()
+    assert await game.is_running()
+    assert player.lines_cleared == 10
+    assert player.level == 2
+    assert player.score == 10 * LINE_CLEAR_SCORES[1]
+    assert game.level == 2
+    assert game.fall_interval_ms() < base_interval
+    assert game.get_state()["level"] == 2
+
+    # Игра заканчивается, только когда новой фигуре некуда появиться
+    game.grid.occupied = {(x, y) for x in range(game.grid.width) for y in range(2)}
+    assert await game_manager.spawn_block(game.id, player.id) is None
+    assert game.finished
+
+@pytest.mark.asyncio
+async def test_endless_level_speeds_up_falling(game_manager):
+    game_manager.settings.auto_spawn = False
+    game, players = await _game_with_players(game_manager, 1, GameType.ENDLESS)
+    player = game.players[players[0]]
+    await game.start()
+    player.next_queue.appendleft(TetrominoType.O)
+    block_id = await game_manager.spawn_block(game.id, player.id)
+    piece = game.pieces[block_id]
+    start_y = piece.y
+
+    # Прошло меньше интервала первого уровня, но больше интервала третьего
+    elapsed = (game.fall_interval() + game.fall_interval() * ENDLESS_SPEEDUP ** 2) / 2
+    player.last_fall_at = time.monotonic() - elapsed
+    assert await game_manager.advance_falling_pieces(game.id) == 0
+    assert piece.y == start_y
+
+    game.level = 3
+    assert await game_manager.advance_falling_pieces(game.id) == 1
+    assert piece.y == start_y + 1
+
+@pytest.mark.asyncio
+async def test_state_json_serialized_once_per_mutation(game_manager, monkeypatch):
+    game, players = await _game_with_players(game_manager, 2)
+    serializations = []
+    get_state = game.get_state
+    monkeypatch.setattr(game, "get_state", lambda: serializations.append(1) or get_state())
+
+    first = await game_manager.get_game_state_json(game.id)
+    for _ in range(3):
+        assert await game_manager.get_game_state_json(game.id) == first
+    assert len(serializations) == 1
+    assert json.loads(first)["player_count"] == 2
+
+    await game_manager.remove_player_from_game(players[1], game.id)
+    updated = await game_manager.get_game_state_json(game.id)
+    await game_manager.get_game_state_json(game.id)
+    assert len(serializations) == 2
+    assert json.loads(updated)["player_count"] == 1
+    assert await game_manager.get_game_state_json(uuid.uuid4()) is None
+
+@pytest.mark.asyncio
+async def test_spectators_disabled(game_manager):
+    game_id = await game_manager.create_game()
+    game_manager.settings.enable_spectators = False
+    with pytest.raises(FeatureDisabledError):
+        await game_manager.add_spectator_to_game(uuid.uuid4(), game_id)
+    game = await game_manager.get_game(game_id)
+    assert game.spectators == set()
+
+@pytest.mark.asyncio
+async def test_elapsed_for_player_starts_at_first_spawn(game_manager):
+    game_manager.settings.auto_spawn = False
+    game, players = await _game_with_players(game_manager, 2)
+    await game.start()
+    assert game.elapsed_for_player(players[0]) is None
+
+    await game_manager.spawn_block(game.id, players[0])
+    await asyncio.sleep(0.05)
+    await game_manager.spawn_block(game.id, players[1])
+    first = game.elapsed_for_player(players[0])
+    second = game.elapsed_for_player(players[1])
+    assert first >= 0.05
+    assert second < first - 0.04
+    assert first < time.monotonic() - game.started_at + 1e-6
+
+    game.players[players[1]].score = 100
+    assert game.players[players[1]].stats()["score_rate"] > game.players[players[0]].stats()["score_rate"]
+    with pytest.raises(PlayerNotFoundError):
+        game.elapsed_for_player(uuid.uuid4())
+
+@pytest.mark.asyncio
+async def test_start_game_counts_down_then_runs(game_manager, monkeypatch):
+    monkeypatch.setattr(game_module, "COUNTDOWN_TICK", 0.01)
+    game_manager.settings.start_countdown = 3
+    events = []
+    async def listener(game_id, event):
+        events.append(event)
+    game_manager.add_listener(listener)
+    game, players = await _game_with_players(game_manager, 2)
+
+    assert await game_manager.start_game(game.id) == 3
+    assert game.state() == "starting"
+    assert game.get_state()["countdown"] == 3
+    with pytest.raises(InvalidActionError):
+        await game_manager.start_game(game.id)
+    with pytest.raises(InvalidActionError):
+        await game_manager.apply_settings(game.id, {"time_limit": 60})
+
+    await game.countdown_task
+    await asyncio.gather(*game_manager.background_tasks)
+    assert game.state() == "running"
+    assert game.countdown is None
+    assert all(game.players[player_id].current_block_id for player_id in players)
+    assert [event["type"] for event in events if event["type"] != "player_changed"] == [
+        "countdown", "countdown", "countdown", "game_started"
+    ]
+    assert [event["remaining"] for event in events if event["type"] == "countdown"] == [3, 2, 1]
+    await game.stop()
+
+@pytest.mark.asyncio
+async def test_player_leaving_cancels_countdown(game_manager, monkeypatch):
+    monkeypatch.setattr(game_module, "COUNTDOWN_TICK", 0.01)
+    game_manager.settings.start_countdown = 3
+    game, players = await _game_with_players(game_manager, 2)
+    await game_manager.start_game(game.id)
+    task = game.countdown_task
+
+    await game_manager.remove_player_from_game(players[1], game.id)
+    assert game.state() == "waiting"
+    await asyncio.sleep(0.05)
+    assert task.cancelled()
+    assert not game.running
+
+@pytest.mark.asyncio
+async def test_game_status_transitions(game_manager):
+    game, _ = await _game_with_players(game_manager, 1)
+    assert game.state() == GameStatus.WAITING
+    assert game.get_state()["state"] == "waiting"
+    assert game.can_join() and game.is_ready_to_start() and not game.is_active()
+
+    await game.start()
+    assert game.state() == GameStatus.RUNNING
+    assert game.is_active() and not game.can_join()
+    with pytest.raises(InvalidActionError):
+        await game.cancel()
+    with pytest.raises(InvalidActionError):
+        await game_manager.start_game(game.id)
+
+    await game_manager.finish_game(game.id)
+    assert game.state() == GameStatus.FINISHED
+    with pytest.raises(InvalidActionError):
+        await game.start()
+    assert not game.running
+
+@pytest.mark.asyncio
+async def test_abandoned_lobby_is_cancelled(game_manager):
+    events = []
+    async def listener(game_id, event):
+        events.append(event)
+    game_manager.add_listener(listener)
+    game, players = await _game_with_players(game_manager, 1)
+
+    await game_manager.remove_player_from_game(players[0], game.id)
+    await asyncio.gather(*game_manager.background_tasks)
+    assert game.state() == GameStatus.CANCELLED
+    assert game.id not in game_manager.games
+    assert events[-1] == {"type": "game_cancelled", "reason": "all players left"}
+    assert game_manager.metrics.get("games.cancelled").count == 1
+    with pytest.raises(InvalidActionError):
+        await game.start()
+    with pytest.raises(InvalidActionError):
+        await game.finish()
+
+@pytest.mark.asyncio
+async def test_state_json_is_byte_identical_for_same_state(game_manager):
+    game, players = await _game_with_players(game_manager, 3)
+    first = game.state_json()
+
+    # Порядок словаря игроков не должен влиять на сериализацию
+    game.players = dict(reversed(list(game.players.items())))
+    game.game_settings = dict(reversed(list(game.game_settings.items())))
+    game.mark_changed()
+    second = game.state_json()
+
+    assert second == first
+    assert [player["player_id"] for player in json.loads(second)["players"]] == [str(p) for p in players]
+
+@pytest.mark.asyncio
+async def test_fog_of_war_hides_opponent_pieces_from_players_but_not_spectators(game_manager):
+    game, players = await _game_with_players(game_manager, 2, GameType.BATTLE)
+    for player_id in players:
+        await game_manager.spawn_block(game.id, player_id)
+    me, opponent = players
+
+    player_view = {p["player_id"]: p for p in game.player_view(me)["players"]}
+    assert player_view[str(me)]["current_block_id"] == str(game.players[me].current_block_id)
+    assert player_view[str(opponent)]["current_block_id"] is None
+    assert player_view[str(opponent)]["next_pieces"] == []
+    assert player_view[str(opponent)]["score"] == game.players[opponent].score
+
+    spectator_view = {p["player_id"]: p for p in game.player_view(uuid.uuid4(), is_spectator=True)["players"]}
+    assert spectator_view[str(opponent)]["current_block_id"] == str(game.players[opponent].current_block_id)
+    assert spectator_view[str(opponent)]["next_pieces"]
+
+    await game_manager.apply_settings(game.id, {"fog_of_war": False})
+    assert game.player_view(me) == game.get_state()
+
+@pytest.mark.asyncio
+async def test_leaderboard_orders_ties_by_finish_time_skips_no_winner_and_persists(tmp_path):
+    path = str(tmp_path / "leaderboard.json")
+    game_manager = GameManager()
+    game_manager.leaderboard.path = path
+
+    winners = []
+    for score in (300, 500, 300):
+        game, players = await _game_with_players(game_manager, 2)
+        game.players[players[0]].name = f"Winner {score}"
+        game.players[players[0]].score = score
+        await game_manager.finish_game(game.id, players[0])
+        winners.append(str(players[0]))
+    coop, _ = await _game_with_players(game_manager, 2, GameType.COOPERATIVE)
+    await game_manager.finish_game(coop.id)
+
+    leaderboard = await game_manager.get_leaderboard(10)
+    assert [entry["player_id"] for entry in leaderboard] == [winners[1], winners[0], winners[2]]
+    assert [entry["score"] for entry in leaderboard] == [500, 300, 300]
+    assert [entry["player_name"] for entry in leaderboard] == ["Winner 500", "Winner 300", "Winner 300"]
+    assert len(await game_manager.get_leaderboard(1)) == 1
+
+    restarted = GameManager()
+    restarted.leaderboard.path = path
+    await restarted.start()
+    try:
+        restored = await restarted.get_leaderboard(10)
+        assert restored == leaderboard
+        assert restored[0]["player_name"] == "Winner 500"
+    finally:
+        await restarted.stop()
+
+@pytest.mark.asyncio
+async def test_leaderboard_is_cached_until_a_game_is_recorded(game_manager, monkeypatch):
+    reads = []
+    top = game_manager.leaderboard.top
+    monkeypatch.setattr(game_manager.leaderboard, "top", lambda limit: reads.append(limit) or top(limit))
+
+    assert await game_manager.get_leaderboard(10) == []
+    assert await game_manager.get_leaderboard(10) == []
+    assert reads == [10]
+
+    game, players = await _game_with_players(game_manager, 2)
+    await game_manager.finish_game(game.id, players[0])
+    leaderboard = await game_manager.get_leaderboard(10)
+    assert [entry["player_id"] for entry in leaderboard] == [str(players[0])]
+    assert reads == [10, 10]
+
+@pytest.mark.asyncio
+async def test_leaderboard_writes_snapshots_one_at_a_time(tmp_path, monkeypatch):
+    path = str(tmp_path / "leaderboard.json")
+    game_manager = GameManager()
+    game_manager.leaderboard.path = path
+    write = game_manager.leaderboard.write
+    written = []
+    writing = []
+
+    def slow_write(text):
+        # Запись в потоке не должна пересекаться с другой записью
+        assert not writing
+        writing.append(text)
+        time.sleep(0.01)
+        written.append(len(json.loads(text)))
+        write(text)
+        writing.clear()
+    monkeypatch.setattr(game_manager.leaderboard, "write", slow_write)
+
+    games = []
+    for score in (100, 200, 300):
+        game, players = await _game_with_players(game_manager, 2)
+        game.winner_id = players[0]
+        game.players[players[0]].score = score
+        games.append(game)
+    assert all(await asyncio.gather(*(game_manager.record_finished_game(game) for game in games)))
+
+    assert written == [1, 2, 3]
+    with open(path, encoding="utf-8") as f:
+        assert [entry["score"] for entry in json.load(f)] == [300, 200, 100]
+
+@pytest.mark.asyncio
+async def test_replayed_sequenced_move_is_applied_once(game_manager):
+    game, players = await _game_with_players(game_manager, 1)
+    block_id = await game_manager.spawn_block(game.id, players[0])
+    start_x = game.pieces[block_id].x
+
+    first = await game_manager.move_block(game.id, players[0], 1, 0, input_seq=7)
+    replayed = await game_manager.move_block(game.id, players[0], 1, 0, input_seq=7)
+
+    assert game.pieces[block_id].x == start_x + 1
+    assert replayed == first
+    await game_manager.move_block(game.id, players[0], 1, 0, input_seq=8)
+    assert game.pieces[block_id].x == start_x + 2
+
+@pytest.mark.asyncio
+async def test_drop_block_locks_piece_at_bottom():
+    physics_manager = PhysicsManager()
+    game_manager = GameManager(physics_manager)
+    game_manager.settings.auto_spawn = False
+    game_manager.settings.start_countdown = 0
+    game, players = await _game_with_players(game_manager, 1)
+    block_id = await game_manager.spawn_block(game.id, players[0])
+    # До старта игры фигуру бросить нельзя
+    with pytest.raises(InvalidActionError):
+        await game_manager.drop_block(game.id, players[0])
+
+    await game_manager.start_game(game.id)
+    piece = game.pieces[block_id]
+    position = await game_manager.drop_block(game.id, players[0])
+    assert position["block_id"] == str(block_id)
+    assert max(y for _, y in piece.cells()) == game.grid.height - 1
+    assert physics_manager.blocks[block_id]["is_static"]
+    assert game.players[players[0]].current_block_id is None
+    assert block_id not in game.pieces
+
+    with pytest.raises(InvalidActionError):
+        await game_manager.drop_block(game.id, players[0])
+    await game.stop()
+
+
+@pytest.mark.asyncio
+async def test_pause_all_and_resume_all():
+    physics_manager = PhysicsManager()
+    game_manager = GameManager(physics_manager)
+    game_manager.settings.start_countdown = 0
+    running = []
+    for _ in range(3):
+        game, players = await _game_with_players(game_manager, 1)
+        await game_manager.start_game(game.id)
+        running.append((game, players[0]))
+    lobby, _ = await _game_with_players(game_manager, 1)
+
+    assert await game_manager.pause_all() == 3
+    for game, player_id in running:
+        assert game.state() == GameStatus.PAUSED
+        assert game.get_state()["state"] == "paused"
+        block_id = game.players[player_id].current_block_id
+        assert not physics_manager.blocks[block_id]["is_active"]
+        with pytest.raises(InvalidActionError):
+            await game_manager.move_block(game.id, player_id, 1, 0)
+    assert lobby.state() == GameStatus.WAITING
+    assert await game_manager.pause_all() == 0
+
+    started_at = running[0][0].started_at
+    await asyncio.sleep(0.01)
+    assert await game_manager.resume_all() == 3
+    for game, player_id in running:
+        assert game.state() == GameStatus.RUNNING
+        assert physics_manager.blocks[game.players[player_id].current_block_id]["is_active"]
+    # Время паузы не засчитывается в игру
+    assert running[0][0].started_at > started_at
+    assert lobby.state() == GameStatus.WAITING
+    for game, _ in running:
+        await game.stop()
+
+@pytest.mark.asyncio
+async def test_sync_tower_heights_from_physics():
+    physics_manager = PhysicsManager()
+    game_manager = GameManager(physics_manager)
+    game_manager.settings.auto_spawn = False
+    game_manager.settings.start_countdown = 0
+    game, players = await _game_with_players(game_manager, 1)
+    await game_manager.start_game(game.id)
+    player = game.players[players[0]]
+    block_id = player.current_block_id
+
+    await game_manager.sync_tower_heights(game.id)
+    # Падающая фигура в высоту башни не входит
+    assert player.tower_height == 0.0
+    await game_manager.drop_block(game.id, players[0])
+    await game_manager.sync_tower_heights(game.id)
+    _, y = physics_manager.blocks[block_id]["position"]
+    assert player.tower_height == game.grid.height - y > 0
+
+    await physics_manager.remove_block(block_id)
+    await game_manager.sync_tower_heights(game.id)
+    assert player.tower_height == 0.0
+    assert block_id not in player.block_ids
+    await game.stop()
+
@@ -0,0 +1,79 @@
+import pytest
+import asyncio
+from ..src.metrics import Metric, Metrics
+from .conftest import FakeClock
+
+def test_metric_record():
+    metric = Metric("latency")
+    for value in [3.0, 1.0, 2.0]:
+        metric.record(value)
+    assert metric.count == 3
+    assert metric.total == 6.0
+    assert metric.min == 1.0
+    assert metric.max == 3.0
+    assert metric.average == 2.0
+
+def test_rate_since_snapshot():
+    clock = FakeClock()
+    metrics = Metrics(clock)
+    metrics.increment("requests")
+    snapshot = metrics.snapshot()
+    for _ in range(50):
+        metrics.increment("requests")
+    clock.now += 10.0
+    assert metrics.rate_since(snapshot, "requests") == pytest.approx(5.0)
+
+def test_rate_since_for_new_counter():
+    clock = FakeClock()
+    metrics = Metrics(clock)
+    snapshot = metrics.snapshot()
+    for _ in range(4):
+        metrics.increment("joins")
+    clock.now += 2.0
+    assert metrics.rate_since(snapshot, "joins") == pytest.approx(2.0)
+    assert metrics.rate_since(snapshot, "unknown") == 0.0
+
+def test_rate_since_without_elapsed_time():
+    metrics = Metrics(FakeClock())
+    snapshot = metrics.snapshot()
+    metrics.increment("requests")
+    assert metrics.rate_since(snapshot, "requests") == 0.0
+
+def test_export_includes_gauges():
+    metrics = Metrics()
+    metrics.record("latency", 2.0)
+    metrics.record("latency", 4.0)
+    values = [1, 2]
+    metrics.register_gauge("queue.size", lambda: len(values))
+
+    exported = metrics.export()
+    assert exported["latency"]["average"] == 3.0
+    assert exported["latency"]["max"] == 4.0
+    assert exported["queue.size"] == 2
+    values.append(3)
+    assert metrics.export()["queue.size"] == 3
+
+def test_timer_guard_records_on_exit():
+    clock = FakeClock()
+    metrics = Metrics(clock)
+    with metrics.start_timer("tick"):
+        clock.now += 0.25
+    assert metrics.get("tick").count == 1
+    assert metrics.get("tick").total == pytest.approx(0.25)
+
+    with pytest.raises(RuntimeError):
+        with metrics.start_timer("tick"):
+            clock.now += 0.5
+            raise RuntimeError("boom")
+    assert metrics.get("tick").count == 2
+    assert metrics.get("tick").max == pytest.approx(0.5)
+
+@pytest.mark.asyncio
+async def test_timer_guard_across_await():
+    metrics = Metrics()
+    with metrics.start_timer("sleep") as timer:
+        await asyncio.sleep(0.02)
+    assert timer.elapsed >= 0.02
+    # Повторная остановка не добавляет запись
+    timer.stop()
+    assert metrics.get("sleep").count == 1
@@ -1,10 +1,15 @@
 import pytest
 import uuid
 import json
-from ..src.network.manager import NetworkManager
+import asyncio
+import time
+from ..src.network.manager import ConnectionObserver, NetworkManager, CloseReason
 from ..src.game.manager import GameManager
 from ..src.session.manager import SessionManager
 from ..src.config import Settings
+from ..src.game.tetromino import TetrominoType
+from ..src.exceptions import InvalidValueError
+from .conftest import FakeWebSocket
 
 @pytest.fixture
 def settings():
@@ -93,9 +98,17 @@ async def test_handle_game_action(network_manager, game_manager, session_manager
 @pytest.mark.asyncio
 async def test_handle_invalid_message(network_manager):
     connection_id = uuid.uuid4()
-    message = "invalid json"
-    await network_manager.handle_message(connection_id, message)
-    # Проверяем, что ошибка была обработана корректно
+    websocket = FakeWebSocket()
+    network_manager.connect(connection_id, websocket)
+    await network_manager.handle_message(connection_id, '{"type": "heartbeat", "session_id": ')
+    response = websocket.sent[-1]
+    assert response["type"] == "error"
+    assert response["error"] == "ProtocolError"
+    assert response["position"] == 36
+
+    await network_manager.handle_message(connection_id, "[1, 2]")
+    assert websocket.sent[-1]["error"] == "ProtocolError"
+    assert websocket.sent[-1]["position"] == 0
 
 @pytest.mark.asyncio
 async def test_handle_unknown_message_type(network_manager):
@@ -105,4 +118,959 @@ async def test_handle_unknown_message_type(network_manager):
         "data": {}
     })
     await network_manager.handle_message(connection_id, message)
-    # Проверяем, что неизвестный тип сообщения был обработан корректно 
\ No newline at end of file
+    # Проверяем, что неизвестный тип сообщения был обработан корректно 
+
+@pytest.mark.asyncio
+async def test_duplicate_message_id_is_ignored(network_manager, game_manager, session_manager):
+    network_manager.set_managers(game_manager, session_manager)
+    connection_id = uuid.uuid4()
+    message = json.dumps({
+        "type": "create_game",
+        "message_id": "client-1"
+    })
+    await network_manager.handle_message(connection_id, message)
+    await network_manager.handle_message(connection_id, message)
+    assert len(game_manager.games) == 1
+
+@pytest.mark.asyncio
+async def test_duplicate_message_id_accepted_after_window(network_manager, game_manager, session_manager):
+    network_manager.set_managers(game_manager, session_manager)
+    network_manager.settings.message_dedup_window = 0.05
+    connection_id = uuid.uuid4()
+    message = json.dumps({
+        "type": "create_game",
+        "message_id": "client-1"
+    })
+    await network_manager.handle_message(connection_id, message)
+    await asyncio.sleep(0.1)
+    await network_manager.handle_message(connection_id, message)
+    assert len(game_manager.games) == 2
+    # Устаревшая запись вытеснена, осталась только новая
+    assert list(network_manager.seen_message_ids) == [(connection_id, "client-1")]
+
+@pytest.mark.asyncio
+async def test_same_message_id_from_different_connections(network_manager, game_manager, session_manager):
+    network_manager.set_managers(game_manager, session_manager)
+    message = json.dumps({
+        "type": "create_game",
+        "message_id": "client-1"
+    })
+    await network_manager.handle_message(uuid.uuid4(), message)
+    await network_manager.handle_message(uuid.uuid4(), message)
+    assert len(game_manager.games) == 2
+
+@pytest.mark.asyncio
+async def test_messages_without_id_are_not_deduplicated(network_manager, game_manager, session_manager):
+    network_manager.set_managers(game_manager, session_manager)
+    connection_id = uuid.uuid4()
+    message = json.dumps({"type": "create_game"})
+    await network_manager.handle_message(connection_id, message)
+    await network_manager.handle_message(connection_id, message)
+    assert len(game_manager.games) == 2
+
+@pytest.mark.asyncio
+async def test_response_carries_client_message_id(network_manager, game_manager, session_manager):
+    network_manager.set_managers(game_manager, session_manager)
+    connection_id = uuid.uuid4()
+    websocket = FakeWebSocket()
+    network_manager.active_connections[connection_id] = websocket
+    message = json.dumps({
+        "type": "create_game",
+        "message_id": "client-42"
+    })
+    await network_manager.handle_message(connection_id, message)
+    assert websocket.sent[0]["type"] == "game_created"
+    assert websocket.sent[0]["message_id"] == "client-42"
+
+@pytest.mark.asyncio
+async def test_configure_game_by_host(network_manager, game_manager, session_manager):
+    network_manager.set_managers(game_manager, session_manager)
+    connection_id = uuid.uuid4()
+    websocket = FakeWebSocket()
+    network_manager.active_connections[connection_id] = websocket
+    game_id = await game_manager.create_game()
+    session_id = await session_manager.create_session(uuid.uuid4())
+    await session_manager.join_game(session_id, game_id)
+
+    await network_manager.handle_message(connection_id, json.dumps({
+        "type": "configure_game",
+        "game_id": str(game_id),
+        "session_id": str(session_id),
+        "settings": {"target_height": 12}
+    }))
+    assert websocket.sent[-1]["type"] == "game_configured"
+    game = await game_manager.get_game(game_id)
+    assert game.game_settings["target_height"] == 12
+
+@pytest.mark.asyncio
+async def test_configure_game_rejected_for_non_host(network_manager, game_manager, session_manager):
+    network_manager.set_managers(game_manager, session_manager)
+    connection_id = uuid.uuid4()
+    websocket = FakeWebSocket()
+    network_manager.active_connections[connection_id] = websocket
+    game_id = await game_manager.create_game()
+    host_session = await session_manager.create_session(uuid.uuid4())
+    guest_session = await session_manager.create_session(uuid.uuid4())
+    await session_manager.join_game(host_session, game_id)
+    await session_manager.join_game(guest_session, game_id)
+
+    await network_manager.handle_message(connection_id, json.dumps({
+        "type": "configure_game",
+        "game_id": str(game_id),
+        "session_id": str(guest_session),
+        "settings": {"target_height": 12}
+    }))
+    assert websocket.sent[-1]["type"] == "error"
+    assert websocket.sent[-1]["error"] == "InvalidActionError"
+
+@pytest.mark.asyncio
+async def test_list_games_includes_new_open_game(network_manager, game_manager, session_manager):
+    network_manager.set_managers(game_manager, session_manager)
+    connection_id = uuid.uuid4()
+    websocket = FakeWebSocket()
+    network_manager.active_connections[connection_id] = websocket
+    session_id = await session_manager.create_session(uuid.uuid4())
+
+    await network_manager.handle_message(connection_id, json.dumps({
+        "type": "create_game",
+        "session_id": str(session_id)
+    }))
+    game_id = websocket.sent[-1]["game_id"]
+
+    await network_manager.handle_message(connection_id, json.dumps({
+        "type": "list_games",
+        "session_id": str(session_id),
+        "game_type": "classic"
+    }))
+    response = websocket.sent[-1]
+    assert response["type"] == "games_list"
+    assert [game["game_id"] for game in response["games"]] == [game_id]
+
+    await network_manager.handle_message(connection_id, json.dumps({
+        "type": "list_games",
+        "game_type": "battle"
+    }))
+    assert websocket.sent[-1]["games"] == []
+
+@pytest.mark.asyncio
+async def test_rejoin_last_game_after_reconnect(network_manager, game_manager, session_manager):
+    network_manager.set_managers(game_manager, session_manager)
+    game_id = await game_manager.create_game()
+    host_session = await session_manager.create_session(uuid.uuid4())
+    await session_manager.join_game(host_session, game_id)
+    session_id = await session_manager.create_session(uuid.uuid4())
+
+    first_connection = uuid.uuid4()
+    network_manager.active_connections[first_connection] = FakeWebSocket()
+    await network_manager.handle_message(first_connection, json.dumps({
+        "type": "join_game",
+        "game_id": str(game_id),
+        "session_id": str(session_id)
+    }))
+    await session_manager.leave_game(session_id)
+    del network_manager.active_connections[first_connection]
+
+    second_connection = uuid.uuid4()
+    websocket = FakeWebSocket()
+    network_manager.active_connections[second_connection] = websocket
+    await network_manager.handle_message(second_connection, json.dumps({
+        "type": "rejoin_last",
+        "session_id": str(session_id)
+    }))
+    await network_manager.flush_broadcasts()
+    joined = websocket.sent[0]
+    assert {key: joined[key] for key in ("type", "game_id")} == {"type": "game_joined", "game_id": str(game_id)}
+    assert joined["reconnect_token"]
+    assert websocket.sent[-1]["type"] == "game_state"
+    session = await session_manager.get_session(session_id)
+    assert session.game_id == game_id
+
+@pytest.mark.asyncio
+async def test_create_game_rejected_over_user_limit(network_manager, game_manager, session_manager):
+    network_manager.set_managers(game_manager, session_manager)
+    game_manager.settings.max_games_per_user = 1
+    connection_id = uuid.uuid4()
+    websocket = FakeWebSocket()
+    network_manager.active_connections[connection_id] = websocket
+    session_id = await session_manager.create_session(uuid.uuid4())
+
+    for _ in range(2):
+        await network_manager.handle_message(connection_id, json.dumps({
+            "type": "create_game",
+            "session_id": str(session_id)
+        }))
+    assert websocket.sent[0]["type"] == "game_created"
+    assert websocket.sent[1]["type"] == "error"
+    assert len(game_manager.games) == 1
+
+@pytest.mark.asyncio
+async def test_single_player_move_broadcasts_player_state(network_manager, game_manager, session_manager):
+    network_manager.set_managers(game_manager, session_manager)
+    game_id = await game_manager.create_game()
+    sockets = {}
+    sessions = {}
+    for name in ("mover", "watcher"):
+        connection_id = uuid.uuid4()
+        sockets[name] = FakeWebSocket()
+        network_manager.connect(connection_id, sockets[name])
+        sessions[name] = await session_manager.create_session(uuid.uuid4())
+        await network_manager.handle_message(connection_id, json.dumps({
+            "type": "join_game",
+            "game_id": str(game_id),
+            "session_id": str(sessions[name])
+        }))
+    # Вход игрока рассылает полное состояние всем участникам
+    await network_manager.flush_broadcasts()
+    assert sockets["watcher"].sent[-1]["type"] == "game_state"
+    assert len(sockets["watcher"].sent[-1]["state"]["players"]) == 2
+
+    mover_connection = next(
+        connection_id for connection_id, websocket in network_manager.active_connections.items()
+        if websocket is sockets["mover"]
+    )
+    await network_manager.handle_message(mover_connection, json.dumps({
+        "type": "game_action",
+        "game_id": str(game_id),
+        "session_id": str(sessions["mover"]),
+        "action": "move",
+        "direction": "left"
+    }))
+    await network_manager.flush_broadcasts()
+    mover = await session_manager.get_session(sessions["mover"])
+    message = sockets["watcher"].sent[-1]
+    assert message["type"] == "player_state"
+    assert message["player"]["player_id"] == str(mover.user_id)
+    assert "state" not in message
+
+    await network_manager.handle_message(mover_connection, json.dumps({
+        "type": "game_action",
+        "game_id": str(game_id),
+        "session_id": str(sessions["mover"]),
+        "action": "cast_spell"
+    }))
+    await network_manager.flush_broadcasts()
+    assert sockets["watcher"].sent[-1]["type"] == "game_state"
+
+@pytest.mark.asyncio
+async def test_heartbeat_reports_time_remaining(network_manager, game_manager, session_manager):
+    network_manager.set_managers(game_manager, session_manager)
+    connection_id = uuid.uuid4()
+    websocket = FakeWebSocket()
+    network_manager.connect(connection_id, websocket)
+    session_id = await session_manager.create_session(uuid.uuid4())
+    session = await session_manager.get_session(session_id)
+    session.last_activity -= 10.0
+
+    await network_manager.handle_message(connection_id, json.dumps({
+        "type": "heartbeat",
+        "session_id": str(session_id)
+    }))
+    response = websocket.sent[-1]
+    assert response["type"] == "heartbeat_ack"
+    # Heartbeat продлевает сессию до полного срока
+    assert response["time_remaining"] == pytest.approx(network_manager.settings.session_cleanup_interval, abs=1.0)
+
+@pytest.mark.asyncio
+async def test_get_my_state_returns_full_state_and_is_rate_limited(network_manager, game_manager, session_manager):
+    network_manager.set_managers(game_manager, session_manager)
+    connection_id = uuid.uuid4()
+    websocket = FakeWebSocket()
+    network_manager.connect(connection_id, websocket)
+    session_id = await session_manager.create_session(uuid.uuid4())
+    def request(message_id):
+        return json.dumps({"type": "get_my_state", "session_id": str(session_id), "message_id": message_id})
+
+    await network_manager.handle_message(connection_id, request("sync-0"))
+    assert websocket.sent[-1]["type"] == "error"
+    assert websocket.sent[-1]["error"] == "NotInGameError"
+
+    game_id = await game_manager.create_game()
+    await session_manager.join_game(session_id, game_id)
+    await network_manager.handle_message(connection_id, request("sync-1"))
+    response = websocket.sent[-1]
+    assert response["type"] == "game_state"
+    assert response["message_id"] == "sync-1"
+    assert response["state"]["game_id"] == str(game_id)
+    assert len(response["state"]["players"]) == 1
+
+    await network_manager.handle_message(connection_id, request("sync-2"))
+    assert websocket.sent[-1]["error"] == "RateLimitedError"
+
+@pytest.mark.asyncio
+async def test_disconnect_forgets_sync_times_of_the_last_connection(network_manager, game_manager, session_manager):
+    network_manager.set_managers(game_manager, session_manager)
+    game_id = await game_manager.create_game()
+    connections = {}
+    for _ in range(2):
+        connection_id = uuid.uuid4()
+        network_manager.connect(connection_id, FakeWebSocket())
+        session_id = await session_manager.create_session(uuid.uuid4())
+        await network_manager.handle_message(connection_id, json.dumps({
+            "type": "join_game", "game_id": str(game_id), "session_id": str(session_id)
+        }))
+        await network_manager.handle_message(connection_id, json.dumps({
+            "type": "get_my_state", "session_id": str(session_id)
+        }))
+        connections[connection_id] = session_id
+    await network_manager.broadcast_game_state(game_id)
+    (first, first_session), (second, second_session) = connections.items()
+    assert set(network_manager.last_resync) == {first_session, second_session}
+
+    # В игре ещё есть второе соединение, поэтому время полной рассылки остаётся
+    network_manager.disconnect(first)
+    assert set(network_manager.last_resync) == {second_session}
+    assert game_id in network_manager.last_full_sync
+
+    network_manager.disconnect(second)
+    assert network_manager.last_resync == {}
+    assert game_id not in network_manager.last_full_sync
+
+@pytest.mark.asyncio
+async def test_sequenced_inputs_are_acked_with_positions(network_manager, game_manager, session_manager):
+    network_manager.set_managers(game_manager, session_manager)
+    game_manager.settings.auto_spawn = False
+    connection_id = uuid.uuid4()
+    websocket = FakeWebSocket()
+    network_manager.connect(connection_id, websocket)
+    game_id = await game_manager.create_game()
+    session_id = await session_manager.create_session(uuid.uuid4())
+    await session_manager.join_game(session_id, game_id)
+    session = await session_manager.get_session(session_id)
+    block_id = await game_manager.spawn_block(game_id, session.user_id)
+    game = await game_manager.get_game(game_id)
+    start_x = game.pieces[block_id].x
+
+    inputs = [(1, "move", "left"), (2, "move", "left"), (3, "move", "down")]
+    for input_seq, action, direction in inputs:
+        await network_manager.handle_message(connection_id, json.dumps({
+            "type": "game_action",
+            "game_id": str(game_id),
+            "session_id": str(session_id),
+            "action": action,
+            "direction": direction,
+            "input_seq": input_seq
+        }))
+    acks = [m for m in websocket.sent if m["type"] == "action_processed"]
+    assert [ack["input_seq"] for ack in acks] == [1, 2, 3]
+    assert [(ack["position"]["x"], ack["position"]["y"]) for ack in acks] == [
+        (start_x - 1, 0), (start_x - 2, 0), (start_x - 2, 1)
+    ]
+
+    # Устаревший ввод не обрабатывается
+    await network_manager.handle_message(connection_id, json.dumps({
+        "type": "game_action",
+        "game_id": str(game_id),
+        "session_id": str(session_id),
+        "action": "move",
+        "direction": "right",
+        "input_seq": 2
+    }))
+    assert len([m for m in websocket.sent if m["type"] == "action_processed"]) == 3
+    assert game.pieces[block_id].x == start_x - 2
+    rejection = websocket.sent[-1]
+    assert rejection["type"] == "action_rejected"
+    assert (rejection["input_seq"], rejection["last_input_seq"]) == (2, 3)
+
+    # Номер, который не является целым числом, отклоняется ошибкой, а не теряется молча
+    for input_seq in ("4", True, 4.0):
+        await network_manager.handle_message(connection_id, json.dumps({
+            "type": "game_action",
+            "game_id": str(game_id),
+            "session_id": str(session_id),
+            "action": "move",
+            "direction": "right",
+            "input_seq": input_seq,
+            "message_id": f"bad-seq-{input_seq}"
+        }))
+        error = websocket.sent[-1]
+        assert error["type"] == "error"
+        assert error["error"] == "InvalidValueError"
+        assert error["message_id"] == f"bad-seq-{input_seq}"
+    assert game.pieces[block_id].x == start_x - 2
+    assert network_manager.last_input_seq[connection_id] == 3
+
+class BlockingWebSocket(FakeWebSocket):
+    """Задерживает отправку состояния игры, пока тест её не отпустит"""
+
+    def __init__(self):
+        super().__init__()
+        self.release = asyncio.Event()
+
+    async def send_json(self, data):
+        if data["type"] == "game_state":
+            await self.release.wait()
+        await super().send_json(data)
+
+@pytest.mark.asyncio
+async def test_messages_processed_while_broadcast_in_flight(network_manager, game_manager, session_manager):
+    network_manager.set_managers(game_manager, session_manager)
+    game_id = await game_manager.create_game()
+    connection_id = uuid.uuid4()
+    websocket = BlockingWebSocket()
+    network_manager.connect(connection_id, websocket)
+    session_id = await session_manager.create_session(uuid.uuid4())
+
+    await network_manager.handle_message(connection_id, json.dumps({
+        "type": "join_game",
+        "game_id": str(game_id),
+        "session_id": str(session_id)
+    }))
+    # Рассылка состояния зависла, но следующее сообщение обрабатывается сразу
+    await asyncio.wait_for(network_manager.handle_message(connection_id, json.dumps({
+        "type": "heartbeat",
+        "session_id": str(session_id)
+    })), timeout=1.0)
+    assert [m["type"] for m in websocket.sent] == ["game_joined", "heartbeat_ack"]
+
+    websocket.release.set()
+    await network_manager.flush_broadcasts()
+    assert websocket.sent[-1]["type"] == "game_state"
+
+@pytest.mark.asyncio
+async def test_broadcast_requests_are_coalesced(network_manager, game_manager, session_manager):
+    network_manager.set_managers(game_manager, session_manager)
+    game_id = await game_manager.create_game()
+    connection_id = uuid.uuid4()
+    websocket = BlockingWebSocket()
+    network_manager.connect(connection_id, websocket)
+    session_id = await session_manager.create_session(uuid.uuid4())
+    await session_manager.join_game(session_id, game_id)
+    network_manager.connection_sessions[connection_id] = session_id
+
+    network_manager.request_broadcast(game_id)
+    await asyncio.sleep(0)
+    # Пока первая рассылка идёт, повторные запросы сливаются в один
+    for _ in range(10):
+        network_manager.request_broadcast(game_id)
+    assert network_manager.broadcast_workers[game_id].pending == {None}
+
+    websocket.release.set()
+    await network_manager.flush_broadcasts()
+    assert [m["type"] for m in websocket.sent] == ["game_state", "game_state"]
+    await network_manager.stop()
+    assert network_manager.broadcast_workers == {}
+
+@pytest.mark.asyncio
+async def test_broadcast_to_game_reaches_only_participants(network_manager, game_manager, session_manager):
+    network_manager.set_managers(game_manager, session_manager)
+    games = [await game_manager.create_game() for _ in range(2)]
+    sockets = {}
+    for name, game_id in (("first", games[0]), ("second", games[0]), ("other", games[1]), ("lobby", None)):
+        connection_id = uuid.uuid4()
+        sockets[name] = FakeWebSocket()
+        network_manager.connect(connection_id, sockets[name])
+        session_id = await session_manager.create_session(uuid.uuid4())
+        network_manager.connection_sessions[connection_id] = session_id
+        if game_id:
+            await session_manager.join_game(session_id, game_id)
+
+    message = {"type": "game_event", "event": "test"}
+    assert await network_manager.broadcast_to_game(games[0], message) == 2
+    assert sockets["first"].sent == [message]
+    assert sockets["second"].sent == [message]
+    assert sockets["other"].sent == []
+    assert sockets["lobby"].sent == []
+
+@pytest.mark.asyncio
+async def test_start_practice_message(network_manager, game_manager, session_manager):
+    network_manager.set_managers(game_manager, session_manager)
+    connection_id = uuid.uuid4()
+    websocket = FakeWebSocket()
+    network_manager.connect(connection_id, websocket)
+    session_id = await session_manager.create_session(uuid.uuid4())
+
+    await network_manager.handle_message(connection_id, json.dumps({
+        "type": "start_practice",
+        "session_id": str(session_id)
+    }))
+    await network_manager.flush_broadcasts()
+    assert websocket.sent[0]["type"] == "practice_started"
+    state = websocket.sent[-1]["state"]
+    assert state["is_running"]
+    assert state["player_count"] == 1
+    assert state["players"][0]["current_block_id"] is not None
+    game = await game_manager.get_game(uuid.UUID(websocket.sent[0]["game_id"]))
+    await game.stop()
+
+@pytest.mark.asyncio
+async def test_server_full_closes_with_code(network_manager):
+    network_manager.settings.max_connections = 1
+    accepted, rejected = FakeWebSocket(), FakeWebSocket()
+    assert await network_manager.register(uuid.uuid4(), accepted)
+    rejected_id = uuid.uuid4()
+    assert not await network_manager.register(rejected_id, rejected)
+    assert rejected.closed == (1013, "server full")
+    assert rejected_id not in network_manager.active_connections
+    assert accepted.closed is None
+
+    await network_manager.stop()
+    assert accepted.closed == (CloseReason.SHUTDOWN.code, CloseReason.SHUTDOWN.reason)
+    assert network_manager.active_connections == {}
+
+@pytest.mark.asyncio
+async def test_rejoin_replays_missed_events(network_manager, game_manager, session_manager):
+    network_manager.set_managers(game_manager, session_manager)
+    game_id = await game_manager.create_game()
+    host_session = await session_manager.create_session(uuid.uuid4())
+    await session_manager.join_game(host_session, game_id)
+    session_id = await session_manager.create_session(uuid.uuid4())
+
+    first_connection = uuid.uuid4()
+    first_socket = FakeWebSocket()
+    network_manager.connect(first_connection, first_socket)
+    await network_manager.handle_message(first_connection, json.dumps({
+        "type": "join_game",
+        "game_id": str(game_id),
+        "session_id": str(session_id)
+    }))
+    await game_manager._emit(game_id, {"type": "block_placed", "n": 1})
+    assert first_socket.sent[-1]["seq"] == 1
+    network_manager.disconnect(first_connection)
+
+    for n in (2, 3):
+        await game_manager._emit(game_id, {"type": "block_placed", "n": n})
+
+    second_connection = uuid.uuid4()
+    websocket = FakeWebSocket()
+    network_manager.connect(second_connection, websocket)
+    await network_manager.handle_message(second_connection, json.dumps({
+        "type": "rejoin_last",
+        "session_id": str(session_id),
+        "last_event_seq": 1
+    }))
+    await network_manager.flush_broadcasts()
+    assert [m["type"] for m in websocket.sent] == ["game_joined", "game_event", "game_event", "game_state"]
+    assert [(m["seq"], m["event"]["n"]) for m in websocket.sent[1:3]] == [(2, 2), (3, 3)]
+
+@pytest.mark.asyncio
+async def test_fog_of_war_redacts_block_events_for_opponents(network_manager, game_manager, session_manager):
+    network_manager.set_managers(game_manager, session_manager)
+    game_id = await game_manager.create_game({"game_type": "battle"})
+    sockets, players = {}, {}
+    for name in ("owner", "receiver", "opponent"):
+        connection_id = uuid.uuid4()
+        sockets[name] = FakeWebSocket()
+        network_manager.connect(connection_id, sockets[name])
+        session_id = await session_manager.create_session(uuid.uuid4())
+        await network_manager.handle_message(connection_id, json.dumps({
+            "type": "join_game",
+            "game_id": str(game_id),
+            "session_id": str(session_id)
+        }))
+        players[name] = str((await session_manager.get_session(session_id)).user_id)
+
+    block_id = str(uuid.uuid4())
+    await game_manager._emit(game_id, {
+        "type": "block_placed", "player_id": players["owner"], "block_id": block_id,
+        "tetromino_type": "T", "x": 3, "y": 17, "rotation": 1, "cleared_lines": False,
+    })
+    await game_manager._emit(game_id, {
+        "type": "block_transferred", "block_id": block_id,
+        "from_player_id": players["owner"], "to_player_id": players["receiver"],
+    })
+    events = {
+        name: [m["event"] for m in websocket.sent if m["type"] == "game_event"]
+        for name, websocket in sockets.items()
+    }
+    placed, transferred = events["owner"]
+    assert (placed["block_id"], placed["tetromino_type"], placed["x"]) == (block_id, "T", 3)
+    assert events["receiver"][1] == transferred
+    assert events["opponent"] == [
+        {**placed, "block_id": None, "tetromino_type": None, "x": None, "y": None, "rotation": None},
+        {**transferred, "block_id": None},
+    ]
+
+@pytest.mark.asyncio
+async def test_event_history_is_bounded(network_manager, game_manager, session_manager):
+    network_manager.settings.event_history_size = 3
+    network_manager.set_managers(game_manager, session_manager)
+    game_id = await game_manager.create_game()
+    for n in range(5):
+        await game_manager._emit(game_id, {"type": "block_placed", "n": n})
+    assert [m["seq"] for m in network_manager.event_history[game_id]] == [3, 4, 5]
+
+@pytest.mark.asyncio
+async def test_disabled_features_are_rejected(network_manager, game_manager, session_manager):
+    network_manager.set_managers(game_manager, session_manager)
+    network_manager.settings.enable_replay = False
+    session_manager.settings.enable_practice = False
+    connection_id = uuid.uuid4()
+    websocket = FakeWebSocket()
+    network_manager.connect(connection_id, websocket)
+    session_id = await session_manager.create_session(uuid.uuid4())
+
+    await network_manager.handle_message(connection_id, json.dumps({
+        "type": "start_practice",
+        "session_id": str(session_id)
+    }))
+    await network_manager.handle_message(connection_id, json.dumps({
+        "type": "rejoin_last",
+        "session_id": str(session_id),
+        "last_event_seq": 0
+    }))
+    assert [m["error"] for m in websocket.sent] == ["FeatureDisabledError", "FeatureDisabledError"]
+    assert "'replay' is disabled" in websocket.sent[1]["message"]
+    assert game_manager.games == {}
+
+@pytest.mark.asyncio
+async def test_score_change_broadcasts_player_state(network_manager, game_manager, session_manager):
+    network_manager.set_managers(game_manager, session_manager)
+    game_manager.settings.auto_spawn = False
+    game_id = await game_manager.create_game()
+    connection_id = uuid.uuid4()
+    websocket = FakeWebSocket()
+    network_manager.connect(connection_id, websocket)
+    session_id = await session_manager.create_session(uuid.uuid4())
+    await network_manager.handle_message(connection_id, json.dumps({
+        "type": "join_game",
+        "game_id": str(game_id),
+        "session_id": str(session_id)
+    }))
+    await network_manager.flush_broadcasts()
+
+    game = await game_manager.get_game(game_id)
+    player = next(iter(game.players.values()))
+    player.next_queue.appendleft(TetrominoType.O)
+    block_id = await game_manager.spawn_block(game_id, player.id)
+    bottom = game.grid.height - 1
+    game.grid.occupied = {(x, bottom) for x in range(2, game.grid.width)}
+    piece = game.pieces[block_id]
+    piece.x, piece.y = 0, bottom - 1
+    await game_manager.lock_block(game_id, block_id)
+    await asyncio.gather(*game_manager.background_tasks)
+    await network_manager.flush_broadcasts()
+
+    player_states = [m for m in websocket.sent if m["type"] == "player_state"]
+    assert len(player_states) == 1
+    assert player_states[0]["player"]["score"] == player.score > 0
+
+    # Небольшое изменение высоты не рассылается
+    player.tower_height += 0.5
+    assert not player.take_report()
+    player.tower_height += 0.5
+    assert player.take_report()
+
+@pytest.mark.asyncio
+async def test_disconnect_after_repeated_parse_failures(network_manager):
+    network_manager.settings.max_parse_failures = 3
+    connection_id = uuid.uuid4()
+    websocket = FakeWebSocket()
+    network_manager.connect(connection_id, websocket)
+
+    # Корректное сообщение сбрасывает счётчик ошибок
+    for message in ("garbage", "garbage", json.dumps({"type": "unknown"}), "garbage", "garbage"):
+        await network_manager.handle_message(connection_id, message)
+    assert websocket.closed is None
+
+    await network_manager.handle_message(connection_id, "garbage")
+    assert websocket.closed == (CloseReason.PROTOCOL_ERROR.code, CloseReason.PROTOCOL_ERROR.reason)
+    assert connection_id not in network_manager.active_connections
+
+@pytest.mark.asyncio
+async def test_connections_per_session_limit_closes_oldest(network_manager, game_manager, session_manager):
+    network_manager.settings.max_connections_per_session = 2
+    network_manager.set_managers(game_manager, session_manager)
+    game_id = await game_manager.create_game()
+    session_id = await session_manager.create_session(uuid.uuid4())
+    connections = []
+    for _ in range(3):
+        connection_id = uuid.uuid4()
+        websocket = FakeWebSocket()
+        network_manager.connect(connection_id, websocket)
+        connections.append((connection_id, websocket))
+        await network_manager.handle_message(connection_id, json.dumps({
+            "type": "join_game",
+            "game_id": str(game_id),
+            "session_id": str(session_id)
+        }))
+
+    oldest_id, oldest_socket = connections[0]
+    assert oldest_socket.closed == (CloseReason.SESSION_LIMIT.code, CloseReason.SESSION_LIMIT.reason)
+    assert oldest_id not in network_manager.active_connections
+    assert all(websocket.closed is None for _, websocket in connections[1:])
+    assert await network_manager._game_connections(game_id) == [c for c, _ in connections[1:]]
+
+@pytest.mark.asyncio
+async def test_broadcast_serializes_message_once(network_manager, game_manager, session_manager, monkeypatch):
+    network_manager.set_managers(game_manager, session_manager)
+    game_id = await game_manager.create_game()
+    sockets = []
+    for _ in range(4):
+        connection_id = uuid.uuid4()
+        sockets.append(FakeWebSocket())
+        network_manager.connect(connection_id, sockets[-1])
+        session_id = await session_manager.create_session(uuid.uuid4())
+        await session_manager.join_game(session_id, game_id)
+        network_manager.connection_sessions[connection_id] = session_id
+
+    dumps_calls = []
+    dumps = json.dumps
+    monkeypatch.setattr(json, "dumps", lambda *args, **kwargs: dumps_calls.append(1) or dumps(*args, **kwargs))
+    message = {"type": "game_event", "event": {"blocks": list(range(100))}}
+    assert await network_manager.broadcast_to_game(game_id, message) == 4
+    assert len(dumps_calls) == 1
+    assert all(websocket.sent == [message] for websocket in sockets)
+
+@pytest.mark.asyncio
+async def test_finished_game_sends_results_once(network_manager, game_manager, session_manager):
+    network_manager.set_managers(game_manager, session_manager)
+    game_id = await game_manager.create_game()
+    sockets = []
+    sessions = []
+    for _ in range(3):
+        connection_id = uuid.uuid4()
+        websocket = FakeWebSocket()
+        network_manager.connect(connection_id, websocket)
+        session_id = await session_manager.create_session(uuid.uuid4())
+        await network_manager.handle_message(connection_id, json.dumps({
+            "type": "join_game",
+            "game_id": str(game_id),
+            "session_id": str(session_id)
+        }))
+        sockets.append(websocket)
+        sessions.append(await session_manager.get_session(session_id))
+
+    game = await game_manager.get_game(game_id)
+    await game.start()
+    game.players[sessions[0].user_id].score = 100
+    game.players[sessions[1].user_id].score = 900
+    game.players[sessions[2].user_id].score = 400
+    game.players[sessions[2].user_id].lines_cleared = 4
+    await game_manager.finish_game(game_id)
+    await game_manager.finish_game(game_id)
+    await asyncio.gather(*game_manager.background_tasks)
+
+    for websocket in sockets:
+        results = [m for m in websocket.sent if m["type"] == "game_over"]
+        assert len(results) == 1
+    results = results[0]
+    assert results["winner_id"] == str(sessions[1].user_id)
+    assert [entry["player_id"] for entry in results["standings"]] == [
+        str(sessions[1].user_id), str(sessions[2].user_id), str(sessions[0].user_id)
+    ]
+    assert [entry["rank"] for entry in results["standings"]] == [1, 2, 3]
+    assert results["standings"][1]["lines_cleared"] == 4
+    assert results["duration"] >= 0
+
+@pytest.mark.asyncio
+async def test_reconnect_rotates_token(network_manager, game_manager, session_manager):
+    network_manager.set_managers(game_manager, session_manager)
+    game_id = await game_manager.create_game()
+    session_id = await session_manager.create_session(uuid.uuid4())
+    first_connection = uuid.uuid4()
+    network_manager.connect(first_connection, FakeWebSocket())
+    await network_manager.handle_message(first_connection, json.dumps({
+        "type": "join_game",
+        "game_id": str(game_id),
+        "session_id": str(session_id)
+    }))
+    token = network_manager.active_connections[first_connection].sent[0]["reconnect_token"]
+    network_manager.disconnect(first_connection)
+
+    second_connection = uuid.uuid4()
+    websocket = FakeWebSocket()
+    network_manager.connect(second_connection, websocket)
+    await network_manager.handle_message(second_connection, json.dumps({
+        "type": "reconnect",
+        "reconnect_token": token
+    }))
+    response = websocket.sent[-1]
+    assert response["type"] == "reconnected"
+    assert response["session_id"] == str(session_id)
+    assert response["game_id"] == str(game_id)
+    assert response["reconnect_token"] != token
+    assert network_manager.connection_sessions[second_connection] == session_id
+
+    # Прежний токен отозван, новый действует
+    await network_manager.handle_message(second_connection, json.dumps({
+        "type": "reconnect",
+        "reconnect_token": token
+    }))
+    assert websocket.sent[-1]["error"] == "InvalidTokenError"
+    await network_manager.handle_message(second_connection, json.dumps({
+        "type": "reconnect",
+        "reconnect_token": response["reconnect_token"]
+    }))
+    assert websocket.sent[-1]["type"] == "reconnected"
+
+@pytest.mark.asyncio
+async def test_session_guard_distinguishes_missing_session_from_not_in_game(network_manager, game_manager, session_manager):
+    network_manager.set_managers(game_manager, session_manager)
+    connection_id = uuid.uuid4()
+    websocket = FakeWebSocket()
+    network_manager.connect(connection_id, websocket)
+
+    await network_manager.handle_message(connection_id, json.dumps({
+        "type": "get_my_state",
+        "message_id": "guard-1"
+    }))
+    assert websocket.sent[-1]["type"] == "error"
+    assert websocket.sent[-1]["error"] == "NotAuthenticatedError"
+
+    session_id = await session_manager.create_session(uuid.uuid4())
+    await network_manager.handle_message(connection_id, json.dumps({
+        "type": "get_my_state",
+        "session_id": str(session_id),
+        "message_id": "guard-2"
+    }))
+    assert websocket.sent[-1]["type"] == "error"
+    assert websocket.sent[-1]["error"] == "NotInGameError"
+
+@pytest.mark.asyncio
+async def test_announcement_reaches_all_connected_clients(network_manager):
+    sockets = [FakeWebSocket() for _ in range(3)]
+    for websocket in sockets:
+        network_manager.connect(uuid.uuid4(), websocket)
+
+    delivered = await network_manager.announce("  Server restarts in 5 minutes  ")
+
+    assert delivered == 3
+    for websocket in sockets:
+        assert websocket.sent[-1] == {"type": "announcement", "text": "Server restarts in 5 minutes"}
+    with pytest.raises(InvalidValueError):
+        await network_manager.announce("x" * (network_manager.settings.max_announcement_length + 1))
+
+@pytest.mark.asyncio
+async def test_batch_returns_ordered_correlated_results(network_manager, game_manager, session_manager):
+    network_manager.set_managers(game_manager, session_manager)
+    connection_id = uuid.uuid4()
+    websocket = FakeWebSocket()
+    network_manager.connect(connection_id, websocket)
+    session_id = await session_manager.create_session(uuid.uuid4())
+
+    await network_manager.handle_message(connection_id, json.dumps({
+        "type": "batch",
+        "message_id": "batch-1",
+        "requests": [
+            {"type": "heartbeat", "session_id": str(session_id), "message_id": "batch-1a"},
+            {"type": "get_my_state", "session_id": str(session_id), "message_id": "batch-1b"},
+            {"type": "list_games", "message_id": "batch-1c"}
+        ]
+    }))
+
+    assert len(websocket.sent) == 1
+    response = websocket.sent[0]
+    assert response["type"] == "batch_result"
+    assert response["message_id"] == "batch-1"
+    results = response["results"]
+    assert [result["message_id"] for result in results] == ["batch-1a", "batch-1b", "batch-1c"]
+    assert [result["ok"] for result in results] == [True, False, True]
+    assert results[0]["responses"][0]["type"] == "heartbeat_ack"
+    assert results[1]["error"]["error"] == "NotInGameError"
+    assert results[2]["responses"][0]["type"] == "games_list"
+
+@pytest.mark.asyncio
+async def test_batch_continues_after_malformed_request(network_manager, game_manager, session_manager):
+    network_manager.set_managers(game_manager, session_manager)
+    connection_id = uuid.uuid4()
+    websocket = FakeWebSocket()
+    network_manager.connect(connection_id, websocket)
+    session_id = await session_manager.create_session(uuid.uuid4())
+
+    await network_manager.handle_message(connection_id, json.dumps({
+        "type": "batch",
+        "message_id": "batch-2",
+        "requests": [
+            {"type": "list_games", "message_id": "batch-2a"},
+            # game_id не разбирается как UUID — обработчик падает не с GameError
+            {"type": "join_game", "game_id": "not-a-uuid", "session_id": str(session_id), "message_id": "batch-2b"},
+            {"type": "heartbeat", "session_id": str(session_id), "message_id": "batch-2c"}
+        ]
+    }))
+
+    assert len(websocket.sent) == 1
+    results = websocket.sent[0]["results"]
+    assert [result["message_id"] for result in results] == ["batch-2a", "batch-2b", "batch-2c"]
+    assert [result["ok"] for result in results] == [True, False, True]
+    assert results[1]["error"]["type"] == "error"
+    assert results[2]["responses"][0]["type"] == "heartbeat_ack"
+    assert connection_id not in network_manager.batch_responses
+
+@pytest.mark.asyncio
+async def test_pong_carries_server_time_and_echoes_client_time(network_manager):
+    connection_id = uuid.uuid4()
+    websocket = FakeWebSocket()
+    network_manager.connect(connection_id, websocket)
+    before = int(time.time() * 1000)
+
+    await network_manager.handle_message(connection_id, json.dumps({
+        "type": "ping",
+        "client_time": 1234567,
+        "message_id": "ping-1"
+    }))
+
+    pong = websocket.sent[-1]
+    assert pong["type"] == "pong"
+    assert pong["message_id"] == "ping-1"
+    assert pong["client_time"] == 1234567
+    assert before <= pong["server_time"] <= int(time.time() * 1000) + 1
+
+class StalledWebSocket(FakeWebSocket):
+    """Клиент, который перестал читать: отправки не завершаются"""
+
+    async def send_text(self, text):
+        await asyncio.Event().wait()
+
+@pytest.mark.asyncio
+async def test_stalled_client_shows_growing_queue_and_is_flagged_slow(network_manager):
+    network_manager.settings.slow_client_threshold = 2
+    connection_id = uuid.uuid4()
+    network_manager.connect(connection_id, StalledWebSocket())
+    stats = network_manager.connection_stats[connection_id]
+
+    sends = []
+    for expected in range(1, 4):
+        sends.append(asyncio.create_task(network_manager.broadcast_message({"type": "announcement", "text": "hi"})))
+        await asyncio.sleep(0)
+        assert stats.pending == expected
+    assert stats.slow
+    assert network_manager.connection_report()[0]["slow"]
+
+    for send in sends:
+        send.cancel()
+    await asyncio.gather(*sends, return_exceptions=True)
+    assert stats.pending == 0
+    assert not stats.slow
+    assert stats.messages_sent == 0
+
+class CountingObserver(ConnectionObserver):
+    def __init__(self):
+        self.events = []
+
+    def on_connect(self, connection_id):
+        self.events.append("connect")
+
+    def on_authenticate(self, connection_id, session_id):
+        self.events.append("authenticate")
+
+    def on_message(self, connection_id, data):
+        self.events.append(f"message:{data['type']}")
+
+    def on_disconnect(self, connection_id):
+        self.events.append("disconnect")
+
+class FailingObserver(ConnectionObserver):
+    def on_message(self, connection_id, data):
+        raise RuntimeError("observer failure")
+
+@pytest.mark.asyncio
+async def test_connection_observers(settings, game_manager, session_manager):
+    observer = CountingObserver()
+    network_manager = NetworkManager(settings, [FailingObserver(), observer])
+    network_manager.set_managers(game_manager, session_manager)
+    game_id = await game_manager.create_game()
+    session_id = await session_manager.create_session(uuid.uuid4())
+
+    connection_id = uuid.uuid4()
+    websocket = FakeWebSocket()
+    network_manager.connect(connection_id, websocket)
+    await network_manager.handle_message(connection_id, json.dumps({
+        "type": "join_game",
+        "game_id": str(game_id),
+        "session_id": str(session_id)
+    }))
+    # Ошибка одного наблюдателя не мешает остальным и самой обработке
+    assert websocket.sent[0]["type"] == "game_joined"
+    network_manager.disconnect(connection_id)
+    network_manager.disconnect(connection_id)
+    assert observer.events == ["connect", "message:join_game", "authenticate", "disconnect"]
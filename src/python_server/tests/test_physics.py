@@ -1,7 +1,17 @@
 import pytest
 import uuid
 import asyncio
-from ..src.physics.manager import PhysicsManager
+import math
+from ..src.physics.manager import (
+    PhysicsManager,
+    PhysicsMaterial,
+    MAX_BLOCK_FORCE,
+    MAX_BLOCK_DENSITY,
+    MAX_PHYSICS_ITERATIONS,
+    MAX_CATCH_UP_STEPS,
+    PhysicsSnapshot
+)
+from ..src.exceptions import PhysicsError
 
 @pytest.fixture
 def physics_manager():
@@ -78,3 +88,257 @@ async def test_physics_update_loop(physics_manager):
     assert physics_manager.blocks[block_id]["position"] != position
     await physics_manager.stop()
     assert not physics_manager.running
+
+@pytest.mark.asyncio
+async def test_apply_explosion_clamps_force(physics_manager):
+    block_id = uuid.uuid4()
+    await physics_manager.add_block(block_id, (0.0, 0.0), 0.0)
+    affected = await physics_manager.apply_explosion((0.0, 1.0), 5.0, 1e12)
+    assert affected == 1
+    vx, vy = physics_manager.blocks[block_id]["velocity"]
+    assert math.hypot(vx, vy) <= MAX_BLOCK_FORCE
+    assert vy < 0
+
+@pytest.mark.asyncio
+async def test_apply_explosion_skips_distant_and_static_blocks(physics_manager):
+    near = uuid.uuid4()
+    far = uuid.uuid4()
+    locked = uuid.uuid4()
+    await physics_manager.add_block(near, (1.0, 0.0), 0.0)
+    await physics_manager.add_block(far, (50.0, 50.0), 0.0)
+    await physics_manager.add_block(locked, (0.5, 0.0), 0.0)
+    await physics_manager.set_block_static(locked, True)
+    assert await physics_manager.apply_explosion((0.0, 0.0), 3.0, 10.0) == 1
+    assert physics_manager.blocks[far]["velocity"] == (0.0, 0.0)
+    assert physics_manager.blocks[locked]["velocity"] == (0.0, 0.0)
+
+@pytest.mark.asyncio
+async def test_apply_explosion_rejects_invalid_input(physics_manager):
+    with pytest.raises(PhysicsError):
+        await physics_manager.apply_explosion((float("nan"), 0.0), 3.0, 10.0)
+    with pytest.raises(PhysicsError):
+        await physics_manager.apply_explosion((0.0, 0.0), float("inf"), 10.0)
+    with pytest.raises(PhysicsError):
+        await physics_manager.apply_explosion((0.0, 0.0), 3.0, float("nan"))
+
+@pytest.mark.asyncio
+async def test_apply_wind_region_affects_only_blocks_inside(physics_manager):
+    inside = uuid.uuid4()
+    outside = uuid.uuid4()
+    locked = uuid.uuid4()
+    await physics_manager.add_block(inside, (2.0, 3.0), 0.0)
+    await physics_manager.add_block(outside, (8.0, 3.0), 0.0)
+    await physics_manager.add_block(locked, (3.0, 3.0), 0.0)
+    await physics_manager.set_block_static(locked, True)
+    assert await physics_manager.query_aabb((0.0, 0.0), (4.0, 5.0)) == [inside]
+    assert await physics_manager.apply_wind_region((0.0, 0.0), (4.0, 5.0), (2.0, 0.0), 3.0) == 1
+    assert physics_manager.blocks[inside]["velocity"] == (3.0, 0.0)
+    assert physics_manager.blocks[outside]["velocity"] == (0.0, 0.0)
+    assert physics_manager.blocks[locked]["velocity"] == (0.0, 0.0)
+
+    # Выбранные блоки задеваются, даже если зафиксированы; остальные в области — нет
+    assert await physics_manager.apply_wind_region((0.0, 0.0), (4.0, 5.0), (2.0, 0.0), 3.0, [locked]) == 1
+    assert physics_manager.blocks[locked]["velocity"] == (3.0, 0.0)
+    assert not physics_manager.blocks[locked]["is_static"]
+    assert physics_manager.blocks[inside]["velocity"] == (3.0, 0.0)
+    with pytest.raises(PhysicsError):
+        await physics_manager.apply_wind_region((0.0, 0.0), (4.0, 5.0), (0.0, 0.0), 3.0)
+
+@pytest.mark.asyncio
+async def test_set_and_get_gravity(physics_manager):
+    await physics_manager.set_gravity(1.0, -2.0)
+    assert await physics_manager.get_gravity() == (1.0, -2.0)
+    with pytest.raises(PhysicsError):
+        await physics_manager.set_gravity(float("nan"), 0.0)
+
+@pytest.mark.asyncio
+async def test_zero_gravity_restores_after_expiry(physics_manager):
+    original = await physics_manager.get_gravity()
+    await physics_manager.zero_gravity(0.05)
+    await physics_manager._update_physics()
+    assert await physics_manager.get_gravity() == (0.0, 0.0)
+    await asyncio.sleep(0.1)
+    await physics_manager._update_physics()
+    assert await physics_manager.get_gravity() == original
+
+@pytest.mark.asyncio
+async def test_blocks_resting_in_zero_gravity_fall_after_expiry(physics_manager):
+    physics_manager.settings.physics_pause_when_idle = True
+    block_id = uuid.uuid4()
+    await physics_manager.add_block(block_id, (0.0, 0.0), 0.0)
+    await physics_manager.zero_gravity(0.05)
+    await physics_manager._update_physics()
+    assert not physics_manager.active
+    assert physics_manager.blocks[block_id]["velocity"] == (0.0, 0.0)
+
+    await asyncio.sleep(0.1)
+    await physics_manager._update_physics()
+    await physics_manager._update_physics()
+    assert physics_manager.blocks[block_id]["velocity"][1] > 0
+    assert physics_manager.blocks[block_id]["position"][1] > 0
+
+@pytest.mark.asyncio
+async def test_reverse_gravity_pushes_blocks_up(physics_manager):
+    block_id = uuid.uuid4()
+    await physics_manager.add_block(block_id, (0.0, 0.0), 0.0)
+    await physics_manager.reverse_gravity(10.0)
+    await physics_manager._update_physics()
+    assert physics_manager.blocks[block_id]["velocity"][1] < 0
+
+@pytest.mark.asyncio
+async def test_add_block_normalizes_rotation(physics_manager):
+    block_id = uuid.uuid4()
+    await physics_manager.add_block(block_id, (0.0, 0.0), -90.0)
+    assert physics_manager.blocks[block_id]["rotation"] == 270.0
+
+@pytest.mark.asyncio
+async def test_add_block_rejects_zero_size(physics_manager):
+    block_id = uuid.uuid4()
+    with pytest.raises(PhysicsError):
+        await physics_manager.add_block(block_id, (0.0, 0.0), 0.0, size=(0.0, 1.0))
+    assert block_id not in physics_manager.blocks
+
+@pytest.mark.asyncio
+async def test_add_block_rejects_over_dense_material(physics_manager):
+    block_id = uuid.uuid4()
+    with pytest.raises(PhysicsError):
+        await physics_manager.add_block(
+            block_id, (0.0, 0.0), 0.0, material=PhysicsMaterial(density=MAX_BLOCK_DENSITY * 2)
+        )
+    assert block_id not in physics_manager.blocks
+
+def test_physics_material_defaults():
+    material = PhysicsMaterial()
+    assert material.to_dict() == {
+        "density": 1.0,
+        "friction": 0.5,
+        "restitution": 0.1,
+        "is_sensor": False,
+    }
+
+def test_physics_material_rejects_out_of_range_values():
+    for values in ({"friction": -0.1}, {"restitution": 1.5}, {"density": 0.0}, {"is_sensor": 1}):
+        with pytest.raises(PhysicsError):
+            PhysicsMaterial(**values)
+
+@pytest.mark.asyncio
+async def test_add_block_with_sensor_material(physics_manager):
+    block_id = uuid.uuid4()
+    material = PhysicsMaterial(density=2.0, is_sensor=True)
+    await physics_manager.add_block(block_id, (0.0, 0.0), 0.0, material=material)
+    block = physics_manager.blocks[block_id]
+    assert block["density"] == 2.0
+    assert block["is_sensor"]
+
+@pytest.mark.asyncio
+async def test_block_iterations_applied_per_update(physics_manager, monkeypatch):
+    steps = []
+    step_block = physics_manager._step_block
+    monkeypatch.setattr(
+        physics_manager, "_step_block",
+        lambda block, dt, damping: (steps.append(dt), step_block(block, dt, damping))
+    )
+    block_id = uuid.uuid4()
+    await physics_manager.add_block(block_id, (0.0, 0.0), 0.0, iterations=4)
+    assert physics_manager.blocks[block_id]["iterations"] == 4
+
+    await physics_manager._update_physics()
+    assert len(steps) == 4
+    assert sum(steps) == pytest.approx(physics_manager.settings.physics_time_step)
+
+    for iterations in (0, MAX_PHYSICS_ITERATIONS + 1):
+        with pytest.raises(PhysicsError):
+            await physics_manager.add_block(uuid.uuid4(), (0.0, 0.0), 0.0, iterations=iterations)
+
+@pytest.mark.asyncio
+async def test_substeps_keep_friction_per_update(physics_manager):
+    coarse, fine = uuid.uuid4(), uuid.uuid4()
+    await physics_manager.add_block(coarse, (0.0, 0.0), 0.0, iterations=1)
+    await physics_manager.add_block(fine, (5.0, 0.0), 0.0, iterations=8)
+    await physics_manager.set_gravity(0.0, 0.0)
+    for block_id in (coarse, fine):
+        physics_manager.blocks[block_id]["velocity"] = (1.0, 0.0)
+    await physics_manager._update_physics()
+    assert physics_manager.blocks[fine]["velocity"][0] == pytest.approx(
+        physics_manager.blocks[coarse]["velocity"][0]
+    )
+
+@pytest.mark.asyncio
+async def test_snapshot_and_restore(physics_manager):
+    first, second = uuid.uuid4(), uuid.uuid4()
+    await physics_manager.add_block(first, (1.0, 2.0), 0.0)
+    await physics_manager.add_block(second, (4.0, 0.0), 90.0, size=(2.0, 1.0))
+    snapshot = await physics_manager.snapshot()
+    expected = {block_id: dict(block) for block_id, block in physics_manager.blocks.items()}
+
+    await physics_manager.apply_force(first, (3.0, -1.0))
+    await physics_manager._update_physics()
+    await physics_manager.remove_block(second)
+    await physics_manager.set_gravity(0.0, -9.8)
+    assert physics_manager.blocks[first]["position"] != (1.0, 2.0)
+
+    await physics_manager.restore(snapshot)
+    assert physics_manager.blocks == expected
+    assert await physics_manager.get_gravity() == (0.0, physics_manager.settings.physics_gravity)
+
+    with pytest.raises(PhysicsError):
+        await physics_manager.restore(PhysicsSnapshot("{}"))
+    assert physics_manager.blocks == expected
+
+@pytest.mark.asyncio
+async def test_static_and_inactive_blocks_do_not_fall(physics_manager):
+    locked = uuid.uuid4()
+    paused = uuid.uuid4()
+    await physics_manager.add_block(locked, (0.0, 0.0), 0.0)
+    await physics_manager.add_block(paused, (2.0, 0.0), 0.0)
+    await physics_manager.apply_force(paused, (0.0, 1.0))
+    await physics_manager.set_block_static(locked, True)
+    await physics_manager.set_block_active(paused, False)
+    for _ in range(3):
+        await physics_manager._update_physics()
+    assert physics_manager.blocks[locked]["position"] == (0.0, 0.0)
+    assert physics_manager.blocks[paused]["position"] == (2.0, 0.0)
+    # Приостановленный блок сохраняет скорость и продолжает движение после возобновления
+    assert physics_manager.blocks[paused]["velocity"] == (0.0, 1.0)
+
+    await physics_manager.set_block_active(paused, True)
+    await physics_manager._update_physics()
+    assert physics_manager.blocks[paused]["position"] != (2.0, 0.0)
+    assert physics_manager.blocks[locked]["position"] == (0.0, 0.0)
+
+@pytest.mark.asyncio
+async def test_settled_blocks_are_not_stepped(physics_manager):
+    settled = uuid.uuid4()
+    await physics_manager.add_block(settled, (0.0, 0.0), 0.0)
+    await physics_manager.set_block_static(settled, True)
+    await physics_manager._update_physics()
+    assert physics_manager.steps == 1
+    assert not physics_manager.active
+
+    for _ in range(3):
+        await physics_manager._update_physics()
+    assert physics_manager.steps == 1
+
+    # Новый блок возобновляет симуляцию
+    falling = uuid.uuid4()
+    await physics_manager.add_block(falling, (1.0, 0.0), 0.0)
+    await physics_manager._update_physics()
+    assert physics_manager.steps == 2
+    assert physics_manager.blocks[falling]["position"] != (1.0, 0.0)
+
+    physics_manager.settings.physics_pause_when_idle = False
+    await physics_manager.set_block_static(falling, True)
+    await physics_manager._update_physics()
+    await physics_manager._update_physics()
+    assert physics_manager.steps == 4
+
+@pytest.mark.asyncio
+async def test_advance_steps_by_real_elapsed_time(physics_manager):
+    step = physics_manager.settings.physics_time_step
+    assert await physics_manager.advance(step * 2.5) == 2
+    # Остаток полшага переносится на следующий проход
+    assert await physics_manager.advance(step * 0.6) == 1
+    assert await physics_manager.advance(0.0) == 0
+    # После долгой задержки симуляция не пытается догнать всё отставание сразу
+    assert await physics_manager.advance(10.0) == MAX_CATCH_UP_STEPS
+
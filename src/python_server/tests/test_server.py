@@ -0,0 +1,110 @@
+import asyncio
+import json
+import uuid
+import pytest
+from ..src.server import GameServer
+from ..src.config import Settings
+from ..src.exceptions import StartupError
+from .conftest import FakeWebSocket
+
+@pytest.fixture
+def server():
+    settings = Settings()
+    settings.startup_timeout = 0.1
+    return GameServer(settings)
+
+@pytest.mark.asyncio
+async def test_start_and_stop(server):
+    await server.start()
+    assert await server.ready()
+
+    await server.stop()
+    assert not await server.ready()
+    for _, manager in server.subsystems():
+        assert not await manager.is_running()
+
+@pytest.mark.asyncio
+async def test_config_file_reaches_every_subsystem(tmp_path):
+    path = tmp_path / "server.toml"
+    path.write_text("field_width = 12\nphysics_gravity = 4.5\n", encoding="utf-8")
+    server = GameServer(Settings.from_file(str(path)))
+
+    for _, manager in server.subsystems():
+        assert manager.settings is server.settings
+    assert server.physics_manager.gravity == (0.0, 4.5)
+    game = await server.game_manager.get_game(await server.game_manager.create_game())
+    assert game.grid.width == 12
+
+@pytest.mark.asyncio
+async def test_physics_start_failure(server, monkeypatch):
+    async def failing_start():
+        raise RuntimeError("physics engine unavailable")
+    monkeypatch.setattr(server.physics_manager, "start", failing_start)
+
+    with pytest.raises(StartupError):
+        await server.start()
+
+    assert not await server.ready()
+    for _, manager in server.subsystems():
+        assert not await manager.is_running()
+
+@pytest.mark.asyncio
+async def test_subsystem_not_ready_times_out(server, monkeypatch):
+    async def never_ready():
+        return False
+    monkeypatch.setattr(server.session_manager, "is_running", never_ready)
+
+    with pytest.raises(StartupError):
+        await server.start()
+
+    # Запущенные до сбоя подсистемы должны быть остановлены
+    assert not await server.physics_manager.is_running()
+    assert not await server.game_manager.is_running()
+    assert not await server.network_manager.is_running()
+
+@pytest.mark.asyncio
+async def test_stop_drains_handlers_before_stopping_physics(server, monkeypatch):
+    await server.start()
+    physics = server.physics_manager
+
+    async def update_block_position(block_id, position):
+        assert physics.running, "physics called after stop"
+    monkeypatch.setattr(physics, "update_block_position", update_block_position)
+
+    # Обработчик перемещения застревает посередине, пока идёт остановка
+    entered = asyncio.Event()
+    release = asyncio.Event()
+    async def slow_move_block(game_id, player_id, dx, dy, input_seq=None):
+        entered.set()
+        await release.wait()
+        await physics.update_block_position(uuid.uuid4(), (0.0, 0.0))
+        return None
+    monkeypatch.setattr(server.game_manager, "move_block", slow_move_block)
+
+    game_id = await server.game_manager.create_game()
+    session_id = await server.session_manager.create_session(uuid.uuid4())
+    await server.session_manager.join_game(session_id, game_id)
+    connection_id = uuid.uuid4()
+    websocket = FakeWebSocket()
+    assert await server.network_manager.register(connection_id, websocket)
+
+    handler = asyncio.create_task(server.network_manager.handle_message(connection_id, json.dumps({
+        "type": "game_action",
+        "game_id": str(game_id),
+        "session_id": str(session_id),
+        "action": "move",
+        "direction": "left"
+    })))
+    await entered.wait()
+    stopping = asyncio.create_task(server.stop())
+    await asyncio.sleep(0.05)
+    assert await physics.is_running()
+    release.set()
+    await handler
+    await stopping
+
+    assert not await physics.is_running()
+    assert websocket.sent[-1]["type"] == "action_processed"
+    # После остановки новые сообщения не обрабатываются
+    await server.network_manager.handle_message(connection_id, json.dumps({"type": "list_games"}))
+    assert websocket.sent[-1]["type"] == "action_processed"
@@ -3,7 +3,13 @@ import uuid
 import asyncio
 from ..src.session.manager import SessionManager, Session
 from ..src.game.manager import GameManager
-from ..src.exceptions import SessionNotFoundError
+from ..src.exceptions import (
+    SessionNotFoundError,
+    GameFullError,
+    GameNotFoundError,
+    InvalidActionError,
+    InvalidTokenError
+)
 
 @pytest.fixture
 def game_manager():
@@ -81,4 +87,167 @@ async def test_session_expiration(session_manager):
     session = await session_manager.get_session(session_id)
     assert not session.is_expired(1.0)  # Не истекла
     await asyncio.sleep(1.1)
-    assert session.is_expired(1.0)  # Истекла 
\ No newline at end of file
+    assert session.is_expired(1.0)  # Истекла 
+
+@pytest.mark.asyncio
+async def test_concurrent_joins_respect_capacity(session_manager):
+    game_manager = session_manager.game_manager
+    game_id = await game_manager.create_game()
+    await game_manager.apply_settings(game_id, {"max_players": 1})
+    session_ids = [await session_manager.create_session(uuid.uuid4()) for _ in range(20)]
+
+    results = await asyncio.gather(
+        *(session_manager.join_game(session_id, game_id) for session_id in session_ids),
+        return_exceptions=True
+    )
+
+    successes = [result for result in results if result is None]
+    failures = [result for result in results if isinstance(result, GameFullError)]
+    assert len(successes) == 1
+    assert len(failures) == 19
+    game = await game_manager.get_game(game_id)
+    assert len(game.players) == 1
+
+@pytest.mark.asyncio
+async def test_failed_join_keeps_previous_game(session_manager):
+    game_manager = session_manager.game_manager
+    current_game = await game_manager.create_game()
+    full_game = await game_manager.create_game()
+    await game_manager.apply_settings(full_game, {"max_players": 1})
+    await game_manager.add_player_to_game(uuid.uuid4(), full_game)
+    session_id = await session_manager.create_session(uuid.uuid4())
+    await session_manager.join_game(session_id, current_game)
+
+    with pytest.raises(GameFullError):
+        await session_manager.join_game(session_id, full_game)
+
+    session = await session_manager.get_session(session_id)
+    assert session.game_id == current_game
+
+@pytest.mark.asyncio
+async def test_leave_game_returns_game_id(session_manager):
+    game_id = await session_manager.game_manager.create_game()
+    first = await session_manager.create_session(uuid.uuid4())
+    second = await session_manager.create_session(uuid.uuid4())
+    await session_manager.join_game(first, game_id)
+    await session_manager.join_game(second, game_id)
+    assert await session_manager.leave_game(first) == game_id
+    assert await session_manager.game_manager.get_game(game_id) is not None
+
+@pytest.mark.asyncio
+async def test_leave_game_by_last_player_cleans_up_game(session_manager):
+    game_id = await session_manager.game_manager.create_game()
+    session_id = await session_manager.create_session(uuid.uuid4())
+    await session_manager.join_game(session_id, game_id)
+    assert await session_manager.leave_game(session_id) == game_id
+    assert await session_manager.game_manager.get_game(game_id) is None
+    assert await session_manager.leave_game(session_id) is None
+
+@pytest.mark.asyncio
+async def test_rejoin_last_game_after_leaving(session_manager):
+    game_id = await session_manager.game_manager.create_game()
+    host = await session_manager.create_session(uuid.uuid4())
+    session_id = await session_manager.create_session(uuid.uuid4())
+    await session_manager.join_game(host, game_id)
+    await session_manager.join_game(session_id, game_id)
+    await session_manager.leave_game(session_id)
+
+    assert await session_manager.rejoin_last_game(session_id) == game_id
+    session = await session_manager.get_session(session_id)
+    assert session.game_id == game_id
+    game = await session_manager.game_manager.get_game(game_id)
+    assert session.user_id in game.players
+
+@pytest.mark.asyncio
+async def test_rejoin_last_game_errors(session_manager):
+    session_id = await session_manager.create_session(uuid.uuid4())
+    with pytest.raises(InvalidActionError):
+        await session_manager.rejoin_last_game(session_id)
+
+    # Опустевшая игра удаляется, вернуться в неё нельзя
+    game_id = await session_manager.game_manager.create_game()
+    await session_manager.join_game(session_id, game_id)
+    await session_manager.leave_game(session_id)
+    with pytest.raises(GameNotFoundError):
+        await session_manager.rejoin_last_game(session_id)
+
+@pytest.mark.asyncio
+async def test_session_time_remaining(session_manager):
+    session_id = await session_manager.create_session(uuid.uuid4())
+    session = await session_manager.get_session(session_id)
+    timeout = 100.0
+    assert session.time_remaining(timeout) == pytest.approx(timeout, abs=1.0)
+
+    session.last_activity -= 40.0
+    assert session.time_remaining(timeout) == pytest.approx(60.0, abs=1.0)
+
+    session.last_activity -= 80.0
+    assert session.time_remaining(timeout) == 0.0
+    assert session.is_expired(timeout)
+
+@pytest.mark.asyncio
+async def test_touch_and_get(session_manager):
+    session_id = await session_manager.create_session(uuid.uuid4())
+    session = await session_manager.get_session(session_id)
+    session.last_activity -= 50.0
+    stale_activity = session.last_activity
+
+    touched = await session_manager.touch_and_get(session_id)
+    assert touched is session
+    assert touched.last_activity > stale_activity
+
+    with pytest.raises(SessionNotFoundError):
+        await session_manager.touch_and_get(uuid.uuid4())
+
+@pytest.mark.asyncio
+async def test_start_practice(session_manager, game_manager):
+    game_manager.settings.auto_spawn = False
+    session_id = await session_manager.create_session(uuid.uuid4())
+    game_id = await session_manager.start_practice(session_id)
+
+    session = await session_manager.get_session(session_id)
+    game = await game_manager.get_game(game_id)
+    assert session.game_id == game_id
+    assert game.running
+    assert list(game.players) == [session.user_id]
+    assert game.game_settings["max_players"] == 1
+    block_id = game.players[session.user_id].current_block_id
+    assert block_id in game.pieces
+
+    # В тренировке следующая фигура выдаётся даже при выключенном GAME_AUTO_SPAWN
+    for _ in range(game.grid.height):
+        await game_manager.move_block(game_id, session.user_id, 0, 1)
+    await game_manager.lock_block(game_id, block_id)
+    assert game.players[session.user_id].current_block_id not in (None, block_id)
+    await game.stop()
+
+    with pytest.raises(SessionNotFoundError):
+        await session_manager.start_practice(uuid.uuid4())
+
+@pytest.mark.asyncio
+async def test_reconnect_token_rotation(session_manager):
+    session_id = await session_manager.create_session(uuid.uuid4())
+    token = await session_manager.issue_reconnect_token(session_id)
+
+    session, rotated = await session_manager.reconnect(token)
+    assert session.id == session_id
+    assert rotated != token
+    with pytest.raises(InvalidTokenError):
+        await session_manager.reconnect(token)
+    session, _ = await session_manager.reconnect(rotated)
+    assert session.id == session_id
+
+@pytest.mark.asyncio
+async def test_reconnect_token_without_rotation_and_tampering(session_manager):
+    session_manager.settings.rotate_reconnect_tokens = False
+    session_id = await session_manager.create_session(uuid.uuid4())
+    token = await session_manager.issue_reconnect_token(session_id)
+    _, same = await session_manager.reconnect(token)
+    assert same == token
+
+    other_session = await session_manager.create_session(uuid.uuid4())
+    _, token_id, signature = token.split(".")
+    tampered = token[:-1] + ("1" if token[-1] == "0" else "0")
+    for forged in (f"{other_session}.{token_id}.{signature}", tampered, "garbage", None):
+        with pytest.raises(InvalidTokenError):
+            await session_manager.reconnect(forged)
@@ -0,0 +1,142 @@
+import pytest
+import uuid
+from ..src.game.tetromino import Grid, Tetromino, TetrominoType, rotation_offsets
+from ..src.game.manager import GameManager
+
+EXPECTED_OFFSETS = {
+    TetrominoType.I: [
+        [(0, 1), (1, 1), (2, 1), (3, 1)],
+        [(2, 0), (2, 1), (2, 2), (2, 3)],
+        [(0, 2), (1, 2), (2, 2), (3, 2)],
+        [(1, 0), (1, 1), (1, 2), (1, 3)],
+    ],
+    TetrominoType.J: [
+        [(0, 0), (0, 1), (1, 1), (2, 1)],
+        [(1, 0), (1, 1), (1, 2), (2, 0)],
+        [(0, 1), (1, 1), (2, 1), (2, 2)],
+        [(0, 2), (1, 0), (1, 1), (1, 2)],
+    ],
+    TetrominoType.L: [
+        [(0, 1), (1, 1), (2, 0), (2, 1)],
+        [(1, 0), (1, 1), (1, 2), (2, 2)],
+        [(0, 1), (0, 2), (1, 1), (2, 1)],
+        [(0, 0), (1, 0), (1, 1), (1, 2)],
+    ],
+    TetrominoType.O: [
+        [(0, 0), (0, 1), (1, 0), (1, 1)],
+        [(0, 0), (0, 1), (1, 0), (1, 1)],
+        [(0, 0), (0, 1), (1, 0), (1, 1)],
+        [(0, 0), (0, 1), (1, 0), (1, 1)],
+    ],
+    TetrominoType.S: [
+        [(0, 1), (1, 0), (1, 1), (2, 0)],
+        [(1, 0), (1, 1), (2, 1), (2, 2)],
+        [(0, 2), (1, 1), (1, 2), (2, 1)],
+        [(0, 0), (0, 1), (1, 1), (1, 2)],
+    ],
+    TetrominoType.T: [
+        [(0, 1), (1, 0), (1, 1), (2, 1)],
+        [(1, 0), (1, 1), (1, 2), (2, 1)],
+        [(0, 1), (1, 1), (1, 2), (2, 1)],
+        [(0, 1), (1, 0), (1, 1), (1, 2)],
+    ],
+    TetrominoType.Z: [
+        [(0, 0), (1, 0), (1, 1), (2, 1)],
+        [(1, 1), (1, 2), (2, 0), (2, 1)],
+        [(0, 1), (1, 1), (1, 2), (2, 2)],
+        [(0, 1), (0, 2), (1, 0), (1, 1)],
+    ],
+}
+
+def test_rotation_offsets_for_every_shape():
+    for tetromino_type, states in EXPECTED_OFFSETS.items():
+        for rotation, expected in enumerate(states):
+            assert rotation_offsets(tetromino_type, rotation) == expected, (tetromino_type, rotation)
+
+def test_four_rotations_return_to_spawn_state():
+    for tetromino_type in TetrominoType:
+        assert rotation_offsets(tetromino_type, 4) == rotation_offsets(tetromino_type, 0)
+
+def test_rotation_uses_wall_kick_near_wall():
+    grid = Grid(10, 20)
+    piece = Tetromino(TetrominoType.I, x=-2, y=5, rotation=1)
+    assert grid.can_place(piece)
+    assert grid.try_rotate(piece, clockwise=False)
+    assert piece.rotation == 0
+    assert all(grid.is_free(cell) for cell in piece.cells())
+
+def test_rotation_fails_when_blocked():
+    grid = Grid(4, 4)
+    grid.occupied = {(x, y) for x in range(4) for y in range(4)} - {(0, 1), (1, 1), (2, 1), (3, 1)}
+    piece = Tetromino(TetrominoType.I, x=0, y=0)
+    assert not grid.try_rotate(piece)
+    assert piece.rotation == 0
+
+def test_clear_full_rows_shifts_cells_down():
+    grid = Grid(4, 4)
+    grid.occupied = {(x, 3) for x in range(4)} | {(1, 2)}
+    assert grid.clear_full_rows() == 1
+    assert grid.occupied == {(1, 3)}
+
+def test_sync_from_physics_snaps_to_cell_and_right_angle():
+    piece = Tetromino(TetrominoType.T)
+    piece.sync_from_physics((3.4, 7.6), 95.0)
+    assert (piece.x, piece.y, piece.rotation) == (3, 8, 1)
+
+@pytest.mark.asyncio
+async def test_game_lock_piece_clears_line():
+    game_manager = GameManager()
+    game_id = await game_manager.create_game()
+    game = await game_manager.get_game(game_id)
+    bottom = game.grid.height - 1
+    game.grid.occupied = {(x, bottom) for x in range(4, game.grid.width)}
+    block_id = uuid.uuid4()
+    await game.track_piece(block_id, Tetromino(TetrominoType.I))
+    await game.sync_piece(block_id, (0.0, bottom - 1.0), 0.0)
+    assert await game.lock_piece(block_id) == 1
+    assert game.grid.occupied == set()
+    assert block_id not in game.pieces
+
+def test_rows_completed_by():
+    grid = Grid(4, 4)
+    # Вертикальная I занимает столбец 2
+    piece = Tetromino(TetrominoType.I, x=0, y=0, rotation=1)
+    grid.occupied = {(0, 3), (3, 3)}
+    assert grid.rows_completed_by(piece) == []
+    grid.occupied = {(0, 3), (1, 3), (3, 3)}
+    assert grid.rows_completed_by(piece) == [3]
+
+def test_o_rotation_is_noop():
+    grid = Grid(10, 20)
+    piece = Tetromino(TetrominoType.O, x=4, y=0)
+    cells = piece.cells()
+    for clockwise in (True, False):
+        assert grid.try_rotate(piece, clockwise)
+        assert (piece.x, piece.y, piece.rotation) == (4, 0, 0)
+        assert piece.cells() == cells
+
+def test_i_s_z_rotation_toggles_between_two_states():
+    grid = Grid(10, 20)
+    for tetromino_type in (TetrominoType.I, TetrominoType.S, TetrominoType.Z):
+        piece = Tetromino(tetromino_type, x=3, y=5)
+        states = []
+        for _ in range(4):
+            assert grid.try_rotate(piece)
+            states.append(piece.rotation)
+        assert states == [1, 0, 1, 0], tetromino_type
+        assert grid.try_rotate(piece, clockwise=False)
+        assert piece.rotation == 1
+
+    piece = Tetromino(TetrominoType.I)
+    piece.sync_from_physics((0.0, 0.0), 270.0)
+    assert piece.rotation == 1
+
+def test_i_counterclockwise_rotation_uses_its_own_kicks_at_wall():
+    grid = Grid(10, 20)
+    # Горизонтальная I у левой стенки; столбец 2 для вертикального положения занят
+    piece = Tetromino(TetrominoType.I, x=0, y=5)
+    grid.occupied = {(2, 8)}
+    assert grid.try_rotate(piece, clockwise=False)
+    # Переход 0 -> L сначала пробует сдвиг на одну клетку влево, а не на две (как 0 -> R)
+    assert (piece.x, piece.y, piece.rotation) == (-1, 5, 1)
+    assert all(grid.is_free(cell) for cell in piece.cells())
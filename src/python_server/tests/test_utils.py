@@ -1,14 +1,29 @@
 import pytest
+import asyncio
 import uuid
+from ..src import utils
 from ..src.utils import (
+    TtlCache,
+    measure_time_async,
     validate_game_settings,
     parse_uuid,
     format_error,
     format_success,
     safe_json_loads,
-    safe_json_dumps
+    safe_json_dumps,
+    parse_duration,
+    parse_interval,
+    normalize_angle_degrees,
+    constant_time_eq,
+    MAX_PLAYERS_PER_GAME,
+    Validator,
+    ValidationError
 )
-from ..src.exceptions import GameError
+from common_utils import validation as common_validation
+from ..src.config import Settings
+from datetime import timedelta
+from ..src.exceptions import GameError, InvalidGameSettingsError
+from .conftest import FakeClock
 
 def test_validate_game_settings_valid():
     settings = {
@@ -96,4 +111,137 @@ def test_safe_json_dumps_invalid():
     
     data = {"key": Unserializable()}
     result = safe_json_dumps(data)
-    assert result is None 
\ No newline at end of file
+    assert result is None 
+
+def test_parse_duration_milliseconds():
+    assert parse_duration("500ms") == timedelta(milliseconds=500)
+
+def test_parse_duration_seconds():
+    assert parse_duration("30s") == timedelta(seconds=30)
+
+def test_parse_duration_minutes():
+    assert parse_duration("5m") == timedelta(minutes=5)
+
+def test_parse_duration_hours():
+    assert parse_duration("1.5h") == timedelta(minutes=90)
+
+def test_parse_duration_invalid():
+    for value in ["", "30", "5 minutes", "m5", "-1s", "1d"]:
+        with pytest.raises(ValueError):
+            parse_duration(value)
+
+def test_parse_interval_accepts_bare_seconds():
+    assert parse_interval("300") == 300.0
+    assert parse_interval(0.016) == 0.016
+    assert parse_interval("2m") == 120.0
+
+def test_settings_accept_human_durations():
+    settings = Settings(session_cleanup_interval="5m", session_heartbeat_interval="30s")
+    assert settings.session_cleanup_interval == 300.0
+    assert settings.session_heartbeat_interval == 30.0
+
+def test_normalize_angle_degrees():
+    assert normalize_angle_degrees(450.0) == 90.0
+    assert normalize_angle_degrees(-90.0) == 270.0
+    assert normalize_angle_degrees(360.0) == 0.0
+    assert normalize_angle_degrees(-1e-20) == 0.0
+
+def test_constant_time_eq():
+    assert constant_time_eq(b"secret-token", b"secret-token")
+    assert constant_time_eq("secret-token", b"secret-token")
+    assert not constant_time_eq("secret-token", "secret-tokeN")
+    assert not constant_time_eq("secret", "secret-token")
+    assert not constant_time_eq("", "x")
+    assert constant_time_eq("", "")
+
+def test_validator_keeps_multiple_errors_per_field():
+    validator = Validator()
+    validator.check(False, "name", "is required")
+    validator.check(False, "name", "is too short")
+    validator.check(True, "age", "must be positive")
+    assert not validator.is_valid()
+    assert validator.errors["name"] == [
+        ValidationError("name", "is required"),
+        ValidationError("name", "is too short"),
+    ]
+    assert "age" not in validator.errors
+    with pytest.raises(InvalidGameSettingsError) as exc_info:
+        validator.into_result(InvalidGameSettingsError)
+    assert len(exc_info.value.errors) == 2
+
+def test_validate_game_settings_reports_all_errors():
+    with pytest.raises(InvalidGameSettingsError) as exc_info:
+        validate_game_settings({
+            "game_type": "classic",
+            "max_players": 0,
+            "target_height": 500
+        })
+    fields = [error.field for error in exc_info.value.errors]
+    assert fields == ["difficulty", "max_players", "target_height"]
+    formatted = format_error(exc_info.value)
+    assert formatted["errors"][0] == {"field": "difficulty", "message": "required field is missing"}
+
+def test_validate_game_settings_bounds_max_players():
+    base = {"game_type": "classic", "difficulty": "medium"}
+    validate_game_settings({**base, "max_players": MAX_PLAYERS_PER_GAME})
+    for max_players in (True, MAX_PLAYERS_PER_GAME + 1, 2.0):
+        with pytest.raises(InvalidGameSettingsError):
+            validate_game_settings({**base, "max_players": max_players})
+
+def test_server_validator_is_the_shared_one():
+    assert Validator is common_validation.Validator
+    assert ValidationError is common_validation.ValidationError
+
+def test_cache_computes_only_on_miss():
+    clock = FakeClock()
+    cache = TtlCache(clock)
+    calls = []
+    compute = lambda: calls.append(1) or len(calls)
+
+    assert cache.get_or_insert_with("top", 10.0, compute) == 1
+    assert cache.get_or_insert_with("top", 10.0, compute) == 1
+    assert len(calls) == 1
+
+    clock.now += 10.0
+    assert cache.get_or_insert_with("top", 10.0, compute) == 2
+    cache.invalidate("top")
+    assert cache.get_or_insert_with("top", 10.0, compute) == 3
+    assert len(calls) == 3
+
+@pytest.mark.asyncio
+async def test_async_cache_computes_once_for_concurrent_misses():
+    cache = TtlCache()
+    calls = []
+
+    async def compute():
+        calls.append(1)
+        await asyncio.sleep(0.01)
+        return "value"
+
+    results = await asyncio.gather(*(cache.get_or_insert_with_async("key", 60.0, compute) for _ in range(5)))
+    assert results == ["value"] * 5
+    assert len(calls) == 1
+    assert cache.locks == {}
+
+class RecordingLogger:
+    def __init__(self):
+        self.warnings = []
+
+    def warning(self, message):
+        self.warnings.append(message)
+
+@pytest.mark.asyncio
+async def test_measure_time_async_warns_only_on_slow_operations(monkeypatch):
+    recorder = RecordingLogger()
+    monkeypatch.setattr(utils, "logger", recorder)
+
+    async def operation(delay):
+        await asyncio.sleep(delay)
+        return "done"
+
+    assert await measure_time_async("fast op", operation(0), 1.0) == "done"
+    assert recorder.warnings == []
+
+    assert await measure_time_async("slow op", operation(0.05), 0.01) == "done"
+    assert len(recorder.warnings) == 1
+    assert "slow op" in recorder.warnings[0]